@@ -37,6 +37,21 @@ impl Stmt {
             Stmt::Class(class_declaration) => visitor.visit_class(class_declaration),
         }
     }
+
+    /// Returns the line on which the statement starts, as far as its tokens tell. Blocks are made
+    /// of other statements and have no line of their own.
+    pub fn line(&self) -> Option<usize> {
+        match self {
+            Stmt::Expr(expr) | Stmt::Print(expr) => expr.line(),
+            Stmt::Var(var) => Some(var.identifier.line()),
+            Stmt::Block(_) => None,
+            Stmt::If(if_stmt) => if_stmt.condition.line(),
+            Stmt::While(while_stmt) => while_stmt.condition.line(),
+            Stmt::Function(func) => Some(func.name.line()),
+            Stmt::Return(return_stmt) => Some(return_stmt.keyword.line()),
+            Stmt::Class(class_declaration) => Some(class_declaration.name.line()),
+        }
+    }
 }
 
 #[derive(Clone)]
@@ -214,6 +229,27 @@ impl Expr {
             Expr::SuperExpr(super_expr) => visitor.visit_super(super_expr),
         }
     }
+
+    /// Returns the line of the leftmost token of the expression
+    pub fn line(&self) -> Option<usize> {
+        match self {
+            Expr::Unary(unary) => Some(unary.operator.line()),
+            Expr::Binary(binary) => binary.left.line(),
+            Expr::Group(group) => group.expr.line(),
+            // Literals added by the parser, like the condition of `for(;;)`, are nowhere in the
+            // source
+            Expr::Literal(literal) => Some(literal.line).filter(|line| *line != 0),
+            Expr::Ternary(ternary) => ternary.first.line(),
+            Expr::Var(token) | Expr::Assign(token, _) | Expr::ClassSelf(token) => {
+                Some(token.line())
+            }
+            Expr::Logical(logical) => logical.left.line(),
+            Expr::Call(call) => call.callee.line(),
+            Expr::Get(get_expr) => get_expr.object.line(),
+            Expr::Set(set_expr) => set_expr.object.line(),
+            Expr::SuperExpr(super_expr) => Some(super_expr.keyword.line()),
+        }
+    }
 }
 
 #[derive(Clone, PartialEq, Eq)]
@@ -315,6 +351,9 @@ impl Ternary {
 #[derive(Clone, PartialEq, Eq)]
 pub struct Literal {
     pub l_type: LiteralType,
+    // Line and column of the literal in the source, 0 when it was not written by the user
+    pub line: usize,
+    pub column: usize,
 }
 
 impl Literal {
@@ -334,13 +373,21 @@ impl Literal {
                 _ => Err(AstError::NotALiteral)?,
             }
         };
-        Ok(Self { l_type })
+        Ok(Self {
+            l_type,
+            line: token.line(),
+            column: token.column(),
+        })
     }
 }
 
 impl From<LiteralType> for Literal {
     fn from(l_type: LiteralType) -> Self {
-        Self { l_type }
+        Self {
+            l_type,
+            line: 0,
+            column: 0,
+        }
     }
 }
 
@@ -0,0 +1,11 @@
+use std::io;
+
+// Language server for Malis scripts, which editors start and talk to over the standard streams
+fn main() {
+    let stdin = io::stdin();
+    let stdout = io::stdout();
+    if let Err(err) = malis::lsp::serve(stdin.lock(), stdout.lock()) {
+        eprintln!("malis-lsp: {err}");
+        std::process::exit(1);
+    }
+}
@@ -1,20 +1,325 @@
-use malis::Malis;
+use malis::{interpreter::CountingAllocator, AstFormat, Malis, MalisError};
+use std::io::Read;
+
+// Lets the profiler count the allocations made by each function. Nothing is counted unless we
+// profile, so the other commands are not slowed down.
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+const USAGE: &str = "\
+Usage: malis [command] [options] [script] [arguments...]
+
+Commands:
+    run       Execute a script (default when a script is given)
+    repl      Start the interactive prompt (default when no script is given)
+    check     Scan, parse and resolve a script without executing it
+    tokens    Print the tokens scanned from a script
+    ast       Print the syntax tree parsed from a script
+    fmt       Format a script in place, or print it formatted when read from the standard input
+    debug     Execute a script under the debugger, reading its commands from the standard input
+    profile   Execute a script, then print how long each function took and write its call stacks
+    coverage  Execute a script, then print which lines and branches it covered and write an LCOV
+              report
+    dap       Serve the Debug Adapter Protocol for editors, over the standard streams by default
+
+Options:
+    -v, --verbose       Print intermediate results of the pipeline, repeat for more detail
+    -, --stdin          Read the script from the standard input
+    --format <format>   Format used by `ast`: `sexpr` (default) or `tree`
+    --check             With `fmt`, only check that the script is formatted, failing if it is not
+    -o, --output <path> With `profile`, where to write the call stacks for flame graph tools,
+                        `malis.folded` by default. With `coverage`, where to write the LCOV
+                        report, `lcov.info` by default
+    --port <port>       With `dap`, wait for the editor to connect to this port on the local host
+    -h, --help          Print this message
+    --                  Stop parsing options, everything that follows is passed to the script
+
+Everything after the script path is passed to the script as arguments.";
+
+// Where `profile` writes the call stacks, unless told otherwise
+const DEFAULT_STACKS: &str = "malis.folded";
+// Where `coverage` writes the LCOV report, unless told otherwise
+const DEFAULT_LCOV: &str = "lcov.info";
+// Exit code of `fmt --check` when the script is not formatted
+const EX_UNFORMATTED: i32 = 1;
+// Exit codes, following the BSD `sysexits.h` convention
+const EX_USAGE: i32 = 64;
+const EX_DATAERR: i32 = 65;
+const EX_SOFTWARE: i32 = 70;
+const EX_IOERR: i32 = 74;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Command {
+    Run,
+    Repl,
+    Check,
+    Tokens,
+    Ast,
+    Fmt,
+    Debug,
+    Profile,
+    Coverage,
+    Dap,
+    Help,
+}
+
+// Where the script to be used by a command comes from
+#[derive(Debug)]
+enum Input {
+    File(String),
+    Stdin,
+}
+
+// Command line arguments of the `malis` binary
+#[derive(Debug)]
+struct Cli {
+    command: Command,
+    input: Option<Input>,
+    verbosity: u8,
+    ast_format: AstFormat,
+    // Whether `fmt` only checks the formatting instead of applying it
+    check: bool,
+    // Where `profile` writes its output
+    output: Option<String>,
+    // Port on which `dap` listens, instead of using the standard streams
+    port: Option<u16>,
+    // Arguments passed to the script being executed
+    script_args: Vec<String>,
+}
+
+impl Cli {
+    fn parse<I: Iterator<Item = String>>(mut args: I) -> Result<Self, String> {
+        let mut command = None;
+        let mut input = None;
+        let mut verbosity = 0;
+        let mut ast_format = AstFormat::default();
+        let mut check = false;
+        let mut port = None;
+        let mut output = None;
+        let mut script_args = vec![];
+        let mut only_positional = false;
+
+        while let Some(arg) = args.next() {
+            // Once we know the script, everything else belongs to it
+            if input.is_some() {
+                script_args.push(arg);
+                continue;
+            }
+
+            match arg.as_str() {
+                "--" if !only_positional => only_positional = true,
+                "-h" | "--help" if !only_positional => command = Some(Command::Help),
+                "-v" | "--verbose" if !only_positional => verbosity += 1,
+                "-" | "--stdin" if !only_positional => input = Some(Input::Stdin),
+                "--check" if !only_positional => check = true,
+                "-o" | "--output" if !only_positional => match args.next() {
+                    Some(path) => output = Some(path),
+                    None => return Err(format!("missing value for `{arg}`")),
+                },
+                "--port" if !only_positional => {
+                    port = match args.next().map(|port| port.parse::<u16>()) {
+                        Some(Ok(port)) => Some(port),
+                        Some(Err(_)) => return Err("invalid value for `--port`".to_string()),
+                        None => return Err("missing value for `--port`".to_string()),
+                    }
+                }
+                "--format" if !only_positional => {
+                    ast_format = match args.next().as_deref() {
+                        Some("sexpr") => AstFormat::SExpr,
+                        Some("tree") => AstFormat::Tree,
+                        Some(format) => return Err(format!("unknown ast format `{format}`")),
+                        None => return Err("missing value for `--format`".to_string()),
+                    }
+                }
+                option if option.starts_with('-') && !only_positional => {
+                    // Short flags could be grouped, as in `-vv`
+                    if option.len() > 1 && option[1..].chars().all(|c| c == 'v') {
+                        verbosity += option.len() as u8 - 1;
+                    } else {
+                        return Err(format!("unknown option `{option}`"));
+                    }
+                }
+                // The command could only be the first positional argument
+                name if command.is_none() && !only_positional => {
+                    command = Some(match name {
+                        "run" => Command::Run,
+                        "repl" => Command::Repl,
+                        "check" => Command::Check,
+                        "tokens" => Command::Tokens,
+                        "ast" => Command::Ast,
+                        "fmt" => Command::Fmt,
+                        "debug" => Command::Debug,
+                        "profile" => Command::Profile,
+                        "coverage" => Command::Coverage,
+                        "dap" => Command::Dap,
+                        // For backwards compatibility, `malis script.ms` runs the script
+                        _ => {
+                            input = Some(Input::File(arg));
+                            Command::Run
+                        }
+                    });
+                }
+                _ => input = Some(Input::File(arg)),
+            }
+        }
+
+        // Without a command, we run the script if there is one or start the REPL otherwise
+        let command = command.unwrap_or(if input.is_some() {
+            Command::Run
+        } else {
+            Command::Repl
+        });
+
+        let needs_script = !matches!(command, Command::Repl | Command::Dap | Command::Help);
+        if needs_script && input.is_none() {
+            return Err("missing script path".to_string());
+        }
+        // The debugger reads its commands from the standard input
+        if command == Command::Debug && matches!(input, Some(Input::Stdin)) {
+            return Err("`debug` cannot read the script from the standard input".to_string());
+        }
+
+        Ok(Self {
+            command,
+            input,
+            verbosity,
+            ast_format,
+            check,
+            port,
+            output,
+            script_args,
+        })
+    }
+}
+
+fn read_source(input: &Input) -> Result<String, MalisError> {
+    match input {
+        Input::File(path) => Ok(std::fs::read_to_string(path)?),
+        Input::Stdin => {
+            let mut source = String::new();
+            std::io::stdin().read_to_string(&mut source)?;
+            Ok(source)
+        }
+    }
+}
+
+// Executes the command requested on the command line
+fn execute(cli: Cli) -> Result<(), MalisError> {
+    if cli.command == Command::Help {
+        println!("{USAGE}");
+        return Ok(());
+    }
+
+    // The editor tells the debug adapter which script to launch
+    if cli.command == Command::Dap {
+        return match cli.port {
+            Some(port) => malis::dap::listen(port),
+            None => malis::dap::serve(std::io::stdin().lock(), std::io::stdout()),
+        };
+    }
+
+    let mut malis = Malis::default();
+    malis.set_verbosity(cli.verbosity);
+    malis.set_args(cli.script_args);
+
+    let Some(input) = cli.input else {
+        return malis.interactive();
+    };
+    let source = read_source(&input)?;
+
+    match cli.command {
+        Command::Run => malis.run(source.as_str(), false),
+        Command::Repl => malis.interactive(),
+        Command::Check => Malis::check(source.as_str()),
+        Command::Tokens => {
+            println!("{}", Malis::tokens(source.as_str())?);
+            Ok(())
+        }
+        Command::Ast => {
+            println!("{}", Malis::ast(source.as_str(), cli.ast_format)?);
+            Ok(())
+        }
+        Command::Fmt => {
+            let formatted = Malis::format(source.as_str())?;
+            let is_formatted = formatted == source;
+            match input {
+                _ if cli.check => {
+                    if !is_formatted {
+                        let name = match &input {
+                            Input::File(path) => path.as_str(),
+                            Input::Stdin => "<stdin>",
+                        };
+                        eprintln!("malis: {name} is not formatted");
+                        std::process::exit(EX_UNFORMATTED);
+                    }
+                }
+                Input::File(path) if !is_formatted => std::fs::write(path, formatted)?,
+                Input::File(_) => {}
+                Input::Stdin => print!("{formatted}"),
+            }
+            Ok(())
+        }
+        Command::Debug => malis.debug(source.as_str(), std::io::stdin().lock()),
+        Command::Profile => {
+            malis.set_profiling(true);
+            // The profile is worth reporting even if the script fails
+            let result = malis.run(source.as_str(), false);
+            if let Some(profile) = malis.take_profile() {
+                eprint!("{}", profile.report());
+                let path = cli.output.as_deref().unwrap_or(DEFAULT_STACKS);
+                std::fs::write(path, profile.collapsed_stacks())?;
+                eprintln!("Call stacks written to {path}");
+            }
+            result
+        }
+        Command::Coverage => {
+            malis.set_coverage(true);
+            // Like the profile, the coverage is worth reporting even if the script fails
+            let result = malis.run(source.as_str(), false);
+            if let Some(coverage) = malis.take_coverage() {
+                let name = match &input {
+                    Input::File(path) => path.as_str(),
+                    Input::Stdin => "<stdin>",
+                };
+                eprintln!("{}", coverage.summary(name));
+                let path = cli.output.as_deref().unwrap_or(DEFAULT_LCOV);
+                std::fs::write(path, coverage.lcov(name))?;
+                eprintln!("LCOV report written to {path}");
+            }
+            result
+        }
+        Command::Dap | Command::Help => unreachable!(),
+    }
+}
+
+// Maps an error to the exit code of the process
+fn exit_code(err: &MalisError) -> i32 {
+    match err {
+        MalisError::StdIoError(_) => EX_IOERR,
+        MalisError::RuntimeError(_) => EX_SOFTWARE,
+        _ => EX_DATAERR,
+    }
+}
 
 fn main() {
     let mut args = std::env::args();
     // First arguments is always the current binary's path, which we do not need
     let _ = args.next();
 
-    match args.next() {
-        // If we do have a second argument, we execute it
-        Some(arg) => {
-            let execution = Malis::execute(&arg);
-            if let Err(e) = execution {
-                println!("{}", e);
-                std::process::exit(70);
-            }
+    let cli = match Cli::parse(args) {
+        Ok(cli) => cli,
+        Err(message) => {
+            eprintln!("malis: {message}\n\n{USAGE}");
+            std::process::exit(EX_USAGE);
         }
-        // If not, we enter interactive mode in the prompt
-        None => Malis::interactive().expect("Failed to execut script"),
     };
+
+    if let Err(e) = execute(cli) {
+        // Exiting through the `exit` native is not an error, so there is nothing to report
+        if let Some(status) = e.exit_status() {
+            std::process::exit(status);
+        }
+        eprintln!("{}", e);
+        std::process::exit(exit_code(&e));
+    }
 }
@@ -0,0 +1,713 @@
+//! Debug adapter for Malis scripts, talking the Debug Adapter Protocol over any pair of streams,
+//! usually the standard input and output of `malis dap` or a local TCP connection
+//!
+//! The adapter runs the script on the thread that reads the requests, so requests sent while the
+//! script is running are only answered once it pauses or finishes.
+use crate::{
+    error::{MalisError, RuntimeError},
+    interpreter::{DebugFrontend, Interpreter, MalisObject, PauseReason, Resume},
+    json::Json,
+    lsp::{read_message, write_message},
+    parser::Parser,
+    scanner::Scanner,
+    Malis,
+};
+use std::cell::RefCell;
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::net::TcpListener;
+use std::rc::Rc;
+
+// Malis only runs scripts on a single thread
+const THREAD_ID: usize = 1;
+// Exit code reported for scripts failing with an error, the same as the one of `malis run`
+const EX_SOFTWARE: i64 = 70;
+
+/// Serves a single debugging session over the given streams
+///
+/// # Errors
+///
+/// Fails if the streams could not be read from or written to, or if the input is not framed
+/// properly
+pub fn serve<R: BufRead + 'static, W: Write + 'static>(
+    input: R,
+    output: W,
+) -> Result<(), MalisError> {
+    let session = Rc::new(RefCell::new(Session {
+        input: Box::new(input),
+        output: Box::new(output),
+        seq: 0,
+    }));
+
+    // Everything the client asks for before the script starts
+    let mut launch = None;
+    let mut breakpoints = vec![];
+    loop {
+        let Some(request) = session.borrow_mut().read()? else {
+            return Ok(());
+        };
+        let command = request.command();
+        let arguments = request.arguments();
+        match command {
+            "initialize" => {
+                session.borrow_mut().respond(&request, Ok(capabilities()))?;
+                session
+                    .borrow_mut()
+                    .event("initialized", Json::object(NO_MEMBERS))?;
+            }
+            "launch" => {
+                let response = Launch::new(arguments).map(|launched| {
+                    launch = Some(launched);
+                    Json::Null
+                });
+                session.borrow_mut().respond(&request, response)?;
+            }
+            "setBreakpoints" => {
+                breakpoints = breakpoint_lines(arguments);
+                let response = Ok(verified_breakpoints(&breakpoints));
+                session.borrow_mut().respond(&request, response)?;
+            }
+            "threads" => {
+                session.borrow_mut().respond(&request, Ok(threads()))?;
+            }
+            "configurationDone" => {
+                let response = match &launch {
+                    Some(_) => Ok(Json::Null),
+                    None => Err("No program was launched".to_string()),
+                };
+                session.borrow_mut().respond(&request, response)?;
+                if let Some(launch) = launch.take() {
+                    run(&session, launch, std::mem::take(&mut breakpoints))?;
+                    // The client disconnects once it sees the script finished
+                }
+            }
+            "disconnect" | "terminate" => {
+                session.borrow_mut().respond(&request, Ok(Json::Null))?;
+                return Ok(());
+            }
+            _ => {
+                let response = Err(format!("`{command}` is not supported before launching"));
+                session.borrow_mut().respond(&request, response)?;
+            }
+        }
+    }
+}
+
+/// Waits for a client to connect to `port` on the local host and serves its debugging session
+///
+/// # Errors
+///
+/// Fails if the port could not be listened on or the connection fails
+pub fn listen(port: u16) -> Result<(), MalisError> {
+    let listener = TcpListener::bind(("127.0.0.1", port))?;
+    let (stream, _) = listener.accept()?;
+    serve(BufReader::new(stream.try_clone()?), BufWriter::new(stream))
+}
+
+// Runs the launched script until it finishes, answering requests whenever it pauses
+fn run(
+    session: &Rc<RefCell<Session>>,
+    launch: Launch,
+    breakpoints: Vec<usize>,
+) -> Result<(), MalisError> {
+    let mut malis = Malis::with_output(
+        EventOutput::new(session.clone(), "stdout"),
+        EventOutput::new(session.clone(), "stderr"),
+    )?;
+    malis.set_args(launch.args.clone());
+
+    let frontend = Adapter {
+        session: session.clone(),
+        program: launch.program.clone(),
+        stop_on_entry: launch.stop_on_entry,
+        breakpoints: Some(breakpoints),
+        references: vec![],
+        disconnected: false,
+    };
+    let result = malis.debug_with(&launch.source, frontend);
+    // Sends whatever the script wrote without ending its line
+    drop(malis);
+
+    let exit_code = match &result {
+        Ok(()) => 0,
+        Err(err) => match err.exit_status() {
+            Some(status) => i64::from(status),
+            None => {
+                let message = format!("{err}\n");
+                session.borrow_mut().event(
+                    "output",
+                    Json::object([
+                        ("category", Json::from("stderr")),
+                        ("output", Json::from(message)),
+                    ]),
+                )?;
+                EX_SOFTWARE
+            }
+        },
+    };
+    let mut session = session.borrow_mut();
+    session.event(
+        "exited",
+        Json::object([("exitCode", Json::from(exit_code))]),
+    )?;
+    session.event("terminated", Json::object(NO_MEMBERS))?;
+    Ok(())
+}
+
+// An empty JSON object
+const NO_MEMBERS: [(&str, Json); 0] = [];
+
+// The streams shared by the adapter and the output of the interpreter
+struct Session {
+    input: Box<dyn BufRead>,
+    output: Box<dyn Write>,
+    // Sequence number of the last message we sent
+    seq: usize,
+}
+
+impl Session {
+    // Reads the next request, skipping anything that is not one
+    fn read(&mut self) -> Result<Option<Json>, MalisError> {
+        while let Some(message) = read_message(&mut self.input)? {
+            match Json::parse(&message) {
+                Ok(message) if message.get("type").and_then(Json::as_str) == Some("request") => {
+                    return Ok(Some(message))
+                }
+                // Clients never answer us, as we send no requests
+                Ok(_) => {}
+                Err(err) => {
+                    self.event(
+                        "output",
+                        Json::object([
+                            ("category", Json::from("console")),
+                            ("output", Json::from(format!("Invalid message: {err}\n"))),
+                        ]),
+                    )?;
+                }
+            }
+        }
+        Ok(None)
+    }
+
+    // Sends a message, failing only if the output could not be written to, such that it works
+    // for both the adapter and the output of the script
+    fn send(&mut self, kind: &str, mut members: Vec<(&str, Json)>) -> io::Result<()> {
+        self.seq += 1;
+        members.splice(
+            0..0,
+            [("seq", Json::from(self.seq)), ("type", Json::from(kind))],
+        );
+        write_message(&mut self.output, &Json::object(members)).map_err(|err| match err {
+            MalisError::StdIoError(err) => err,
+            err => io::Error::other(err.to_string()),
+        })
+    }
+
+    fn respond(&mut self, request: &Json, result: Result<Json, String>) -> io::Result<()> {
+        let mut members = vec![
+            (
+                "request_seq",
+                request.get("seq").cloned().unwrap_or(Json::Null),
+            ),
+            ("command", Json::from(request.command())),
+        ];
+        match result {
+            Ok(body) => {
+                members.push(("success", Json::Bool(true)));
+                if body != Json::Null {
+                    members.push(("body", body));
+                }
+            }
+            Err(message) => {
+                members.push(("success", Json::Bool(false)));
+                members.push(("message", Json::from(message)));
+            }
+        }
+        self.send("response", members)
+    }
+
+    fn event(&mut self, event: &str, body: Json) -> io::Result<()> {
+        self.send("event", vec![("event", Json::from(event)), ("body", body)])
+    }
+}
+
+// Accessors for the members of requests
+trait Request {
+    fn command(&self) -> &str;
+    fn arguments(&self) -> &Json;
+}
+
+impl Request for Json {
+    fn command(&self) -> &str {
+        self.get("command")
+            .and_then(Json::as_str)
+            .unwrap_or_default()
+    }
+
+    fn arguments(&self) -> &Json {
+        self.get("arguments").unwrap_or(&Json::Null)
+    }
+}
+
+// The script that the client asked us to debug
+struct Launch {
+    program: String,
+    source: String,
+    args: Vec<String>,
+    stop_on_entry: bool,
+}
+
+impl Launch {
+    fn new(arguments: &Json) -> Result<Self, String> {
+        let program = arguments
+            .get("program")
+            .and_then(Json::as_str)
+            .ok_or("`launch` expects the path of the program")?;
+        let source = std::fs::read_to_string(program)
+            .map_err(|err| format!("Could not read `{program}`: {err}"))?;
+        let args = arguments
+            .get("args")
+            .and_then(Json::as_array)
+            .unwrap_or_default()
+            .iter()
+            .filter_map(Json::as_str)
+            .map(str::to_string)
+            .collect();
+        let stop_on_entry = arguments
+            .get("stopOnEntry")
+            .and_then(Json::as_bool)
+            .unwrap_or(false);
+        Ok(Self {
+            program: program.to_string(),
+            source,
+            args,
+            stop_on_entry,
+        })
+    }
+}
+
+// Sends what the script writes to the client as output events, one line at a time
+struct EventOutput {
+    session: Rc<RefCell<Session>>,
+    category: &'static str,
+    // What was written after the last complete line
+    pending: Vec<u8>,
+}
+
+impl EventOutput {
+    fn new(session: Rc<RefCell<Session>>, category: &'static str) -> Self {
+        Self {
+            session,
+            category,
+            pending: vec![],
+        }
+    }
+
+    fn send(&mut self, end: usize) -> io::Result<()> {
+        if end == 0 {
+            return Ok(());
+        }
+        let output = self.pending.drain(..end).collect::<Vec<_>>();
+        self.session.borrow_mut().event(
+            "output",
+            Json::object([
+                ("category", Json::from(self.category)),
+                (
+                    "output",
+                    Json::from(String::from_utf8_lossy(&output).as_ref()),
+                ),
+            ]),
+        )
+    }
+}
+
+impl Write for EventOutput {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.pending.extend_from_slice(buf);
+        let end = self
+            .pending
+            .iter()
+            .rposition(|byte| *byte == b'\n')
+            .map_or(0, |idx| idx + 1);
+        self.send(end)?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.send(self.pending.len())
+    }
+}
+
+impl Drop for EventOutput {
+    fn drop(&mut self) {
+        // Nothing could be done about a client that went away
+        let _ = self.flush();
+    }
+}
+
+// Debugger front end answering the requests of the client while the script is paused
+struct Adapter {
+    session: Rc<RefCell<Session>>,
+    program: String,
+    stop_on_entry: bool,
+    // Breakpoints set before the script started, applied on its first pause
+    breakpoints: Option<Vec<usize>>,
+    // Values whose members the client could ask for, where the reference of a value is its
+    // index plus one. Variables are only valid while paused, so the list is cleared on resume.
+    references: Vec<Vec<(String, MalisObject)>>,
+    // Set once the client went away, after which the script runs to completion
+    disconnected: bool,
+}
+
+impl Adapter {
+    // Returns a reference through which the members of `value` could be listed, or 0 if it has
+    // none
+    fn reference(&mut self, value: &MalisObject) -> usize {
+        let MalisObject::Instance(instance) = value else {
+            return 0;
+        };
+        let fields = instance
+            .fields()
+            .map(|(name, value)| (name.clone(), value.clone()))
+            .collect();
+        self.references.push(fields);
+        self.references.len()
+    }
+
+    fn variable(&mut self, name: &str, value: &MalisObject) -> Json {
+        Json::object([
+            ("name", Json::from(name)),
+            ("value", Json::from(value.to_string())),
+            ("type", Json::from(value.type_name())),
+            ("variablesReference", Json::from(self.reference(value))),
+        ])
+    }
+
+    // Answers a request made while paused. Returns how the script resumes, if the request asks
+    // for it.
+    fn handle(
+        &mut self,
+        interpreter: &mut Interpreter,
+        request: &Json,
+    ) -> Result<(Result<Json, String>, Option<Resume>), RuntimeError> {
+        let arguments = request.arguments();
+        let response = match request.command() {
+            "continue" => {
+                return Ok((
+                    Ok(Json::object([("allThreadsContinued", Json::Bool(true))])),
+                    Some(Resume::Continue),
+                ))
+            }
+            "next" => return Ok((Ok(Json::Null), Some(Resume::StepOver))),
+            "stepIn" => return Ok((Ok(Json::Null), Some(Resume::StepIn))),
+            "stepOut" => return Ok((Ok(Json::Null), Some(Resume::StepOut))),
+            "threads" => Ok(threads()),
+            "setBreakpoints" => {
+                let lines = breakpoint_lines(arguments);
+                for line in interpreter.breakpoints() {
+                    interpreter.remove_breakpoint(line);
+                }
+                for line in lines.iter() {
+                    interpreter.set_breakpoint(*line);
+                }
+                Ok(verified_breakpoints(&lines))
+            }
+            "stackTrace" => {
+                let frames = interpreter
+                    .call_stack()
+                    .iter()
+                    .rev()
+                    .enumerate()
+                    .map(|(id, frame)| {
+                        Json::object([
+                            ("id", Json::from(id)),
+                            ("name", Json::from(frame.name.as_str())),
+                            (
+                                "source",
+                                Json::object([("path", Json::from(self.program.as_str()))]),
+                            ),
+                            ("line", Json::from(frame.line)),
+                            ("column", Json::from(1usize)),
+                        ])
+                    })
+                    .collect::<Vec<_>>();
+                Ok(Json::object([
+                    ("totalFrames", Json::from(frames.len())),
+                    ("stackFrames", Json::Array(frames)),
+                ]))
+            }
+            "scopes" => {
+                // Only the environments of the innermost frame are around while paused, those
+                // of its callers are restored once it returns
+                let frame = arguments.get("frameId").and_then(Json::as_usize);
+                let scopes = match frame {
+                    Some(0) => interpreter.scopes(),
+                    _ => vec![],
+                };
+                let last = scopes.len().saturating_sub(1);
+                let scopes = scopes
+                    .into_iter()
+                    .enumerate()
+                    .map(|(idx, variables)| {
+                        let name = match idx {
+                            0 if last != 0 => "Locals",
+                            _ if idx == last => "Globals",
+                            _ => "Enclosing",
+                        };
+                        self.references.push(variables);
+                        Json::object([
+                            ("name", Json::from(name)),
+                            ("variablesReference", Json::from(self.references.len())),
+                            ("expensive", Json::Bool(false)),
+                        ])
+                    })
+                    .collect();
+                Ok(Json::object([("scopes", Json::Array(scopes))]))
+            }
+            "variables" => {
+                let variables = arguments
+                    .get("variablesReference")
+                    .and_then(Json::as_usize)
+                    .and_then(|reference| reference.checked_sub(1))
+                    .and_then(|idx| self.references.get(idx))
+                    .cloned()
+                    .unwrap_or_default();
+                let variables = variables
+                    .iter()
+                    // Natives are always there and not worth listing
+                    .filter(|(_, value)| !value.is_native())
+                    .map(|(name, value)| self.variable(name, value))
+                    .collect();
+                Ok(Json::object([("variables", Json::Array(variables))]))
+            }
+            "evaluate" => {
+                let expression = arguments
+                    .get("expression")
+                    .and_then(Json::as_str)
+                    .unwrap_or_default();
+                match evaluate(interpreter, expression) {
+                    Ok(value) => Ok(Json::object([
+                        ("result", Json::from(value.to_string())),
+                        ("type", Json::from(value.type_name())),
+                        ("variablesReference", Json::from(self.reference(&value))),
+                    ])),
+                    Err(err) => Err(err.to_string()),
+                }
+            }
+            "disconnect" | "terminate" => {
+                self.session.borrow_mut().respond(request, Ok(Json::Null))?;
+                return Err(RuntimeError::Terminated);
+            }
+            command => Err(format!("`{command}` is not supported")),
+        };
+        Ok((response, None))
+    }
+}
+
+impl DebugFrontend for Adapter {
+    fn paused(
+        &mut self,
+        interpreter: &mut Interpreter,
+        reason: PauseReason,
+        line: usize,
+    ) -> Result<Resume, RuntimeError> {
+        if self.disconnected {
+            return Ok(Resume::Continue);
+        }
+
+        let mut reason = match reason {
+            PauseReason::Entry => "entry",
+            PauseReason::Breakpoint => "breakpoint",
+            PauseReason::Step => "step",
+        };
+        if let Some(breakpoints) = self.breakpoints.take() {
+            for line in breakpoints {
+                interpreter.set_breakpoint(line);
+            }
+            // The first line could have a breakpoint of its own
+            if interpreter.breakpoints().contains(&line) {
+                reason = "breakpoint";
+            } else if !self.stop_on_entry {
+                return Ok(Resume::Continue);
+            }
+        }
+
+        self.session.borrow_mut().event(
+            "stopped",
+            Json::object([
+                ("reason", Json::from(reason)),
+                ("threadId", Json::from(THREAD_ID)),
+                ("allThreadsStopped", Json::Bool(true)),
+            ]),
+        )?;
+
+        loop {
+            let request = self.session.borrow_mut().read();
+            let request = match request {
+                Ok(Some(request)) => request,
+                // The client went away, so there is no one left to pause for
+                Ok(None) => {
+                    self.disconnected = true;
+                    return Ok(Resume::Continue);
+                }
+                Err(MalisError::StdIoError(err)) => return Err(RuntimeError::StdIoError(err)),
+                Err(_) => return Err(RuntimeError::Terminated),
+            };
+            // The session must not stay borrowed while evaluating, as the script could print
+            let (response, resume) = self.handle(interpreter, &request)?;
+            self.session.borrow_mut().respond(&request, response)?;
+            if let Some(resume) = resume {
+                self.references.clear();
+                return Ok(resume);
+            }
+        }
+    }
+}
+
+// Evaluates `expression` in the paused frame
+fn evaluate(interpreter: &mut Interpreter, expression: &str) -> Result<MalisObject, MalisError> {
+    let tokens = Scanner::new(expression).scan_tokens()?;
+    let expr = Parser::new(tokens).parse_expression()?;
+    Ok(interpreter.evaluate(&expr)?)
+}
+
+fn capabilities() -> Json {
+    Json::object([
+        ("supportsConfigurationDoneRequest", Json::Bool(true)),
+        ("supportsEvaluateForHovers", Json::Bool(true)),
+        ("supportsTerminateRequest", Json::Bool(true)),
+    ])
+}
+
+fn threads() -> Json {
+    Json::object([(
+        "threads",
+        Json::Array(vec![Json::object([
+            ("id", Json::from(THREAD_ID)),
+            ("name", Json::from("main")),
+        ])]),
+    )])
+}
+
+// Returns the lines of the breakpoints of a `setBreakpoints` request. We debug a single script,
+// so the source they are set in does not matter.
+fn breakpoint_lines(arguments: &Json) -> Vec<usize> {
+    arguments
+        .get("breakpoints")
+        .and_then(Json::as_array)
+        .unwrap_or_default()
+        .iter()
+        .filter_map(|breakpoint| breakpoint.get("line").and_then(Json::as_usize))
+        .collect()
+}
+
+fn verified_breakpoints(lines: &[usize]) -> Json {
+    let breakpoints = lines
+        .iter()
+        .map(|line| Json::object([("verified", Json::Bool(true)), ("line", Json::from(*line))]))
+        .collect();
+    Json::object([("breakpoints", Json::Array(breakpoints))])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::serve;
+    use crate::json::Json;
+    use crate::lsp::{read_message, write_message};
+    use crate::SharedBuffer;
+
+    #[test]
+    fn debugs_a_script() {
+        let program = std::env::temp_dir().join(format!("malis_dap_{}.ms", std::process::id()));
+        std::fs::write(
+            &program,
+            "class Tort {\n    gust() {\n        return 1;\n    }\n}\nvar t = Tort();\nt.aroma = \"vanilie\";\nprint t.gust();\n",
+        )
+        .expect("Failed to write the program");
+        let path = program.display().to_string().replace('\\', "\\\\");
+
+        let requests = [
+            r#"{"seq":1,"type":"request","command":"initialize","arguments":{"adapterID":"malis"}}"#.to_string(),
+            format!(r#"{{"seq":2,"type":"request","command":"launch","arguments":{{"program":"{path}"}}}}"#),
+            format!(r#"{{"seq":3,"type":"request","command":"setBreakpoints","arguments":{{"source":{{"path":"{path}"}},"breakpoints":[{{"line":3}}]}}}}"#),
+            r#"{"seq":4,"type":"request","command":"configurationDone"}"#.to_string(),
+            // Paused on line 3
+            r#"{"seq":5,"type":"request","command":"stackTrace","arguments":{"threadId":1}}"#.to_string(),
+            r#"{"seq":6,"type":"request","command":"scopes","arguments":{"frameId":0}}"#.to_string(),
+            r#"{"seq":7,"type":"request","command":"evaluate","arguments":{"expression":"self","frameId":0}}"#.to_string(),
+            r#"{"seq":8,"type":"request","command":"variables","arguments":{"variablesReference":5}}"#.to_string(),
+            r#"{"seq":9,"type":"request","command":"continue","arguments":{"threadId":1}}"#.to_string(),
+            r#"{"seq":10,"type":"request","command":"disconnect"}"#.to_string(),
+        ];
+        let mut input = vec![];
+        for request in requests.iter() {
+            let request = Json::parse(request).expect("Invalid request");
+            write_message(&mut input, &request).expect("Failed to frame request");
+        }
+
+        let output = SharedBuffer::new();
+        serve(std::io::Cursor::new(input), output.clone()).expect("Failed to serve");
+        let _ = std::fs::remove_file(program);
+
+        let output = output.contents();
+        let mut output = output.as_bytes();
+        let mut messages = vec![];
+        while let Some(message) = read_message(&mut output).expect("Invalid framing") {
+            let message = Json::parse(&message).expect("Invalid message");
+            // Responses and events are told apart by their command or event name
+            let name = message
+                .get("command")
+                .or(message.get("event"))
+                .and_then(Json::as_str)
+                .unwrap_or_default()
+                .to_string();
+            messages.push((name, message));
+        }
+        let names = messages
+            .iter()
+            .map(|(name, _)| name.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            names,
+            [
+                "initialize",
+                "initialized",
+                "launch",
+                "setBreakpoints",
+                "configurationDone",
+                "stopped",
+                "stackTrace",
+                "scopes",
+                "evaluate",
+                "variables",
+                "continue",
+                "output",
+                "exited",
+                "terminated",
+                "disconnect"
+            ]
+        );
+        let body = |idx: usize| messages[idx].1.get("body").expect("No body").to_string();
+
+        assert!(body(5).contains(r#""reason":"breakpoint""#));
+        let stack = body(6);
+        assert!(
+            stack.contains(r#""name":"gust","source":{"path":"#),
+            "{stack}"
+        );
+        assert!(stack.contains(r#""line":3"#) && stack.contains(r#""line":8"#));
+        assert!(body(7).contains(r#""name":"Locals""#));
+        assert!(
+            body(8).contains(
+                r#""result":"<class instance Tort>","type":"instance","variablesReference":5"#
+            ),
+            "{}",
+            body(8)
+        );
+        assert_eq!(
+            body(9),
+            r#"{"variables":[{"name":"aroma","value":"vanilie","type":"string","variablesReference":0}]}"#
+        );
+        assert_eq!(body(11), r#"{"category":"stdout","output":"1\n"}"#);
+        assert_eq!(body(12), r#"{"exitCode":0}"#);
+    }
+}
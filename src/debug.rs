@@ -0,0 +1,312 @@
+use crate::{
+    error::{MalisError, RuntimeError},
+    interpreter::{DebugFrontend, Interpreter, MalisObject, PauseReason, Resume},
+    parser::Parser,
+    scanner::Scanner,
+    Malis,
+};
+use std::io::BufRead;
+
+const HELP: &str = "\
+Commands:
+    b, break <line>     Pause before executing the given line
+    d, delete <line>    Remove the breakpoint on the given line
+    breakpoints         List the breakpoints
+    c, continue         Run until the next breakpoint
+    s, step             Execute the current line, pausing inside the functions it calls
+    n, next             Execute the current line, stepping over the functions it calls
+    f, finish           Run until the current function returns
+    p, print <expr>     Evaluate an expression in the paused frame and print its value
+    env                 List the variables visible from the paused frame, scope by scope
+    self                Print the fields of the instance whose method is paused
+    bt, backtrace       Print the functions being executed
+    l, list             Print the source around the paused line
+    q, quit             Stop the script
+    h, help             Print this message";
+
+// Number of lines printed by `list` before and after the paused one
+const LIST_CONTEXT: usize = 3;
+
+/// Debugger front end reading commands from an input, usually the terminal, and writing what it
+/// finds to the output of the interpreter
+pub struct DebugConsole<R> {
+    input: R,
+    // Lines of the script being debugged, for showing where we paused
+    lines: Vec<String>,
+    // Set once the input ends, after which the script runs to completion
+    detached: bool,
+}
+
+impl<R: BufRead> DebugConsole<R> {
+    pub fn new(input: R, source: &str) -> Self {
+        Self {
+            input,
+            lines: source.lines().map(str::to_string).collect(),
+            detached: false,
+        }
+    }
+
+    fn source_line(&self, line: usize) -> &str {
+        line.checked_sub(1)
+            .and_then(|idx| self.lines.get(idx))
+            .map_or("", |text| text.trim())
+    }
+
+    // Executes a command which does not resume the execution, writing its result to `out`
+    fn command(
+        &mut self,
+        interpreter: &mut Interpreter,
+        line: usize,
+        name: &str,
+        argument: &str,
+        out: &mut String,
+    ) -> Result<(), MalisError> {
+        let line_argument = || {
+            argument
+                .parse::<usize>()
+                .map_err(|_| MalisError::InvalidCommand(format!("`{name}` expects a line number")))
+        };
+
+        match name {
+            "b" | "break" => {
+                let line = line_argument()?;
+                interpreter.set_breakpoint(line);
+                out.push_str(&format!("Breakpoint set on line {line}\n"));
+            }
+            "d" | "delete" => {
+                let line = line_argument()?;
+                if interpreter.remove_breakpoint(line) {
+                    out.push_str(&format!("Breakpoint removed from line {line}\n"));
+                } else {
+                    out.push_str(&format!("No breakpoint on line {line}\n"));
+                }
+            }
+            "breakpoints" => {
+                let breakpoints = interpreter.breakpoints();
+                if breakpoints.is_empty() {
+                    out.push_str("No breakpoints\n");
+                }
+                for line in breakpoints {
+                    out.push_str(&format!("line {line}: {}\n", self.source_line(line)));
+                }
+            }
+            "p" | "print" => {
+                if argument.is_empty() {
+                    return Err(MalisError::InvalidCommand(
+                        "`print` expects an expression".to_string(),
+                    ));
+                }
+                let tokens = Scanner::new(argument).scan_tokens()?;
+                let expr = Parser::new(tokens).parse_expression()?;
+                let value = interpreter.evaluate(&expr)?;
+                out.push_str(&format!("{value}\n"));
+            }
+            "env" => {
+                let scopes = interpreter.scopes();
+                let last = scopes.len().saturating_sub(1);
+                for (idx, scope) in scopes.into_iter().enumerate() {
+                    out.push_str(if idx == last {
+                        "globals:\n"
+                    } else {
+                        "scope:\n"
+                    });
+                    for (name, value) in scope {
+                        // Natives are always there and not worth listing
+                        if !value.is_native() {
+                            out.push_str(&format!("    {name} = {value}\n"));
+                        }
+                    }
+                }
+            }
+            "self" => {
+                let instance = interpreter
+                    .scopes()
+                    .into_iter()
+                    .flatten()
+                    .find(|(name, _)| name == "self");
+                match instance {
+                    Some((_, MalisObject::Instance(instance))) => {
+                        out.push_str(&format!("instance of {}\n", instance.name()));
+                        for (name, value) in instance.fields() {
+                            out.push_str(&format!("    {name} = {value}\n"));
+                        }
+                    }
+                    _ => out.push_str("Not paused in a method\n"),
+                }
+            }
+            "bt" | "backtrace" => {
+                for (idx, frame) in interpreter.call_stack().iter().rev().enumerate() {
+                    out.push_str(&format!("#{idx} {} at line {}\n", frame.name, frame.line));
+                }
+            }
+            "l" | "list" => {
+                let first = line.saturating_sub(LIST_CONTEXT).max(1);
+                let last = (line + LIST_CONTEXT).min(self.lines.len());
+                for current in first..=last {
+                    let marker = if current == line { "->" } else { "  " };
+                    out.push_str(&format!(
+                        "{marker} {current:>3} {}\n",
+                        self.lines[current - 1]
+                    ));
+                }
+            }
+            "h" | "help" => {
+                out.push_str(HELP);
+                out.push('\n');
+            }
+            _ => {
+                return Err(MalisError::InvalidCommand(format!(
+                    "Unknown command `{name}`, use `help` to list the available ones"
+                )))
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<R: BufRead> DebugFrontend for DebugConsole<R> {
+    fn paused(
+        &mut self,
+        interpreter: &mut Interpreter,
+        reason: PauseReason,
+        line: usize,
+    ) -> Result<Resume, RuntimeError> {
+        if self.detached {
+            return Ok(Resume::Continue);
+        }
+
+        let why = match reason {
+            PauseReason::Entry => "Paused at the start of the script",
+            PauseReason::Breakpoint => "Paused at breakpoint",
+            PauseReason::Step => "Paused",
+        };
+        writeln!(
+            interpreter.diagnostics(),
+            "{why}, line {line}: {}",
+            self.source_line(line)
+        )?;
+
+        loop {
+            write!(interpreter.diagnostics(), "(debug) ")?;
+            interpreter.diagnostics().flush()?;
+            let mut command = String::new();
+            if self.input.read_line(&mut command)? == 0 {
+                // Without anyone to ask, we let the script run to completion
+                self.detached = true;
+                writeln!(interpreter.diagnostics())?;
+                return Ok(Resume::Continue);
+            }
+
+            let command = command.trim();
+            let (name, argument) = command
+                .split_once(char::is_whitespace)
+                .map_or((command, ""), |(name, argument)| (name, argument.trim()));
+            match name {
+                // An empty line repeats nothing, we just ask again
+                "" => continue,
+                "c" | "continue" => return Ok(Resume::Continue),
+                "s" | "step" => return Ok(Resume::StepIn),
+                "n" | "next" => return Ok(Resume::StepOver),
+                "f" | "finish" => return Ok(Resume::StepOut),
+                "q" | "quit" => return Err(RuntimeError::Terminated),
+                _ => {
+                    let mut out = String::new();
+                    match self.command(interpreter, line, name, argument, &mut out) {
+                        Ok(()) => write!(interpreter.diagnostics(), "{out}")?,
+                        Err(err) => writeln!(interpreter.diagnostics(), "Error: {err}")?,
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl Malis {
+    /// Executes `source` under the debugger, reading its commands from `commands`. The script
+    /// pauses before its first statement.
+    pub fn debug<R: BufRead + 'static>(
+        &mut self,
+        source: &str,
+        commands: R,
+    ) -> Result<(), MalisError> {
+        self.debug_with(source, DebugConsole::new(commands, source))
+    }
+
+    /// Executes `source` under the debugger, pausing whenever `frontend` asks to
+    pub fn debug_with<F: DebugFrontend + 'static>(
+        &mut self,
+        source: &str,
+        frontend: F,
+    ) -> Result<(), MalisError> {
+        self.interpreter.set_debugger(frontend);
+        let result = self.run(source, false);
+        self.interpreter.clear_debugger();
+        match result {
+            // Stopping the script from the debugger is not an error
+            Err(MalisError::RuntimeError(RuntimeError::Terminated)) => Ok(()),
+            result => result,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Malis, SharedBuffer};
+
+    #[test]
+    fn steps_through_a_script() {
+        let source = "\
+fun suma(a, b) {
+    var total = a + b;
+    return total;
+}
+var x = 1;
+var y = suma(x, 2);
+print y;
+";
+        let commands = "\
+next
+next
+step
+break 3
+continue
+print total
+env
+bt
+finish
+print y
+continue
+";
+        let output = SharedBuffer::new();
+        let diagnostics = SharedBuffer::new();
+        let mut malis =
+            Malis::with_output(output.clone(), diagnostics.clone()).expect("Failed to create");
+        malis
+            .debug(source, commands.as_bytes())
+            .expect("Failed to debug");
+
+        let expected = "\
+Paused at the start of the script, line 1: fun suma(a, b) {
+(debug) Paused, line 5: var x = 1;
+(debug) Paused, line 6: var y = suma(x, 2);
+(debug) Paused, line 2: var total = a + b;
+(debug) Breakpoint set on line 3
+(debug) Paused at breakpoint, line 3: return total;
+(debug) 3
+(debug) scope:
+    total = 3
+scope:
+    a = 1
+    b = 2
+globals:
+    suma = <fn suma:1>
+(debug) #0 suma at line 3
+#1 <script> at line 6
+(debug) Paused, line 7: print y;
+(debug) 3
+(debug) ";
+        assert_eq!(diagnostics.contents(), expected);
+        // The script prints to its own output, without the debugger in between
+        assert_eq!(output.contents(), "3\n");
+    }
+}
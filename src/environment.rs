@@ -83,6 +83,14 @@ impl Environment {
         Err(EnvironmentError::UndefinedVariable(name.to_string()))
     }
 
+    /// Runs `f` on the outermost environment of the chain starting at this one
+    pub fn with_root<T>(&mut self, f: impl FnOnce(&mut Environment) -> T) -> T {
+        match self.enclosing.clone() {
+            Some(enclosing) => enclosing.borrow_mut().with_root(f),
+            None => f(self),
+        }
+    }
+
     pub fn insert_at(
         &mut self,
         distance: usize,
@@ -7,11 +7,21 @@ use std::fmt;
 pub enum MalisError {
     StdIoError(std::io::Error),
     ScannerError(ScannerError),
+    // All the errors found while scanning a source
+    ScannerErrors(Vec<ScannerError>),
     NoneTokenType,
     AstError(AstError),
     ParserError(ParserError),
     ResolverError(ResolverError),
     RuntimeError(RuntimeError),
+    // Failure of the line editor used by the interactive prompt
+    LineEditor(rustyline::error::ReadlineError),
+    // A command of the interactive prompt that is unknown or was given wrong arguments
+    InvalidCommand(String),
+    // The formatter produced code which does not mean the same thing as the original
+    FormatterError(String),
+    // A client of the language server sent something we could not make sense of
+    ProtocolError(String),
 }
 
 impl fmt::Display for MalisError {
@@ -19,23 +29,62 @@ impl fmt::Display for MalisError {
         match self {
             MalisError::ParserError(e) => write!(f, "{e}"),
             MalisError::RuntimeError(e) => write!(f, "{e}"),
+            MalisError::ResolverError(e) => write!(f, "{e}"),
+            MalisError::LineEditor(e) => write!(f, "{e}"),
+            MalisError::InvalidCommand(message)
+            | MalisError::FormatterError(message)
+            | MalisError::ProtocolError(message) => {
+                write!(f, "{message}")
+            }
+            MalisError::ScannerErrors(errors) => {
+                for (idx, err) in errors.iter().enumerate() {
+                    if idx != 0 {
+                        writeln!(f)?;
+                    }
+                    write!(f, "{err}")?;
+                }
+                Ok(())
+            }
             _ => write!(f, "{self:?}"),
         }
     }
 }
 
+impl MalisError {
+    /// Returns the status the script requested the process to exit with, through the `exit`
+    /// native, if any
+    pub fn exit_status(&self) -> Option<i32> {
+        match self {
+            MalisError::RuntimeError(RuntimeError::Exit(code)) => Some(*code),
+            _ => None,
+        }
+    }
+}
+
 impl From<std::io::Error> for MalisError {
     fn from(err: std::io::Error) -> Self {
         Self::StdIoError(err)
     }
 }
 
+impl From<rustyline::error::ReadlineError> for MalisError {
+    fn from(err: rustyline::error::ReadlineError) -> Self {
+        Self::LineEditor(err)
+    }
+}
+
 impl From<ScannerError> for MalisError {
     fn from(err: ScannerError) -> Self {
         Self::ScannerError(err)
     }
 }
 
+impl From<Vec<ScannerError>> for MalisError {
+    fn from(err: Vec<ScannerError>) -> Self {
+        Self::ScannerErrors(err)
+    }
+}
+
 impl From<AstError> for MalisError {
     fn from(err: AstError) -> Self {
         Self::AstError(err)
@@ -71,8 +120,24 @@ pub enum ScannerError {
     FailedToIndexSlice,
     StdIoError(std::io::Error),
     ParseFloatError(core::num::ParseFloatError),
-    UnexpectedCharacter(char),
-    UnterminatedString,
+    // The unexpected character and the line it was found on
+    UnexpectedCharacter(char, usize),
+    // The line on which the unterminated string ends
+    UnterminatedString(usize),
+}
+
+impl fmt::Display for ScannerError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> Result<(), fmt::Error> {
+        match self {
+            ScannerError::UnexpectedCharacter(ch, line) => {
+                write!(f, "[line {line}] Error: Unexpected character {ch:?}")
+            }
+            ScannerError::UnterminatedString(line) => {
+                write!(f, "[line {line}] Error: Unterminated string")
+            }
+            _ => write!(f, "{:?}", self),
+        }
+    }
 }
 
 impl From<std::io::Error> for ScannerError {
@@ -87,22 +152,6 @@ impl From<core::num::ParseFloatError> for ScannerError {
     }
 }
 
-pub struct SourceError<P: fmt::Debug> {
-    pub line: usize,
-    pub location: usize,
-    pub err: P,
-}
-
-impl<P: fmt::Debug> fmt::Debug for SourceError<P> {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> Result<(), fmt::Error> {
-        writeln!(
-            f,
-            "[line {0}] Error {1:?}: {2:?}",
-            self.line, self.location, self.err
-        )
-    }
-}
-
 #[derive(Debug)]
 pub enum AstError {
     NotALiteral,
@@ -120,6 +169,8 @@ pub enum ParserError {
     TooManyFuncArg,
     PanicMode(String, Token),
     InvalidIfStmt(String),
+    // All the errors found while parsing a source, in the order they were found
+    Multiple(Vec<ParserError>),
 }
 
 impl fmt::Display for ParserError {
@@ -134,6 +185,15 @@ impl fmt::Display for ParserError {
                     message
                 )
             }
+            ParserError::Multiple(errors) => {
+                for (idx, err) in errors.iter().enumerate() {
+                    if idx != 0 {
+                        writeln!(f)?;
+                    }
+                    write!(f, "{err}")?;
+                }
+                Ok(())
+            }
             _ => write!(f, "{:?}", self),
         }
     }
@@ -149,6 +209,34 @@ pub enum ResolverError {
     InvalidSelfUse(String),
     InvalidSuperUse(String),
     SelfInheritance(String),
+    // Any of the errors above, along with the token it was found at
+    Located(Box<ResolverError>, Token),
+}
+
+impl ResolverError {
+    /// Returns the token at which the error was found, if it is known
+    pub fn token(&self) -> Option<&Token> {
+        match self {
+            ResolverError::Located(_, token) => Some(token),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for ResolverError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> Result<(), fmt::Error> {
+        match self {
+            ResolverError::NotInitialized(message)
+            | ResolverError::DoubleInitialise(message)
+            | ResolverError::ReturnOutsideFunction(message)
+            | ResolverError::UnusedName(message)
+            | ResolverError::InvalidSelfUse(message)
+            | ResolverError::InvalidSuperUse(message)
+            | ResolverError::SelfInheritance(message) => write!(f, "{}", message),
+            ResolverError::EnvironmentError(env) => write!(f, "{:?}", env),
+            ResolverError::Located(err, _) => write!(f, "{}", err),
+        }
+    }
 }
 
 impl From<EnvironmentError> for ResolverError {
@@ -168,12 +256,33 @@ pub enum RuntimeError {
     BinaryEvaluation(String),
     VariableNotInitialized(String),
     InvalidArgumentsNumber(String),
+    InvalidArgument(String),
+    // A value did not have the type expected by the Rust code converting it
+    TypeMismatch(String),
+    // A value had the expected type but could not be converted, like `num("abc")`
+    InvalidConversion(String),
     NotCallable(String),
     EnvironmentError(EnvironmentError),
     SystemTimeError(std::time::SystemTimeError),
+    StdIoError(std::io::Error),
+    // A script handed text which is not valid JSON to `json.parse`
+    JsonError(JsonError),
     // This is used in conjunction with the `return` statement from `Malis` to return early from
     // a function.
     Return(MalisObject),
+    // Used by the `exit` native to stop the execution of the script and request the process to
+    // exit with the given status
+    Exit(i32),
+    // The debugger stopped the script before it finished
+    Terminated,
+    // The script executed more statements than its limit allows
+    StepLimitExceeded(u64),
+    // The script was still running when its deadline passed
+    DeadlineExceeded,
+    // The script nested more calls than its limit allows
+    CallDepthExceeded(usize),
+    // The host cancelled the script through its cancellation handle
+    Cancelled,
     ResolverError(ResolverError),
     InvalidAccess(String),
     PropertyNotPresent(String),
@@ -196,6 +305,18 @@ impl From<std::time::SystemTimeError> for RuntimeError {
     }
 }
 
+impl From<std::io::Error> for RuntimeError {
+    fn from(value: std::io::Error) -> Self {
+        Self::StdIoError(value)
+    }
+}
+
+impl From<JsonError> for RuntimeError {
+    fn from(value: JsonError) -> Self {
+        Self::JsonError(value)
+    }
+}
+
 impl fmt::Display for RuntimeError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> Result<(), fmt::Error> {
         match self {
@@ -207,9 +328,45 @@ impl fmt::Display for RuntimeError {
             | RuntimeError::UnaryEvaluation(message)
             | RuntimeError::BinaryEvaluation(message)
             | RuntimeError::InvalidArgumentsNumber(message)
-            | RuntimeError::NotCallable(message) => write!(f, "{}", message),
+            | RuntimeError::InvalidArgument(message)
+            | RuntimeError::TypeMismatch(message)
+            | RuntimeError::InvalidConversion(message)
+            | RuntimeError::NotCallable(message)
+            | RuntimeError::InvalidAccess(message)
+            | RuntimeError::PropertyNotPresent(message)
+            | RuntimeError::InvalidClassInit(message)
+            | RuntimeError::InvalidSuperclass(message)
+            | RuntimeError::InvalidSuperReference(message) => write!(f, "{}", message),
             RuntimeError::EnvironmentError(env) => write!(f, "{:?}", env),
+            RuntimeError::ResolverError(err) => write!(f, "{}", err),
+            RuntimeError::JsonError(err) => write!(f, "Invalid JSON: {err}"),
+            RuntimeError::StepLimitExceeded(steps) => {
+                write!(f, "The script exceeded its limit of {steps} steps")
+            }
+            RuntimeError::DeadlineExceeded => write!(f, "The script ran past its deadline"),
+            RuntimeError::CallDepthExceeded(depth) => {
+                write!(f, "The script exceeded its limit of {depth} nested calls")
+            }
+            RuntimeError::Cancelled => write!(f, "The script was cancelled"),
             _ => write!(f, "{:?}", self),
         }
     }
 }
+
+/// Reason for which a JSON document could not be parsed and where in the document it happened
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct JsonError {
+    pub message: String,
+    pub line: usize,
+    pub column: usize,
+}
+
+impl fmt::Display for JsonError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> Result<(), fmt::Error> {
+        write!(
+            f,
+            "{} at line {}, column {}",
+            self.message, self.line, self.column
+        )
+    }
+}
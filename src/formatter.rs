@@ -0,0 +1,445 @@
+//! Formats Malis source code into its canonical style
+//!
+//! The formatter works on the tokens of the source instead of on the syntax tree, such that
+//! nothing written by the user is lost: comments are kept and syntactic sugar, like `for` loops,
+//! is not replaced by what the parser desugars it into. The source still has to parse, which
+//! lets us walk the tokens knowing that they follow the grammar.
+use crate::token::{Keyword, SingleChar, Token, TokenType};
+
+// What a single level of indentation looks like
+const INDENT: &str = "    ";
+
+pub struct Formatter {
+    tokens: Vec<Token>,
+    current: usize,
+    // The formatted source
+    out: String,
+    // Number of indentation levels of the line being written
+    indent: usize,
+    // Whether nothing was written yet on the current line
+    at_line_start: bool,
+    // Last token written, deciding the spacing of the next one
+    previous: Option<Token>,
+    // Whether the previous token is a unary operator, which sticks to its operand
+    previous_unary: bool,
+    // Line in the source on which the last token or comment written ends
+    last_line: usize,
+}
+
+impl Formatter {
+    // Creates a new formatter over `tokens`, which has to include the comments of the source
+    pub fn new(tokens: Vec<Token>) -> Self {
+        Self {
+            tokens,
+            current: 0,
+            out: String::new(),
+            indent: 0,
+            at_line_start: true,
+            previous: None,
+            previous_unary: false,
+            last_line: 1,
+        }
+    }
+
+    /// Returns the formatted source
+    pub fn format(mut self) -> String {
+        while !self.at_end() {
+            self.declaration();
+        }
+        // Comments could follow the last declaration
+        self.comments();
+        self.newline();
+        self.out
+    }
+
+    // Formats a declaration or a statement, which always gets its own line
+    fn declaration(&mut self) {
+        self.statement_start();
+        if self.at_end() {
+            return;
+        }
+
+        match self.peek_type() {
+            TokenType::Keyword(Keyword::Class) => self.class_declaration(),
+            TokenType::Keyword(Keyword::Fun) => {
+                self.token();
+                self.function();
+            }
+            TokenType::Keyword(Keyword::If) => self.if_statement(),
+            TokenType::Keyword(Keyword::While | Keyword::For) => {
+                self.token();
+                self.parenthesized();
+                self.body();
+            }
+            TokenType::SingleChar(SingleChar::LeftBrace) => self.block(),
+            // Everything else is a simple statement, ending with a `;`
+            _ => self.until_semicolon(),
+        }
+        self.newline();
+    }
+
+    fn class_declaration(&mut self) {
+        // The `class` keyword, the name and the optional superclass
+        while !self.at_end() && !self.check(SingleChar::LeftBrace) {
+            self.token();
+        }
+        self.token();
+
+        if self.check(SingleChar::RightBrace) && !self.comment_before_current() {
+            self.token();
+            return;
+        }
+
+        self.indent += 1;
+        self.newline();
+        while !self.at_end() && !self.check(SingleChar::RightBrace) {
+            self.statement_start();
+            if self.check(SingleChar::RightBrace) {
+                break;
+            }
+            self.function();
+            self.newline();
+        }
+        self.close_block();
+    }
+
+    // Formats the name, parameters and body of a function or method
+    fn function(&mut self) {
+        while !self.at_end() && !self.check(SingleChar::LeftParen) {
+            self.token();
+        }
+        self.parenthesized();
+        self.block();
+    }
+
+    fn if_statement(&mut self) {
+        self.token();
+        self.parenthesized();
+        let had_block = self.check(SingleChar::LeftBrace);
+        self.body();
+
+        if self.peek_type() != &TokenType::Keyword(Keyword::Else) {
+            return;
+        }
+        // A single statement body already moved us to the next line
+        if !had_block {
+            self.newline();
+        }
+        self.token();
+        match self.peek_type() {
+            // `else if` chains stay on the same line
+            TokenType::Keyword(Keyword::If) => self.if_statement(),
+            _ => self.body(),
+        }
+    }
+
+    // Formats the body of a control flow statement. Blocks start on the same line, while single
+    // statements go on their own indented line.
+    fn body(&mut self) {
+        if self.check(SingleChar::LeftBrace) {
+            self.block();
+        } else {
+            self.indent += 1;
+            self.newline();
+            self.declaration();
+            self.indent -= 1;
+        }
+    }
+
+    fn block(&mut self) {
+        // The `{`
+        self.token();
+        if self.check(SingleChar::RightBrace) && !self.comment_before_current() {
+            self.token();
+            return;
+        }
+
+        self.indent += 1;
+        self.newline();
+        while !self.at_end() && !self.check(SingleChar::RightBrace) {
+            self.declaration();
+        }
+        self.close_block();
+    }
+
+    // Writes the `}` ending a block, along with the comments preceding it
+    fn close_block(&mut self) {
+        self.comments();
+        self.indent -= 1;
+        self.newline();
+        self.token();
+    }
+
+    // Writes everything up to and including the parenthesis matching the current one
+    fn parenthesized(&mut self) {
+        let mut depth = 0;
+        while !self.at_end() {
+            match self.peek_type() {
+                TokenType::SingleChar(SingleChar::LeftParen) => depth += 1,
+                TokenType::SingleChar(SingleChar::RightParen) => depth -= 1,
+                _ => {}
+            }
+            self.token();
+            if depth == 0 {
+                break;
+            }
+        }
+    }
+
+    // Writes everything up to and including the `;` which ends a simple statement
+    fn until_semicolon(&mut self) {
+        while !self.at_end() {
+            let is_semicolon = self.check(SingleChar::SemiColon);
+            self.token();
+            if is_semicolon {
+                break;
+            }
+        }
+    }
+
+    // Prepares the start of a statement: writes the comments preceding it and keeps a single
+    // blank line if the source had any before it
+    fn statement_start(&mut self) {
+        self.comments();
+        if self.at_end() {
+            return;
+        }
+        let start = start_line(&self.tokens[self.current]);
+        if start > self.last_line + 1 {
+            self.blank_line();
+        }
+    }
+
+    // Writes the comments found before the current token. Comments on the same line as the
+    // previous token stay at the end of that line, while the others get their own lines.
+    fn comments(&mut self) {
+        while let Some(comment) = self
+            .tokens
+            .get(self.current)
+            .filter(|token| token.t_type() == &TokenType::Comment)
+            .cloned()
+        {
+            self.current += 1;
+            let start = start_line(&comment);
+
+            if start == self.last_line && !self.out.is_empty() && !self.at_line_start {
+                self.out.push(' ');
+            } else {
+                self.newline();
+                if start > self.last_line + 1 {
+                    self.blank_line();
+                }
+                self.write_indent();
+            }
+            self.out.push_str(comment.lexeme());
+            self.at_line_start = false;
+            self.last_line = comment.line();
+
+            // Nothing could follow a line comment on its line, while code following a block
+            // comment stays on its line only if it was like that in the source
+            let next_line = self.tokens.get(self.current).map(start_line);
+            if comment.lexeme().starts_with("//") || next_line > Some(comment.line()) {
+                self.newline();
+            }
+        }
+    }
+
+    // Writes the comments which follow the last token on its line
+    fn trailing_comments(&mut self) {
+        while let Some(comment) = self
+            .tokens
+            .get(self.current)
+            .filter(|token| token.t_type() == &TokenType::Comment)
+            .filter(|token| start_line(token) == self.last_line)
+            .cloned()
+        {
+            self.current += 1;
+            self.out.push(' ');
+            self.out.push_str(comment.lexeme());
+            self.last_line = comment.line();
+        }
+    }
+
+    // Writes the current token, preceded by the comments before it, and moves past it
+    fn token(&mut self) {
+        self.comments();
+        let Some(token) = self.tokens.get(self.current).cloned() else {
+            return;
+        };
+        if token.t_type() == &TokenType::Eof {
+            return;
+        }
+        self.current += 1;
+
+        if self.at_line_start {
+            self.write_indent();
+        } else if self.needs_space(&token) {
+            self.out.push(' ');
+        }
+        self.out.push_str(token.lexeme());
+        self.at_line_start = false;
+        self.last_line = token.line();
+
+        self.previous_unary = match token.t_type() {
+            TokenType::SingleChar(SingleChar::Bang) => true,
+            // A minus is unary when it does not follow an operand
+            TokenType::SingleChar(SingleChar::Minus) => {
+                !self.previous.as_ref().is_some_and(ends_operand)
+            }
+            _ => false,
+        };
+        self.previous = Some(token);
+    }
+
+    // Decides whether `token` is separated by a space from the previous one on the same line
+    fn needs_space(&self, token: &Token) -> bool {
+        let Some(previous) = self.previous.as_ref() else {
+            return false;
+        };
+        if self.previous_unary {
+            return false;
+        }
+
+        match (previous.t_type(), token.t_type()) {
+            (
+                _,
+                TokenType::SingleChar(
+                    SingleChar::RightParen
+                    | SingleChar::Comma
+                    | SingleChar::SemiColon
+                    | SingleChar::Dot,
+                ),
+            ) => false,
+            (TokenType::SingleChar(SingleChar::LeftParen | SingleChar::Dot), _) => false,
+            // Empty blocks are written as `{}`
+            (
+                TokenType::SingleChar(SingleChar::LeftBrace),
+                TokenType::SingleChar(SingleChar::RightBrace),
+            ) => false,
+            // Calls stick to what they call, while keywords are followed by a space
+            (_, TokenType::SingleChar(SingleChar::LeftParen)) => !ends_operand(previous),
+            _ => true,
+        }
+    }
+
+    // Ends the current line, if anything was written on it
+    fn newline(&mut self) {
+        if !self.at_line_start {
+            self.trailing_comments();
+            let trimmed = self.out.trim_end_matches(' ').len();
+            self.out.truncate(trimmed);
+            self.out.push('\n');
+            self.at_line_start = true;
+        }
+    }
+
+    // Adds an empty line, unless we are at the start of the source or of a block
+    fn blank_line(&mut self) {
+        self.newline();
+        if self.out.is_empty() || self.out.ends_with("\n\n") || self.out.ends_with("{\n") {
+            return;
+        }
+        self.out.push('\n');
+    }
+
+    fn write_indent(&mut self) {
+        for _ in 0..self.indent {
+            self.out.push_str(INDENT);
+        }
+    }
+
+    // Returns the type of the first token that is not a comment, starting with the current one
+    fn peek_type(&self) -> &TokenType {
+        self.tokens[self.current..]
+            .iter()
+            .map(Token::t_type)
+            .find(|t_type| *t_type != &TokenType::Comment)
+            .unwrap_or(&TokenType::Eof)
+    }
+
+    fn check(&self, single_char: SingleChar) -> bool {
+        self.peek_type() == &TokenType::SingleChar(single_char)
+    }
+
+    // Returns whether there is a comment between the previous token and the next one
+    fn comment_before_current(&self) -> bool {
+        self.tokens
+            .get(self.current)
+            .is_some_and(|token| token.t_type() == &TokenType::Comment)
+    }
+
+    fn at_end(&self) -> bool {
+        self.peek_type() == &TokenType::Eof
+    }
+}
+
+// Returns whether `token` could be the last token of an operand, such that an operator following
+// it is binary and a parenthesis following it is a call
+fn ends_operand(token: &Token) -> bool {
+    matches!(
+        token.t_type(),
+        TokenType::Ident
+            | TokenType::Literal(_)
+            | TokenType::SingleChar(SingleChar::RightParen)
+            | TokenType::Keyword(
+                Keyword::True | Keyword::False | Keyword::Nil | Keyword::ClassSelf | Keyword::Super
+            )
+    )
+}
+
+// Returns the line on which `token` starts, as tokens only know the line they end on
+fn start_line(token: &Token) -> usize {
+    token.line() - token.lexeme().matches('\n').count()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::Malis;
+
+    #[test]
+    fn formats_scripts() {
+        let source = r#"// Salutare
+var a=1;  var b = -a*(2+3) ;// la final
+
+
+fun  suma(x,y){return x+y;}
+class Tort<Prajitura{ init(aroma){print aroma;}
+  /* metoda */
+  gust() {}
+}
+for(var i=0;i<3;i=i+1) print suma(i, !true);
+if (a) print a; else if (b) { print b; } else
+  print "nimic";
+"#;
+        let expected = r#"// Salutare
+var a = 1;
+var b = -a * (2 + 3); // la final
+
+fun suma(x, y) {
+    return x + y;
+}
+class Tort < Prajitura {
+    init(aroma) {
+        print aroma;
+    }
+    /* metoda */
+    gust() {}
+}
+for (var i = 0; i < 3; i = i + 1)
+    print suma(i, !true);
+if (a)
+    print a;
+else if (b) {
+    print b;
+} else
+    print "nimic";
+"#;
+        let formatted = Malis::format(source).expect("Failed to format");
+        assert_eq!(formatted, expected);
+        // Formatting twice does not change anything
+        assert_eq!(
+            Malis::format(&formatted).expect("Failed to format"),
+            expected
+        );
+    }
+}
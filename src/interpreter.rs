@@ -1,30 +1,81 @@
+pub mod callable;
+pub mod convert;
+pub mod coverage;
+pub mod debugger;
 pub mod function;
+pub mod host;
+pub mod limits;
+pub mod list;
 pub mod malis_class;
+pub mod natives;
 pub mod object;
+pub mod observer;
+pub mod output;
+pub mod profiler;
+#[cfg(feature = "serde")]
+pub mod serialize;
 pub mod visit;
 
 use crate::{
     ast::{Expr, Stmt},
-    environment::Environment,
+    environment::{Environment, EnvironmentError},
     error::{ResolverError, RuntimeError},
     token::Token,
 };
-pub use function::{MalisCallable, NativeFunction, UserFunction};
+pub use callable::Callable;
+pub use convert::{FromMalis, IntoMalis, TypedNative};
+pub use coverage::Coverage;
+use debugger::Debugger;
+pub use debugger::{DebugFrontend, Frame, PauseReason, Resume};
+pub use function::{Arity, MalisCallable, NativeFn, NativeFunction, UserFunction};
+pub use host::{Host, HostClass, HostObject};
+use limits::Budget;
+pub use limits::{CancellationHandle, Limits};
+pub use list::List;
 pub use malis_class::{MalisClass, MalisInstance};
+pub use natives::NativeModule;
 pub use object::MalisObject;
+pub use observer::Observer;
+pub use output::{LineCallback, SharedBuffer};
+use profiler::Profiler;
+pub use profiler::{CountingAllocator, FunctionProfile, Profile};
 use std::cell::RefCell;
 use std::collections::HashMap;
+use std::io::{self, Write};
 use std::rc::Rc;
 
 pub struct Interpreter {
     // This is the global environment that is accessible at all times
-    _globals: Rc<RefCell<Environment>>,
+    globals: Rc<RefCell<Environment>>,
     // This is the current local environment that the interepreter executes in
     environment: Rc<RefCell<Environment>>,
     // Stores resolution information about variables and how many scopes we have to traverse
     // between the current scope (the one in which the variable is accessed) and the resolution
     // scope (the one that contains the value for the variable)
     locals: HashMap<String, usize>,
+    // Environments put aside by the calls in progress, the outermost first. A call runs on a copy
+    // of the environment its function was declared in, so the globals themselves are only
+    // reachable from the environment put aside by the outermost call.
+    callers: Vec<Rc<RefCell<Environment>>>,
+    // Command line arguments passed to the script, accessible through the `args` native
+    args: Vec<String>,
+    // Destination of the values printed by the `print` statement
+    output: Box<dyn Write>,
+    // Destination of errors and debugging information
+    diagnostics: Box<dyn Write>,
+    // Pauses the execution when asked to, only set while debugging
+    debugger: Option<Debugger>,
+    // Measures the calls made, only set while profiling
+    profiler: Option<Profiler>,
+    // Records the statements and branches executed, only set while measuring coverage
+    coverage: Option<Coverage>,
+    // Notified of everything executed, only set by embedders watching the execution
+    observer: Option<Box<dyn Observer>>,
+    // Bounds the work done by scripts, only set when limits or cancellation were asked for
+    budget: Option<Budget>,
+    // Runs and calls started by the host which did not finish yet. Natives could call back into
+    // the interpreter while a script runs, so there could be more than one.
+    entered: usize,
 }
 
 impl Default for Interpreter {
@@ -45,37 +96,181 @@ impl Default for Interpreter {
 
 impl Interpreter {
     pub fn new() -> Result<Self, RuntimeError> {
+        Self::with_output(io::stdout(), io::stderr())
+    }
+
+    /// Creates an interpreter which writes the values printed by scripts to `output` and errors
+    /// and debugging information to `diagnostics`
+    pub fn with_output<O: Write + 'static, D: Write + 'static>(
+        output: O,
+        diagnostics: D,
+    ) -> Result<Self, RuntimeError> {
         // Define a new environment
         let globals = Rc::new(RefCell::new(Environment::new(None)));
         let environment = globals.clone();
 
-        // Create a new native function
-        let clock = MalisObject::NativeFunction(Box::new(NativeFunction::new(
-            "clock <native fn>".to_string(),
-            0,
-            |_interpreter, _arguments| {
-                Ok(MalisObject::Number(
-                    std::time::SystemTime::now()
-                        .duration_since(std::time::UNIX_EPOCH)?
-                        .as_secs_f32(),
-                ))
-            },
-        )));
-
-        globals.borrow_mut().define("clock".to_string(), clock)?;
-
-        Ok(Self {
-            _globals: globals,
+        let mut interpreter = Self {
+            globals,
             environment,
             locals: HashMap::new(),
-        })
+            callers: vec![],
+            args: vec![],
+            output: Box::new(output),
+            diagnostics: Box::new(diagnostics),
+            debugger: None,
+            profiler: None,
+            coverage: None,
+            observer: None,
+            budget: None,
+            entered: 0,
+        };
+        interpreter.install(natives::core())?;
+        Ok(interpreter)
+    }
+
+    /// Drops every global, local and resolution defined so far, starting over with only the
+    /// natives. The output, diagnostics and script arguments are kept.
+    pub fn reset(&mut self) -> Result<(), RuntimeError> {
+        let fresh = Self::with_output(io::sink(), io::sink())?;
+        self.globals = fresh.globals;
+        self.environment = fresh.environment;
+        self.locals = fresh.locals;
+        self.callers = fresh.callers;
+        Ok(())
+    }
+
+    pub fn set_output<O: Write + 'static>(&mut self, output: O) {
+        self.output = Box::new(output);
+    }
+
+    pub fn set_diagnostics<D: Write + 'static>(&mut self, diagnostics: D) {
+        self.diagnostics = Box::new(diagnostics);
+    }
+
+    pub fn output(&mut self) -> &mut dyn Write {
+        self.output.as_mut()
+    }
+
+    pub fn diagnostics(&mut self) -> &mut dyn Write {
+        self.diagnostics.as_mut()
+    }
+
+    /// Sets the command line arguments that are passed to the script
+    pub fn set_args(&mut self, args: Vec<String>) {
+        self.args = args;
+    }
+
+    pub fn args(&self) -> &[String] {
+        &self.args
+    }
+
+    // Runs `f` on the global environment, which is the root of the current chain outside of calls
+    // and the root of the chain put aside by the outermost call otherwise
+    pub(crate) fn with_globals<T>(&self, f: impl FnOnce(&mut Environment) -> T) -> T {
+        self.callers
+            .first()
+            .unwrap_or(&self.globals)
+            .borrow_mut()
+            .with_root(f)
+    }
+
+    /// Returns the names defined in the global environment
+    pub fn global_names(&self) -> Vec<String> {
+        self.with_globals(|globals| globals.values.keys().cloned().collect())
+    }
+
+    /// Returns the value of the global named `name`, if there is one
+    pub fn get_global(&self, name: &str) -> Option<MalisObject> {
+        self.with_globals(|globals| globals.values.get(name).cloned())
     }
 
     pub fn interpret(&mut self, statements: &[Stmt]) -> Result<(), RuntimeError> {
-        for stmt in statements.iter() {
-            self.execute(stmt)?;
+        self.interpret_value(statements).map(|_| ())
+    }
+
+    /// Executes `statements` like `interpret`, returning the value of the last one when it is an
+    /// expression, or `nil` otherwise
+    pub fn interpret_value(&mut self, statements: &[Stmt]) -> Result<MalisObject, RuntimeError> {
+        self.enter(|interpreter| {
+            interpreter.register_coverage(statements);
+            let mut value = MalisObject::Nil;
+            for stmt in statements.iter() {
+                value = match stmt {
+                    // Executing an expression statement is evaluating it, so we keep the value
+                    Stmt::Expr(expr) => interpreter
+                        .before_statement(stmt)
+                        .and_then(|_| interpreter.evaluate(expr))?,
+                    _ => interpreter.execute(stmt).map(|_| MalisObject::Nil)?,
+                };
+            }
+            Ok(value)
+        })
+    }
+
+    // Runs `run` on behalf of the host, like executing a script or calling a function. It gets the
+    // whole budget to itself, and the observer hears about an error once, when it gets back to
+    // the host.
+    fn enter<T, F>(&mut self, run: F) -> Result<T, RuntimeError>
+    where
+        F: FnOnce(&mut Self) -> Result<T, RuntimeError>,
+    {
+        if self.entered == 0 {
+            self.renew_budget();
+        }
+        self.entered += 1;
+        let result = run(self);
+        self.entered -= 1;
+        if let (Err(err), 0, Some(observer)) = (&result, self.entered, self.observer.as_mut()) {
+            // Returning and exiting are how scripts finish, not errors
+            if !matches!(err, RuntimeError::Return(_) | RuntimeError::Exit(_)) {
+                observer.runtime_error(err);
+            }
+        }
+        result
+    }
+
+    /// Defines the global `name`, replacing its value if it was already defined
+    pub fn set_global(&mut self, name: &str, value: MalisObject) {
+        self.with_globals(|globals| globals.values.insert(name.to_string(), value));
+    }
+
+    /// Calls `callee`, which could be a function, a method, a native or a class, with `arguments`
+    pub fn call(
+        &mut self,
+        callee: &MalisObject,
+        arguments: Vec<MalisObject>,
+    ) -> Result<MalisObject, RuntimeError> {
+        self.enter(|interpreter| {
+            if !callee.is_callable() {
+                return Err(RuntimeError::NotCallable(format!(
+                    "Object {} is not callable.",
+                    callee
+                )));
+            }
+            let arity = callee.arity()?;
+            if !arity.accepts(arguments.len()) {
+                return Err(RuntimeError::InvalidArgumentsNumber(format!(
+                    "Expected {} arguments but got {}.",
+                    arity,
+                    arguments.len()
+                )));
+            }
+            callee.call(interpreter, arguments)
+        })
+    }
+
+    /// Calls the global function, native or class named `name` with `arguments`
+    pub fn call_function(
+        &mut self,
+        name: &str,
+        arguments: Vec<MalisObject>,
+    ) -> Result<MalisObject, RuntimeError> {
+        match self.get_global(name) {
+            Some(callee) => self.call(&callee, arguments),
+            None => {
+                self.enter(|_| Err(EnvironmentError::UndefinedVariable(name.to_string()).into()))
+            }
         }
-        Ok(())
     }
 
     pub fn resolve(&mut self, expr: String, scope_level: usize) -> Result<(), ResolverError> {
@@ -93,7 +288,7 @@ impl Interpreter {
                 .get_at(*distance, var.lexeme())?
                 .clone()
         } else {
-            self._globals.borrow().get(var.lexeme())?.clone()
+            self.globals.borrow().get(var.lexeme())?.clone()
         };
         Ok(object)
     }
@@ -103,9 +298,27 @@ impl Interpreter {
     }
 
     pub fn execute(&mut self, stmt: &Stmt) -> Result<(), RuntimeError> {
+        self.before_statement(stmt)?;
         stmt.walk(self)
     }
 
+    // Lets everyone watching the execution know that `stmt` is about to be executed
+    fn before_statement(&mut self, stmt: &Stmt) -> Result<(), RuntimeError> {
+        if self.budget.is_some() {
+            self.spend_step()?;
+        }
+        if self.debugger.is_some() {
+            self.debug_statement(stmt)?;
+        }
+        if self.coverage.is_some() {
+            self.cover_statement(stmt);
+        }
+        if let Some(observer) = self.observer.as_mut() {
+            observer.statement(stmt);
+        }
+        Ok(())
+    }
+
     pub fn execute_block(
         &mut self,
         stmts: &[Stmt],
@@ -114,6 +327,10 @@ impl Interpreter {
         // Executing a block requires creating a new environment, executing within that environment
         // and restoring the environment to its previous state
 
+        // Blocks are given the environment they are entered from, while calls are given the one of
+        // the function
+        let is_call = !Rc::ptr_eq(&parent_env, &self.environment);
+
         // To prevent creating a cycle, we must take the value out of the parent environment.
         // Afterwards, we wrap it in a `Rc` as it is required in order to share it. We also wrap it
         // in a `RefCell` such that we obtain mutable state
@@ -127,29 +344,21 @@ impl Interpreter {
         let previous_env = self
             .environment
             .replace(Environment::new(Some(parent_env_rc.clone())));
+        // The environment of the caller is kept where the host could still reach the globals
+        // through it, while the call runs
+        let previous_env = if is_call {
+            self.callers.push(Rc::new(RefCell::new(previous_env)));
+            None
+        } else {
+            Some(previous_env)
+        };
 
-        // Start executing statements
+        // Start executing statements. If one of them fails, we cannot return the error just yet.
+        let mut result = Ok(());
         for stmt in stmts.iter() {
-            // Execute statement
-            let stmt_exec = self.execute(stmt);
-
-            // If the statement is an error, we cannot return it just yet
-            if stmt_exec.is_err() {
-                // We must reverse the scope created above and replace the executing scope with
-                // the scope we have before entering the block.
-                // Order of operations is important. Replacing the current execution environment
-                // first assures that there is not any other strong reference to the previous
-                // environment
-                self.environment.replace(previous_env);
-
-                // We also replace the parent environment with the initial environment we passed
-                // when entering the scope
-                parent_env.replace(
-                    Rc::into_inner(parent_env_rc)
-                        .ok_or(RuntimeError::MultipleReferenceForEnclosingEnvironment)?
-                        .into_inner(),
-                );
-                return stmt_exec;
+            result = self.execute(stmt);
+            if result.is_err() {
+                break;
             }
         }
 
@@ -158,6 +367,15 @@ impl Interpreter {
         // Order of operations is important. Replacing the current execution environment
         // first assures that there is not any other strong reference to the previous
         // environment
+        let previous_env = match previous_env {
+            Some(previous_env) => previous_env,
+            None => self
+                .callers
+                .pop()
+                .and_then(Rc::into_inner)
+                .ok_or(RuntimeError::MultipleReferenceForEnclosingEnvironment)?
+                .into_inner(),
+        };
         self.environment.replace(previous_env);
         // We also replace the parent environment with the initial environment we passed
         // when entering the scope
@@ -167,6 +385,6 @@ impl Interpreter {
                 .into_inner(),
         );
 
-        Ok(())
+        result
     }
 }
@@ -0,0 +1,158 @@
+use super::{
+    Arity, FromMalis, Interpreter, IntoMalis, MalisCallable, MalisInstance, MalisObject,
+    RuntimeError,
+};
+use std::fmt;
+
+/// Function, method, native or class that Rust code could keep and call later, for example an
+/// event handler registered by a script. Methods stay bound to their instance.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Callable(MalisObject);
+
+impl Callable {
+    /// Wraps `value`, failing if it could not be called
+    pub fn new(value: MalisObject) -> Result<Self, RuntimeError> {
+        if value.is_callable() {
+            Ok(Self(value))
+        } else {
+            Err(RuntimeError::NotCallable(format!(
+                "Object {value} is not callable."
+            )))
+        }
+    }
+
+    /// Returns the method `name` of `instance`, bound to it
+    pub fn method(instance: &MalisInstance, name: &str) -> Result<Self, RuntimeError> {
+        Self::new(instance.property(name)?)
+    }
+
+    pub fn arity(&self) -> Result<Arity, RuntimeError> {
+        self.0.arity()
+    }
+
+    /// Calls the wrapped value with `arguments`, after checking there are as many as it accepts
+    pub fn call(
+        &self,
+        interpreter: &mut Interpreter,
+        arguments: Vec<MalisObject>,
+    ) -> Result<MalisObject, RuntimeError> {
+        interpreter.call(&self.0, arguments)
+    }
+
+    pub fn value(&self) -> &MalisObject {
+        &self.0
+    }
+}
+
+impl fmt::Display for Callable {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> Result<(), fmt::Error> {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl From<Callable> for MalisObject {
+    fn from(callable: Callable) -> Self {
+        callable.0
+    }
+}
+
+impl TryFrom<MalisObject> for Callable {
+    type Error = RuntimeError;
+
+    fn try_from(value: MalisObject) -> Result<Self, Self::Error> {
+        Self::new(value)
+    }
+}
+
+// Lets typed natives return callbacks
+impl IntoMalis for Callable {
+    fn into_malis(self) -> MalisObject {
+        self.0
+    }
+}
+
+// Lets typed natives take callbacks as arguments
+impl FromMalis for Callable {
+    fn from_malis(value: MalisObject) -> Result<Self, RuntimeError> {
+        match value {
+            value if value.is_callable() => Ok(Self(value)),
+            value => Err(RuntimeError::TypeMismatch(format!(
+                "Expected a function, got {value} of type {}",
+                value.type_name()
+            ))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Callable;
+    use crate::{
+        interpreter::{MalisObject, NativeModule},
+        Malis, MalisError, RuntimeError, SharedBuffer,
+    };
+    use std::{cell::RefCell, rc::Rc};
+
+    #[test]
+    fn calls_handlers_registered_by_scripts() {
+        let handlers = Rc::new(RefCell::new(vec![]));
+        let module = NativeModule::new()
+            .typed("on", {
+                let handlers = handlers.clone();
+                move |handler: Callable| {
+                    handlers.borrow_mut().push(handler);
+                    Ok(())
+                }
+            })
+            .typed("acelasi", |handler: Callable| Ok(handler));
+        let output = SharedBuffer::new();
+        let mut malis =
+            Malis::with_output(output.clone(), SharedBuffer::new()).expect("Failed to create");
+        malis.install(module).expect("Failed to install");
+        malis
+            .run(
+                "\
+fun salut(nume) { print \"salut \" + nume; }
+class Buton {
+    apasa(nume) { return \"apasat de \" + nume; }
+}
+var buton = Buton();
+on(salut);
+on(buton.apasa);
+acelasi(salut)(\"Ion\");
+",
+                false,
+            )
+            .expect("Failed to run");
+
+        let handlers = handlers.borrow().clone();
+        assert_eq!(handlers.len(), 2);
+        let ana = MalisObject::StringValue("Ana".to_string());
+        assert_eq!(
+            malis
+                .call(&handlers[0], vec![ana.clone()])
+                .expect("Failed to call"),
+            MalisObject::Nil
+        );
+        assert_eq!(
+            malis.call(&handlers[1], vec![ana]).expect("Failed to call"),
+            MalisObject::StringValue("apasat de Ana".to_string())
+        );
+        assert_eq!(output.contents(), "salut Ion\nsalut Ana\n");
+        assert!(matches!(
+            malis.call(&handlers[0], vec![]),
+            Err(MalisError::RuntimeError(
+                RuntimeError::InvalidArgumentsNumber(_)
+            ))
+        ));
+
+        let Some(MalisObject::Instance(buton)) = malis.get_global("buton") else {
+            panic!("The button should be an instance");
+        };
+        let apasa = Callable::method(&buton, "apasa").expect("No method");
+        assert_eq!(apasa.arity().map(|arity| arity.min).ok(), Some(1));
+        assert!(Callable::method(&buton, "lipsa").is_err());
+        assert!(Callable::new(MalisObject::Number(1.0)).is_err());
+        assert!(malis.run("on(1);", false).is_err());
+    }
+}
@@ -0,0 +1,355 @@
+use super::{List, MalisInstance, MalisObject, NativeModule, RuntimeError};
+use std::collections::HashMap;
+
+/// Conversion of a Rust value into a Malis one
+pub trait IntoMalis {
+    fn into_malis(self) -> MalisObject;
+}
+
+/// Conversion of a Malis value into a Rust one, failing with `RuntimeError::TypeMismatch` when
+/// the value does not have the expected type
+pub trait FromMalis: Sized {
+    fn from_malis(value: MalisObject) -> Result<Self, RuntimeError>;
+}
+
+fn mismatch(expected: &str, value: &MalisObject) -> RuntimeError {
+    RuntimeError::TypeMismatch(format!(
+        "Expected {expected}, got {value} of type {}",
+        value.type_name()
+    ))
+}
+
+impl IntoMalis for MalisObject {
+    fn into_malis(self) -> MalisObject {
+        self
+    }
+}
+
+impl FromMalis for MalisObject {
+    fn from_malis(value: MalisObject) -> Result<Self, RuntimeError> {
+        Ok(value)
+    }
+}
+
+impl IntoMalis for () {
+    fn into_malis(self) -> MalisObject {
+        MalisObject::Nil
+    }
+}
+
+impl IntoMalis for bool {
+    fn into_malis(self) -> MalisObject {
+        MalisObject::Boolean(self)
+    }
+}
+
+impl FromMalis for bool {
+    fn from_malis(value: MalisObject) -> Result<Self, RuntimeError> {
+        match value {
+            MalisObject::Boolean(value) => Ok(value),
+            _ => Err(mismatch("a bool", &value)),
+        }
+    }
+}
+
+impl IntoMalis for String {
+    fn into_malis(self) -> MalisObject {
+        MalisObject::StringValue(self)
+    }
+}
+
+impl IntoMalis for &str {
+    fn into_malis(self) -> MalisObject {
+        MalisObject::StringValue(self.to_string())
+    }
+}
+
+impl FromMalis for String {
+    fn from_malis(value: MalisObject) -> Result<Self, RuntimeError> {
+        match value {
+            MalisObject::StringValue(value) => Ok(value),
+            _ => Err(mismatch("a string", &value)),
+        }
+    }
+}
+
+// Malis numbers are `f32`, so wider numbers lose precision on the way in
+macro_rules! float {
+    ($($t:ty),*) => {
+        $(
+            impl IntoMalis for $t {
+                fn into_malis(self) -> MalisObject {
+                    MalisObject::Number(self as f32)
+                }
+            }
+
+            impl FromMalis for $t {
+                fn from_malis(value: MalisObject) -> Result<Self, RuntimeError> {
+                    match value {
+                        MalisObject::Number(n) => Ok(n as $t),
+                        _ => Err(mismatch("a number", &value)),
+                    }
+                }
+            }
+        )*
+    };
+}
+
+float!(f32, f64);
+
+// Integers only accept numbers without a fractional part which fit in their range. `MAX` rounds up
+// to the next power of two as a float, so the upper bound is that power of two, left out.
+macro_rules! integer {
+    ($($t:ty),*) => {
+        $(
+            impl IntoMalis for $t {
+                fn into_malis(self) -> MalisObject {
+                    MalisObject::Number(self as f32)
+                }
+            }
+
+            impl FromMalis for $t {
+                fn from_malis(value: MalisObject) -> Result<Self, RuntimeError> {
+                    match value {
+                        MalisObject::Number(n)
+                            if n.fract() == 0.0
+                                && f64::from(n) >= <$t>::MIN as f64
+                                && f64::from(n)
+                                    < 2f64.powi((<$t>::BITS - u32::from(<$t>::MIN != 0)) as i32) =>
+                        {
+                            Ok(n as $t)
+                        }
+                        _ => Err(mismatch(concat!("an integer fitting in ", stringify!($t)), &value)),
+                    }
+                }
+            }
+        )*
+    };
+}
+
+integer!(i8, i16, i32, i64, isize, u8, u16, u32, u64, usize);
+
+impl<T: IntoMalis> IntoMalis for Option<T> {
+    fn into_malis(self) -> MalisObject {
+        self.map_or(MalisObject::Nil, IntoMalis::into_malis)
+    }
+}
+
+// `nil` is `None`, while anything else has to convert to `T`
+impl<T: FromMalis> FromMalis for Option<T> {
+    fn from_malis(value: MalisObject) -> Result<Self, RuntimeError> {
+        match value {
+            MalisObject::Nil => Ok(None),
+            value => T::from_malis(value).map(Some),
+        }
+    }
+}
+
+impl<T: IntoMalis> IntoMalis for Vec<T> {
+    fn into_malis(self) -> MalisObject {
+        MalisObject::List(List::new(
+            self.into_iter().map(IntoMalis::into_malis).collect(),
+        ))
+    }
+}
+
+// Lists are copied, converting each of their values to `T`
+impl<T: FromMalis> FromMalis for Vec<T> {
+    fn from_malis(value: MalisObject) -> Result<Self, RuntimeError> {
+        List::from_malis(value)?
+            .values()
+            .into_iter()
+            .map(T::from_malis)
+            .collect()
+    }
+}
+
+// Maps become plain objects, with a field for each entry
+impl<T: IntoMalis> IntoMalis for HashMap<String, T> {
+    fn into_malis(self) -> MalisObject {
+        let mut object = MalisInstance::object();
+        for (name, value) in self {
+            object.set_field(&name, value.into_malis());
+        }
+        MalisObject::Instance(object)
+    }
+}
+
+// Any instance is a map of its fields, each of them converted to `T`
+impl<T: FromMalis> FromMalis for HashMap<String, T> {
+    fn from_malis(value: MalisObject) -> Result<Self, RuntimeError> {
+        match value {
+            MalisObject::Instance(instance) => instance
+                .fields()
+                .map(|(name, value)| Ok((name.clone(), T::from_malis(value.clone())?)))
+                .collect(),
+            value => Err(mismatch("an object", &value)),
+        }
+    }
+}
+
+/// Rust function with typed arguments and result, which could be installed as a native. The
+/// arguments are converted with `FromMalis` and the result with `IntoMalis`.
+pub trait TypedNative<Args> {
+    const ARITY: usize;
+
+    fn call_typed(
+        &self,
+        name: &str,
+        arguments: Vec<MalisObject>,
+    ) -> Result<MalisObject, RuntimeError>;
+}
+
+// Converts the argument at `idx`, saying which one did not have the expected type
+pub(crate) fn argument<T: FromMalis>(
+    name: &str,
+    idx: usize,
+    value: MalisObject,
+) -> Result<T, RuntimeError> {
+    T::from_malis(value).map_err(|err| match err {
+        RuntimeError::TypeMismatch(message) => {
+            RuntimeError::TypeMismatch(format!("{name}, argument {}: {message}", idx + 1))
+        }
+        err => err,
+    })
+}
+
+macro_rules! typed_native {
+    ($($arg:ident),*) => {
+        impl<F, R, $($arg),*> TypedNative<($($arg,)*)> for F
+        where
+            F: Fn($($arg),*) -> Result<R, RuntimeError>,
+            R: IntoMalis,
+            $($arg: FromMalis,)*
+        {
+            const ARITY: usize = <[&str]>::len(&[$(stringify!($arg)),*]);
+
+            #[allow(non_snake_case, unused_variables, unused_mut)]
+            fn call_typed(
+                &self,
+                name: &str,
+                arguments: Vec<MalisObject>,
+            ) -> Result<MalisObject, RuntimeError> {
+                let mut arguments = arguments.into_iter().enumerate();
+                $(
+                    // The interpreter already checked the number of arguments
+                    let (idx, value) = arguments.next().unwrap_or((0, MalisObject::Nil));
+                    let $arg = argument::<$arg>(name, idx, value)?;
+                )*
+                Ok(self($($arg),*)?.into_malis())
+            }
+        }
+    };
+}
+
+typed_native!();
+typed_native!(A);
+typed_native!(A, B);
+typed_native!(A, B, C);
+typed_native!(A, B, C, D);
+
+impl NativeModule {
+    /// Adds a native function called `name` from a Rust function with typed arguments, whose
+    /// number is the arity of the native
+    pub fn typed<Args, F: TypedNative<Args> + 'static>(self, name: &str, function: F) -> Self {
+        let owned_name = name.to_string();
+        self.function(name, F::ARITY, move |_interpreter, arguments| {
+            function.call_typed(&owned_name, arguments)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{FromMalis, IntoMalis};
+    use crate::{
+        error::RuntimeError,
+        interpreter::{MalisObject, NativeModule},
+        Malis, MalisError, SharedBuffer,
+    };
+    use std::collections::HashMap;
+
+    #[test]
+    fn converts_values() {
+        assert_eq!(3u8.into_malis(), MalisObject::Number(3.0));
+        assert_eq!("sus".into_malis(), MalisObject::StringValue("sus".into()));
+        assert_eq!(None::<bool>.into_malis(), MalisObject::Nil);
+        assert_eq!(Some(true).into_malis(), MalisObject::Boolean(true));
+
+        assert_eq!(i32::from_malis(MalisObject::Number(-4.0)).ok(), Some(-4));
+        assert_eq!(f64::from_malis(MalisObject::Number(0.5)).ok(), Some(0.5));
+        assert_eq!(
+            Option::<String>::from_malis(MalisObject::Nil).ok(),
+            Some(None)
+        );
+        assert!(u8::from_malis(MalisObject::Number(256.0)).is_err());
+        assert_eq!(u8::from_malis(MalisObject::Number(255.0)).ok(), Some(255));
+        // Bounds which are not exact as floats
+        let (two_63, two_64) = (2f32.powi(63), 2f32.powi(64));
+        assert!(i64::from_malis(MalisObject::Number(two_63)).is_err());
+        assert_eq!(
+            i64::from_malis(MalisObject::Number(-two_63)).ok(),
+            Some(i64::MIN)
+        );
+        assert!(u64::from_malis(MalisObject::Number(two_64)).is_err());
+        assert_eq!(
+            u64::from_malis(MalisObject::Number(two_63)).ok(),
+            Some(1 << 63)
+        );
+        assert!(usize::from_malis(MalisObject::Number(1.5)).is_err());
+        let Err(RuntimeError::TypeMismatch(message)) =
+            bool::from_malis(MalisObject::StringValue("da".into()))
+        else {
+            panic!("A string is not a bool");
+        };
+        assert_eq!(message, "Expected a bool, got da of type string");
+
+        let preturi = HashMap::from([("paine".to_string(), 3u8), ("lapte".to_string(), 7)]);
+        let object = preturi.clone().into_malis();
+        assert_eq!(object.type_name(), "instance");
+        assert_eq!(
+            HashMap::<String, u8>::from_malis(object).ok(),
+            Some(preturi)
+        );
+        assert!(HashMap::<String, bool>::from_malis(
+            HashMap::from([("da".to_string(), 1)]).into_malis()
+        )
+        .is_err());
+        assert!(HashMap::<String, u8>::from_malis(MalisObject::Nil).is_err());
+    }
+
+    #[test]
+    fn installs_typed_natives() {
+        let module = NativeModule::new()
+            .typed(
+                "repeta",
+                |text: String, times: usize| Ok(text.repeat(times)),
+            )
+            .typed("jumatate", |n: f64| Ok(n / 2.0))
+            .typed("salut", |name: Option<String>| {
+                Ok(format!("salut, {}", name.unwrap_or("lume".to_string())))
+            })
+            .typed("nimic", || Ok(()));
+        let output = SharedBuffer::new();
+        let mut malis =
+            Malis::with_output(output.clone(), SharedBuffer::new()).expect("Failed to create");
+        malis.install(module).expect("Failed to install");
+        malis
+            .run(
+                "print repeta(\"ha\", 3); print jumatate(5); print salut(nil); print nimic();",
+                false,
+            )
+            .expect("Failed to run");
+        assert_eq!(output.contents(), "hahaha\n2.5\nsalut, lume\nnil\n");
+
+        let Err(MalisError::RuntimeError(RuntimeError::TypeMismatch(message))) =
+            malis.run("repeta(\"ha\", -1);", false)
+        else {
+            panic!("A negative number is not a count");
+        };
+        assert_eq!(
+            message,
+            "repeta, argument 2: Expected an integer fitting in usize, got -1 of type number"
+        );
+    }
+}
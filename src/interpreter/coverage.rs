@@ -0,0 +1,386 @@
+use super::Interpreter;
+use crate::{
+    ast::{
+        Binary, Call, ClassDeclaration, Expr, FunctionDeclaration, GetExpr, Group, IfStmt, Literal,
+        Logical, ReturnStmt, SetExpr, Stmt, SuperExpr, Ternary, Unary, VarStmt, WhileStmt,
+    },
+    token::Token,
+    visit::{ExprVisitor, StmtVisitor},
+};
+use std::collections::BTreeMap;
+use std::fmt::Write;
+
+/// Kinds of code that could go one of two ways
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum BranchKind {
+    // Either branch of an `if`
+    If,
+    // Entering the body of a loop or leaving it
+    While,
+    // Either side of `?:`
+    Ternary,
+    // Short-circuiting an `and` or an `or`, or evaluating its right operand
+    Logical,
+}
+
+// Functions are cloned when declared, so the nodes of the syntax tree could not identify a branch.
+// Their position in the source does, along with their kind.
+type BranchKey = (usize, usize, BranchKind);
+
+/// Which statements and branches of a script executed, and how many times
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Coverage {
+    // Number of statements executed on each line with statements
+    lines: BTreeMap<usize, u64>,
+    // How many times each branch went the first and the second way. For conditions, the first
+    // way is taking the `true` branch, while for logical operators it is short-circuiting.
+    branches: BTreeMap<BranchKey, [u64; 2]>,
+}
+
+impl Coverage {
+    /// Returns the number of lines with statements and how many of them executed
+    pub fn line_summary(&self) -> (usize, usize) {
+        let hit = self.lines.values().filter(|hits| **hits > 0).count();
+        (hit, self.lines.len())
+    }
+
+    /// Returns the number of branches, two for each condition, and how many of them were taken
+    pub fn branch_summary(&self) -> (usize, usize) {
+        let hit = self
+            .branches
+            .values()
+            .flatten()
+            .filter(|hits| **hits > 0)
+            .count();
+        (hit, self.branches.len() * 2)
+    }
+
+    /// Returns the lines with statements that never executed
+    pub fn missed_lines(&self) -> Vec<usize> {
+        self.lines
+            .iter()
+            .filter(|(_, hits)| **hits == 0)
+            .map(|(line, _)| *line)
+            .collect()
+    }
+
+    /// Returns a summary of what the script at `path` covered, listing the lines it missed
+    pub fn summary(&self, path: &str) -> String {
+        let percent = |hit: usize, total: usize| {
+            if total == 0 {
+                100.0
+            } else {
+                hit as f64 / total as f64 * 100.0
+            }
+        };
+        let (lines_hit, lines) = self.line_summary();
+        let (branches_hit, branches) = self.branch_summary();
+        let mut summary = format!(
+            "{path}: lines {lines_hit}/{lines} ({:.1}%), branches {branches_hit}/{branches} ({:.1}%)",
+            percent(lines_hit, lines),
+            percent(branches_hit, branches)
+        );
+        let missed = self.missed_lines();
+        if !missed.is_empty() {
+            let missed = missed
+                .iter()
+                .map(usize::to_string)
+                .collect::<Vec<_>>()
+                .join(", ");
+            let _ = write!(summary, ", missed lines {missed}");
+        }
+        summary
+    }
+
+    /// Returns the coverage as an LCOV record for the script at `path`
+    pub fn lcov(&self, path: &str) -> String {
+        let mut lcov = format!("TN:\nSF:{path}\n");
+        for (block, ((line, _, _), hits)) in self.branches.iter().enumerate() {
+            // A condition that was never evaluated has no counts at all
+            let never_evaluated = hits.iter().all(|hits| *hits == 0);
+            for (branch, hits) in hits.iter().enumerate() {
+                if never_evaluated {
+                    let _ = writeln!(lcov, "BRDA:{line},{block},{branch},-");
+                } else {
+                    let _ = writeln!(lcov, "BRDA:{line},{block},{branch},{hits}");
+                }
+            }
+        }
+        let (branches_hit, branches) = self.branch_summary();
+        let _ = writeln!(lcov, "BRF:{branches}\nBRH:{branches_hit}");
+        for (line, hits) in self.lines.iter() {
+            let _ = writeln!(lcov, "DA:{line},{hits}");
+        }
+        let (lines_hit, lines) = self.line_summary();
+        let _ = writeln!(lcov, "LF:{lines}\nLH:{lines_hit}\nend_of_record");
+        lcov
+    }
+
+    // Adds the statements and branches of `stmts` to the ones that could be covered
+    fn register(&mut self, stmts: &[Stmt]) {
+        for stmt in stmts {
+            self.register_stmt(stmt);
+        }
+    }
+
+    fn register_stmt(&mut self, stmt: &Stmt) {
+        if let Some(line) = stmt.line() {
+            self.lines.entry(line).or_default();
+        }
+        stmt.walk(self)
+    }
+
+    fn register_branch(&mut self, kind: BranchKind, (line, column): (usize, usize)) {
+        // Conditions added by the parser, like the one of `for(;;)`, are nowhere in the source
+        if line != 0 {
+            self.branches.entry((line, column, kind)).or_default();
+        }
+    }
+}
+
+// Returns the position of the leftmost token of `expr`
+fn position(expr: &Expr) -> (usize, usize) {
+    let at = |token: &Token| (token.line(), token.column());
+    match expr {
+        Expr::Unary(unary) => at(&unary.operator),
+        Expr::Binary(binary) => position(&binary.left),
+        Expr::Group(group) => position(&group.expr),
+        Expr::Literal(literal) => (literal.line, literal.column),
+        Expr::Ternary(ternary) => position(&ternary.first),
+        Expr::Var(token) | Expr::Assign(token, _) | Expr::ClassSelf(token) => at(token),
+        Expr::Logical(logical) => position(&logical.left),
+        Expr::Call(call) => position(&call.callee),
+        Expr::Get(get_expr) => position(get_expr.object()),
+        Expr::Set(set_expr) => position(set_expr.object()),
+        Expr::SuperExpr(super_expr) => at(super_expr.keyword()),
+    }
+}
+
+// Walks the whole syntax tree, including the bodies of functions that might never be called, to
+// know everything that could be covered
+impl ExprVisitor<()> for Coverage {
+    fn visit_unary(&mut self, unary: &Unary) {
+        unary.right.walk(self)
+    }
+
+    fn visit_binary(&mut self, binary: &Binary) {
+        binary.left.walk(self);
+        binary.right.walk(self)
+    }
+
+    fn visit_ternary(&mut self, ternary: &Ternary) {
+        let operator = &ternary.first_operator;
+        self.register_branch(BranchKind::Ternary, (operator.line(), operator.column()));
+        ternary.first.walk(self);
+        ternary.second.walk(self);
+        ternary.third.walk(self)
+    }
+
+    fn visit_literal(&mut self, _literal: &Literal) {}
+
+    fn visit_group(&mut self, group: &Group) {
+        group.expr.walk(self)
+    }
+
+    fn visit_variable(&mut self, _variable: &Token) {}
+
+    fn visit_assign(&mut self, _ident: &Token, expr: &Expr) {
+        expr.walk(self)
+    }
+
+    fn visit_logical(&mut self, logical: &Logical) {
+        let operator = &logical.operator;
+        self.register_branch(BranchKind::Logical, (operator.line(), operator.column()));
+        logical.left.walk(self);
+        logical.right.walk(self)
+    }
+
+    fn visit_call(&mut self, call: &Call) {
+        call.callee.walk(self);
+        for argument in call.arguments.iter() {
+            argument.walk(self);
+        }
+    }
+
+    fn visit_get(&mut self, get: &GetExpr) {
+        get.object().walk(self)
+    }
+
+    fn visit_set(&mut self, set: &SetExpr) {
+        set.object().walk(self);
+        set.value().walk(self)
+    }
+
+    fn visit_self(&mut self, _class_self: &Token) {}
+
+    fn visit_super(&mut self, _super_expr: &SuperExpr) {}
+}
+
+impl StmtVisitor<()> for Coverage {
+    fn visit_expr_stmt(&mut self, stmt: &Expr) {
+        stmt.walk(self)
+    }
+
+    fn visit_print_stmt(&mut self, stmt: &Expr) {
+        stmt.walk(self)
+    }
+
+    fn visit_var_stmt(&mut self, stmt: &VarStmt) {
+        if let Some(expr) = stmt.expr() {
+            expr.walk(self);
+        }
+    }
+
+    fn visit_block_stmt(&mut self, stmts: &[Stmt]) {
+        self.register(stmts)
+    }
+
+    fn visit_if_stmt(&mut self, stmt: &IfStmt) {
+        self.register_branch(BranchKind::If, position(&stmt.condition));
+        stmt.condition.walk(self);
+        self.register_stmt(&stmt.then_branch);
+        if let Some(else_branch) = &stmt.else_branch {
+            self.register_stmt(else_branch);
+        }
+    }
+
+    fn visit_while_stmt(&mut self, stmt: &WhileStmt) {
+        self.register_branch(BranchKind::While, position(&stmt.condition));
+        stmt.condition.walk(self);
+        self.register_stmt(&stmt.stmt)
+    }
+
+    fn visit_return_stmt(&mut self, stmt: &ReturnStmt) {
+        if let Some(expr) = stmt.expr() {
+            expr.walk(self);
+        }
+    }
+
+    fn visit_function(&mut self, function: &FunctionDeclaration) {
+        self.register(&function.body)
+    }
+
+    fn visit_class(&mut self, class: &ClassDeclaration) {
+        self.register(&class.methods)
+    }
+}
+
+impl Interpreter {
+    /// Starts recording which statements and branches the code executed from now on covers
+    pub fn start_coverage(&mut self) {
+        self.coverage = Some(Coverage::default());
+    }
+
+    /// Stops recording coverage, returning what was covered since it started
+    pub fn stop_coverage(&mut self) -> Option<Coverage> {
+        self.coverage.take()
+    }
+
+    // Adds the statements about to be executed to the ones that could be covered
+    pub(crate) fn register_coverage(&mut self, stmts: &[Stmt]) {
+        if let Some(coverage) = self.coverage.as_mut() {
+            coverage.register(stmts);
+        }
+    }
+
+    // Records the execution of a statement
+    pub(crate) fn cover_statement(&mut self, stmt: &Stmt) {
+        if let (Some(coverage), Some(line)) = (self.coverage.as_mut(), stmt.line()) {
+            *coverage.lines.entry(line).or_default() += 1;
+        }
+    }
+
+    // Records which way a condition went
+    pub(crate) fn cover_condition(&mut self, kind: BranchKind, condition: &Expr, taken: bool) {
+        self.cover_branch(kind, position(condition), taken)
+    }
+
+    // Records whether a logical operator short-circuited
+    pub(crate) fn cover_logical(&mut self, logical: &Logical, short_circuited: bool) {
+        let operator = &logical.operator;
+        self.cover_branch(
+            BranchKind::Logical,
+            (operator.line(), operator.column()),
+            short_circuited,
+        )
+    }
+
+    // Records which way the `?:` operator went
+    pub(crate) fn cover_ternary(&mut self, ternary: &Ternary, taken: bool) {
+        let operator = &ternary.first_operator;
+        self.cover_branch(
+            BranchKind::Ternary,
+            (operator.line(), operator.column()),
+            taken,
+        )
+    }
+
+    fn cover_branch(&mut self, kind: BranchKind, (line, column): (usize, usize), first: bool) {
+        if line == 0 {
+            return;
+        }
+        if let Some(coverage) = self.coverage.as_mut() {
+            let hits = coverage.branches.entry((line, column, kind)).or_default();
+            hits[usize::from(!first)] += 1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Malis, SharedBuffer};
+
+    #[test]
+    fn records_lines_and_branches() {
+        let source = "\
+fun semn(n) {
+    if (n < 0) {
+        return -1;
+    }
+    return n > 0 ? 1 : 0;
+}
+fun nefolosita() {
+    print 1;
+}
+var i = 0;
+while (i < 2) i = i + 1;
+print semn(3) or false;
+";
+        let mut malis =
+            Malis::with_output(SharedBuffer::new(), SharedBuffer::new()).expect("Failed to create");
+        malis.set_coverage(true);
+        malis.run(source, false).expect("Failed to run");
+        let coverage = malis.take_coverage().expect("No coverage");
+
+        assert_eq!(coverage.missed_lines(), vec![3, 8]);
+        assert_eq!(coverage.line_summary(), (7, 9));
+        // Only the `true` side of the ternary and the short-circuit of `or` were taken
+        assert_eq!(coverage.branch_summary(), (5, 8));
+        let lcov = coverage.lcov("semn.ms");
+        assert!(
+            lcov.starts_with("TN:\nSF:semn.ms\nBRDA:2,0,0,0\nBRDA:2,0,1,1\n"),
+            "{lcov}"
+        );
+        assert!(lcov.contains("BRDA:11,2,0,2\nBRDA:11,2,1,1\n"), "{lcov}");
+        assert!(lcov.contains("DA:3,0\nDA:5,1\n"), "{lcov}");
+        assert!(lcov.ends_with("LF:9\nLH:7\nend_of_record\n"), "{lcov}");
+        assert_eq!(
+            coverage.summary("semn.ms"),
+            "semn.ms: lines 7/9 (77.8%), branches 5/8 (62.5%), missed lines 3, 8"
+        );
+    }
+
+    #[test]
+    fn tells_literal_conditions_apart() {
+        let mut malis =
+            Malis::with_output(SharedBuffer::new(), SharedBuffer::new()).expect("Failed to create");
+        malis.set_coverage(true);
+        malis
+            .run("if (true) print 1; if (false) print 2;\n", false)
+            .expect("Failed to run");
+        let coverage = malis.take_coverage().expect("No coverage");
+
+        // Each `if` has its own pair of branches, of which one was taken
+        assert_eq!(coverage.branch_summary(), (2, 4));
+    }
+}
@@ -0,0 +1,219 @@
+use super::{Interpreter, MalisObject, RuntimeError};
+use crate::ast::Stmt;
+use std::collections::BTreeSet;
+
+/// How the execution continues after a pause
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Resume {
+    // Run until the next breakpoint
+    Continue,
+    // Pause on the next line, entering the functions that it calls
+    StepIn,
+    // Pause on the next line of the current function, or of its caller once it returns
+    StepOver,
+    // Pause once the current function returns to its caller
+    StepOut,
+}
+
+/// Why the execution paused
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PauseReason {
+    // The script is about to execute its first statement
+    Entry,
+    Breakpoint,
+    Step,
+}
+
+/// A function being executed, or the script itself for the outermost frame
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Frame {
+    pub name: String,
+    // Line of the statement being executed in this frame
+    pub line: usize,
+}
+
+/// Front end of the debugger, deciding what happens whenever the execution pauses
+pub trait DebugFrontend {
+    /// Called before executing the statement on `line` when the execution pauses. The
+    /// interpreter could be inspected and used to evaluate expressions in the paused frame, while
+    /// breakpoints could be changed. Returning an error stops the script with that error.
+    fn paused(
+        &mut self,
+        interpreter: &mut Interpreter,
+        reason: PauseReason,
+        line: usize,
+    ) -> Result<Resume, RuntimeError>;
+}
+
+// State of the debugger attached to an interpreter
+pub(crate) struct Debugger {
+    // Taken out while the execution is paused, such that it could borrow the interpreter
+    frontend: Option<Box<dyn DebugFrontend>>,
+    breakpoints: BTreeSet<usize>,
+    resume: Resume,
+    // Number of frames when the execution last resumed, against which steps are measured
+    resume_depth: usize,
+    frames: Vec<Frame>,
+    // Depth and line of the last statement we went through. Several statements could be on the
+    // same line, but we only pause once per line.
+    last: Option<(usize, usize)>,
+}
+
+impl Debugger {
+    pub(crate) fn new(frontend: Box<dyn DebugFrontend>) -> Self {
+        Self {
+            frontend: Some(frontend),
+            breakpoints: BTreeSet::new(),
+            // We pause before the first statement, giving the front end a chance to set
+            // breakpoints
+            resume: Resume::StepIn,
+            resume_depth: 1,
+            frames: vec![Frame {
+                name: "<script>".to_string(),
+                line: 0,
+            }],
+            last: None,
+        }
+    }
+
+    // Decides whether we pause before the statement on `line`, given how the execution resumed
+    fn should_pause(&self, depth: usize, line: usize) -> Option<PauseReason> {
+        if self.last.is_none() {
+            return Some(PauseReason::Entry);
+        }
+        let step = match self.resume {
+            Resume::Continue => false,
+            Resume::StepIn => true,
+            Resume::StepOver => depth <= self.resume_depth,
+            Resume::StepOut => depth < self.resume_depth,
+        };
+        if step {
+            Some(PauseReason::Step)
+        } else if self.breakpoints.contains(&line) {
+            Some(PauseReason::Breakpoint)
+        } else {
+            None
+        }
+    }
+}
+
+impl Interpreter {
+    /// Attaches a debugger, which pauses before the first statement executed and then whenever
+    /// `frontend` asks it to
+    pub fn set_debugger<F: DebugFrontend + 'static>(&mut self, frontend: F) {
+        self.debugger = Some(Debugger::new(Box::new(frontend)));
+    }
+
+    /// Detaches the debugger, if there is one, letting the script run freely
+    pub fn clear_debugger(&mut self) {
+        self.debugger = None;
+    }
+
+    /// Pauses before executing the statements on `line`. Returns false if no debugger is attached.
+    pub fn set_breakpoint(&mut self, line: usize) -> bool {
+        self.debugger
+            .as_mut()
+            .map(|debugger| debugger.breakpoints.insert(line))
+            .is_some()
+    }
+
+    /// Removes the breakpoint on `line`, returning whether there was one
+    pub fn remove_breakpoint(&mut self, line: usize) -> bool {
+        self.debugger
+            .as_mut()
+            .is_some_and(|debugger| debugger.breakpoints.remove(&line))
+    }
+
+    /// Returns the lines with breakpoints, in order
+    pub fn breakpoints(&self) -> Vec<usize> {
+        self.debugger
+            .as_ref()
+            .map(|debugger| debugger.breakpoints.iter().copied().collect())
+            .unwrap_or_default()
+    }
+
+    /// Returns the functions being executed, starting with the script itself and ending with the
+    /// innermost call. It is only tracked while a debugger is attached.
+    pub fn call_stack(&self) -> &[Frame] {
+        self.debugger
+            .as_ref()
+            .map_or(&[][..], |debugger| debugger.frames.as_slice())
+    }
+
+    /// Returns the variables visible from the code being executed, one scope at a time, starting
+    /// with the innermost one. The last scope holds the globals.
+    pub fn scopes(&self) -> Vec<Vec<(String, MalisObject)>> {
+        let mut scopes = vec![];
+        let mut environment = Some(self.environment.clone());
+        while let Some(current) = environment {
+            let current = current.borrow();
+            let mut values = current
+                .values
+                .iter()
+                .map(|(name, value)| (name.clone(), value.clone()))
+                .collect::<Vec<_>>();
+            values.sort_by(|(first, _), (second, _)| first.cmp(second));
+            scopes.push(values);
+            environment = current.enclosing.clone();
+        }
+        scopes
+    }
+
+    // Called before executing each statement while a debugger is attached, pausing if needed
+    pub(crate) fn debug_statement(&mut self, stmt: &Stmt) -> Result<(), RuntimeError> {
+        let Some(line) = stmt.line() else {
+            return Ok(());
+        };
+        let Some(debugger) = self.debugger.as_mut() else {
+            return Ok(());
+        };
+        // Code evaluated by the front end while paused does not pause again
+        if debugger.frontend.is_none() {
+            return Ok(());
+        }
+
+        let depth = debugger.frames.len();
+        if let Some(frame) = debugger.frames.last_mut() {
+            frame.line = line;
+        }
+        if debugger.last == Some((depth, line)) {
+            return Ok(());
+        }
+        let reason = debugger.should_pause(depth, line);
+        debugger.last = Some((depth, line));
+        let Some(reason) = reason else {
+            return Ok(());
+        };
+
+        let Some(mut frontend) = debugger.frontend.take() else {
+            return Ok(());
+        };
+        let resume = frontend.paused(self, reason, line);
+        // The front end could have detached the debugger while paused
+        if let Some(debugger) = self.debugger.as_mut() {
+            debugger.frontend = Some(frontend);
+            debugger.resume = *resume.as_ref().unwrap_or(&Resume::Continue);
+            debugger.resume_depth = depth;
+        }
+        resume.map(|_| ())
+    }
+
+    // Tracks the call of a user function named `name`, while a debugger is attached
+    pub(crate) fn enter_function(&mut self, name: &str) {
+        if let Some(debugger) = self.debugger.as_mut() {
+            debugger.frames.push(Frame {
+                name: name.to_string(),
+                line: 0,
+            });
+        }
+    }
+
+    pub(crate) fn exit_function(&mut self) {
+        if let Some(debugger) = self.debugger.as_mut() {
+            // The script frame is never popped
+            if debugger.frames.len() > 1 {
+                debugger.frames.pop();
+            }
+        }
+    }
+}
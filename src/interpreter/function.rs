@@ -5,7 +5,7 @@ use std::fmt;
 use std::{cell::RefCell, rc::Rc};
 
 pub trait MalisCallable {
-    fn arity(&self) -> Result<usize, RuntimeError>;
+    fn arity(&self) -> Result<Arity, RuntimeError>;
 
     fn call(
         &self,
@@ -14,33 +14,100 @@ pub trait MalisCallable {
     ) -> Result<MalisObject, RuntimeError>;
 }
 
-#[derive(Debug, PartialEq, PartialOrd, Clone)]
+/// Number of arguments a callable accepts, between `min` and `max` when there is a maximum
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Arity {
+    pub min: usize,
+    pub max: Option<usize>,
+}
+
+impl Arity {
+    pub const fn exact(count: usize) -> Self {
+        Self {
+            min: count,
+            max: Some(count),
+        }
+    }
+
+    /// Accepts from `min` to `max` arguments, for callables with optional arguments
+    pub const fn range(min: usize, max: usize) -> Self {
+        Self {
+            min,
+            max: Some(max),
+        }
+    }
+
+    /// Accepts any number of arguments starting from `min`, for variadic callables
+    pub const fn at_least(min: usize) -> Self {
+        Self { min, max: None }
+    }
+
+    pub fn accepts(&self, count: usize) -> bool {
+        count >= self.min && self.max.is_none_or(|max| count <= max)
+    }
+}
+
+impl From<usize> for Arity {
+    fn from(count: usize) -> Self {
+        Self::exact(count)
+    }
+}
+
+impl fmt::Display for Arity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> Result<(), fmt::Error> {
+        match self.max {
+            Some(max) if max == self.min => write!(f, "{max}"),
+            Some(max) => write!(f, "{} to {max}", self.min),
+            None => write!(f, "at least {}", self.min),
+        }
+    }
+}
+
+/// Rust code called by a native function. Closures could capture whatever state they need, like
+/// configuration or counters, keeping it in a `Cell` or a `RefCell` to change it.
+pub type NativeFn = dyn Fn(&mut Interpreter, Vec<MalisObject>) -> Result<MalisObject, RuntimeError>;
+
+#[derive(Clone)]
 pub struct NativeFunction {
     name: String,
-    arity: usize,
-    call_fn: fn(&mut Interpreter, Vec<MalisObject>) -> Result<MalisObject, RuntimeError>,
+    arity: Arity,
+    // Shared, such that every copy of the function sees the same captured state
+    call_fn: Rc<NativeFn>,
 }
 
 impl NativeFunction {
-    pub fn new(
-        name: String,
-        arity: usize,
-        call_fn: fn(&mut Interpreter, Vec<MalisObject>) -> Result<MalisObject, RuntimeError>,
-    ) -> Self {
+    pub fn new<A, F>(name: String, arity: A, call_fn: F) -> Self
+    where
+        A: Into<Arity>,
+        F: Fn(&mut Interpreter, Vec<MalisObject>) -> Result<MalisObject, RuntimeError> + 'static,
+    {
         Self {
             name,
-            arity,
-            call_fn,
+            arity: arity.into(),
+            call_fn: Rc::new(call_fn),
         }
     }
 
     pub fn name(&self) -> &str {
         self.name.as_str()
     }
+
+    pub(crate) fn rename(&mut self, name: String) {
+        self.name = name;
+    }
+}
+
+impl fmt::Debug for NativeFunction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> Result<(), fmt::Error> {
+        f.debug_struct("NativeFunction")
+            .field("name", &self.name)
+            .field("arity", &self.arity)
+            .finish()
+    }
 }
 
 impl MalisCallable for NativeFunction {
-    fn arity(&self) -> Result<usize, RuntimeError> {
+    fn arity(&self) -> Result<Arity, RuntimeError> {
         Ok(self.arity)
     }
 
@@ -49,12 +116,38 @@ impl MalisCallable for NativeFunction {
         interpreter: &mut Interpreter,
         arguments: Vec<MalisObject>,
     ) -> Result<MalisObject, RuntimeError> {
-        (self.call_fn)(interpreter, arguments)
+        // The name is shown without the suffix marking it as a native
+        let name = || {
+            let name = self.name.strip_suffix(" <native fn>");
+            name.unwrap_or(&self.name).to_string()
+        };
+        interpreter.observe_entry(name, &arguments);
+        interpreter.profile_enter(name);
+        let value = (self.call_fn)(interpreter, arguments);
+        interpreter.profile_exit();
+        interpreter.observe_exit(name, &value);
+        value
+    }
+}
+
+impl PartialEq for NativeFunction {
+    fn eq(&self, other: &Self) -> bool {
+        // Copies of a native share its closure, while natives made separately are different, even
+        // with the same name like the methods of two strings
+        Rc::ptr_eq(&self.call_fn, &other.call_fn)
+    }
+}
+
+impl PartialOrd for NativeFunction {
+    fn partial_cmp(&self, _other: &Self) -> Option<Ordering> {
+        // We do not order functions, but we need to implement this trait in order to confirm to
+        // the `MalisObject` protocol
+        None
     }
 }
 
 impl MalisCallable for Box<NativeFunction> {
-    fn arity(&self) -> Result<usize, RuntimeError> {
+    fn arity(&self) -> Result<Arity, RuntimeError> {
         Ok(self.arity)
     }
 
@@ -63,7 +156,7 @@ impl MalisCallable for Box<NativeFunction> {
         interpreter: &mut Interpreter,
         arguments: Vec<MalisObject>,
     ) -> Result<MalisObject, RuntimeError> {
-        (self.call_fn)(interpreter, arguments)
+        self.as_ref().call(interpreter, arguments)
     }
 }
 
@@ -91,6 +184,15 @@ impl UserFunction {
         &self.function_declaration.name
     }
 
+    // Returns the name of the function, preceded by the name of the class for bound methods
+    fn qualified_name(&self) -> String {
+        let name = self.function_declaration.name.lexeme();
+        match self.closure.borrow().values.get("self") {
+            Some(MalisObject::Instance(instance)) => format!("{}.{name}", instance.name()),
+            _ => name.to_string(),
+        }
+    }
+
     // Binds this function to the class `instance` by defnining a new environment and inside it a
     // `self` variable to access the instance
     pub fn bind(self, instance: &MalisInstance) -> Result<Self, RuntimeError> {
@@ -107,8 +209,8 @@ impl UserFunction {
 }
 
 impl MalisCallable for UserFunction {
-    fn arity(&self) -> Result<usize, RuntimeError> {
-        Ok(self.function_declaration.parameters.len())
+    fn arity(&self) -> Result<Arity, RuntimeError> {
+        Ok(Arity::exact(self.function_declaration.parameters.len()))
     }
 
     fn call(
@@ -126,22 +228,32 @@ impl MalisCallable for UserFunction {
             .function_declaration
             .parameters
             .iter()
-            .zip(arguments.into_iter())
+            .zip(arguments.iter())
         {
-            environment.define(param.lexeme().to_string(), arg)?;
+            environment.define(param.lexeme().to_string(), arg.clone())?;
         }
+        // The call only starts once nothing could fail before it ends, such that each start is
+        // matched by an end
+        interpreter.start_call()?;
+        interpreter.observe_entry(|| self.qualified_name(), &arguments);
 
         // Afterwards, we wrap it in a `Rc` as it is required in order to share it. We also wrap it
         // in a `RefCell` such that we obtain mutable state
         let environment = Rc::new(RefCell::new(environment));
 
         // With the new environment defined, execute the body of the function
+        interpreter.enter_function(self.function_declaration.name.lexeme());
+        interpreter.profile_enter(|| self.qualified_name());
         let value =
             match interpreter.execute_block(&self.function_declaration.body, environment.clone()) {
                 Ok(_) => Ok(MalisObject::Nil),
                 Err(RuntimeError::Return(return_obj)) => Ok(return_obj),
                 Err(e) => Err(e),
             };
+        interpreter.profile_exit();
+        interpreter.exit_function();
+        interpreter.end_call();
+        interpreter.observe_exit(|| self.qualified_name(), &value);
 
         // Take out the previous globals environment
         let previous_globals = environment
@@ -0,0 +1,346 @@
+use super::{Arity, Interpreter, MalisObject, NativeFunction, RuntimeError};
+use core::cmp::Ordering;
+use std::{
+    cell::{RefCell, RefMut},
+    fmt,
+    rc::Rc,
+};
+
+/// Rust value that scripts could use like an instance, reading and setting its properties and
+/// calling its methods
+pub trait HostObject {
+    /// Name of the class of the object, shown when printing it
+    fn class_name(&self) -> &str;
+
+    /// Returns the value of the property `name`, or `None` if there is no such property
+    fn get(&self, _name: &str) -> Option<MalisObject> {
+        None
+    }
+
+    /// Sets the property `name` to `value`. Properties are read only unless implemented.
+    fn set(&mut self, name: &str, _value: MalisObject) -> Result<(), RuntimeError> {
+        Err(RuntimeError::InvalidAccess(format!(
+            "Property {name:?} of {} cannot be set",
+            self.class_name()
+        )))
+    }
+
+    /// Returns the number of arguments of the method `name`, or `None` if there is no such method
+    fn method_arity(&self, _name: &str) -> Option<Arity> {
+        None
+    }
+
+    /// Calls the method `name`, after the interpreter checked its number of arguments
+    fn call_method(
+        &mut self,
+        _interpreter: &mut Interpreter,
+        name: &str,
+        _arguments: Vec<MalisObject>,
+    ) -> Result<MalisObject, RuntimeError> {
+        Err(RuntimeError::PropertyNotPresent(format!(
+            "Method {name:?} not present in {}",
+            self.class_name()
+        )))
+    }
+
+    /// Returns the names of the properties and methods, for completion and introspection
+    fn member_names(&self) -> Vec<String> {
+        vec![]
+    }
+}
+
+/// Shared handle to a host object. Copies of a `MalisObject` holding it refer to the same object.
+#[derive(Clone)]
+pub struct Host {
+    object: Rc<RefCell<dyn HostObject>>,
+    // Set for the namespaces grouping natives, like `json`
+    namespace: bool,
+}
+
+impl Host {
+    pub fn new<T: HostObject + 'static>(object: T) -> Self {
+        Self {
+            object: Rc::new(RefCell::new(object)),
+            namespace: false,
+        }
+    }
+
+    pub(crate) fn namespace<T: HostObject + 'static>(object: T) -> Self {
+        Self {
+            namespace: true,
+            ..Self::new(object)
+        }
+    }
+
+    pub fn is_namespace(&self) -> bool {
+        self.namespace
+    }
+
+    pub fn class_name(&self) -> String {
+        self.object
+            .try_borrow()
+            .map_or("<busy>".to_string(), |object| {
+                object.class_name().to_string()
+            })
+    }
+
+    // Hands out the object, unless one of its methods is already running and calls back into it
+    fn borrow_mut(&self) -> Result<RefMut<'_, dyn HostObject + 'static>, RuntimeError> {
+        self.object.try_borrow_mut().map_err(|_| {
+            RuntimeError::InvalidAccess(format!(
+                "Host object {} is in use by one of its methods",
+                self.class_name()
+            ))
+        })
+    }
+
+    /// Returns the property or the method called `name`. Methods are returned bound to the
+    /// object, as natives.
+    pub fn get(&self, name: &str) -> Result<Option<MalisObject>, RuntimeError> {
+        let object = self.borrow_mut()?;
+        if let Some(value) = object.get(name) {
+            return Ok(Some(value));
+        }
+        let Some(arity) = object.method_arity(name) else {
+            return Ok(None);
+        };
+        let host = self.clone();
+        let method = name.to_string();
+        let native = NativeFunction::new(
+            format!("{}.{name} <native fn>", object.class_name()),
+            arity,
+            move |interpreter, arguments| {
+                host.borrow_mut()?
+                    .call_method(interpreter, &method, arguments)
+            },
+        );
+        Ok(Some(MalisObject::NativeFunction(Box::new(native))))
+    }
+
+    pub fn set(&self, name: &str, value: MalisObject) -> Result<(), RuntimeError> {
+        self.borrow_mut()?.set(name, value)
+    }
+
+    /// Returns whether `name` is a property of the object
+    pub fn has_property(&self, name: &str) -> bool {
+        self.object
+            .try_borrow()
+            .is_ok_and(|object| object.get(name).is_some())
+    }
+
+    pub fn member_names(&self) -> Vec<String> {
+        self.object
+            .try_borrow()
+            .map(|object| object.member_names())
+            .unwrap_or_default()
+    }
+}
+
+impl fmt::Debug for Host {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> Result<(), fmt::Error> {
+        write!(f, "Host({})", self.class_name())
+    }
+}
+
+impl PartialEq for Host {
+    fn eq(&self, other: &Self) -> bool {
+        // Host objects are only equal to themselves
+        Rc::ptr_eq(&self.object, &other.object)
+    }
+}
+
+impl PartialOrd for Host {
+    fn partial_cmp(&self, _other: &Self) -> Option<Ordering> {
+        None
+    }
+}
+
+type Constructor = dyn Fn(&mut Interpreter, Vec<MalisObject>) -> Result<Host, RuntimeError>;
+
+/// Class implemented in Rust, whose instances wrap host objects. Scripts call it like any other
+/// class and Malis classes could inherit from it.
+#[derive(Clone)]
+pub struct HostClass {
+    name: String,
+    arity: Arity,
+    constructor: Rc<Constructor>,
+}
+
+impl HostClass {
+    /// Creates a class called `name`, making a host object with `constructor` for every instance.
+    /// Instances of Malis classes inheriting from it get the same arguments as their `init`.
+    pub fn new<A, F>(name: &str, arity: A, constructor: F) -> Self
+    where
+        A: Into<Arity>,
+        F: Fn(&mut Interpreter, Vec<MalisObject>) -> Result<Host, RuntimeError> + 'static,
+    {
+        Self {
+            name: name.to_string(),
+            arity: arity.into(),
+            constructor: Rc::new(constructor),
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn arity(&self) -> Arity {
+        self.arity
+    }
+
+    pub(crate) fn construct(
+        &self,
+        interpreter: &mut Interpreter,
+        arguments: Vec<MalisObject>,
+    ) -> Result<Host, RuntimeError> {
+        if !self.arity.accepts(arguments.len()) {
+            return Err(RuntimeError::InvalidArgumentsNumber(format!(
+                "Host class {} expects {} arguments but got {}.",
+                self.name,
+                self.arity,
+                arguments.len()
+            )));
+        }
+        (self.constructor)(interpreter, arguments)
+    }
+}
+
+impl fmt::Debug for HostClass {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> Result<(), fmt::Error> {
+        f.debug_struct("HostClass")
+            .field("name", &self.name)
+            .field("arity", &self.arity)
+            .finish()
+    }
+}
+
+impl PartialEq for HostClass {
+    fn eq(&self, other: &Self) -> bool {
+        Rc::ptr_eq(&self.constructor, &other.constructor)
+    }
+}
+
+impl PartialOrd for HostClass {
+    fn partial_cmp(&self, _other: &Self) -> Option<Ordering> {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Host, HostClass, HostObject};
+    use crate::{
+        interpreter::{Arity, Interpreter, MalisClass, MalisObject},
+        Malis, RuntimeError, SharedBuffer,
+    };
+
+    // Request handed to scripts by the host
+    struct Request {
+        path: String,
+        status: f32,
+    }
+
+    impl HostObject for Request {
+        fn class_name(&self) -> &str {
+            "Request"
+        }
+
+        fn get(&self, name: &str) -> Option<MalisObject> {
+            match name {
+                "path" => Some(MalisObject::StringValue(self.path.clone())),
+                "status" => Some(MalisObject::Number(self.status)),
+                _ => None,
+            }
+        }
+
+        fn set(&mut self, name: &str, value: MalisObject) -> Result<(), RuntimeError> {
+            match (name, value) {
+                ("status", MalisObject::Number(status)) => {
+                    self.status = status;
+                    Ok(())
+                }
+                (name, value) => Err(RuntimeError::InvalidArgument(format!(
+                    "Cannot set {name} to {value}"
+                ))),
+            }
+        }
+
+        fn method_arity(&self, name: &str) -> Option<Arity> {
+            (name == "header").then_some(Arity::exact(1))
+        }
+
+        fn call_method(
+            &mut self,
+            _interpreter: &mut Interpreter,
+            _name: &str,
+            arguments: Vec<MalisObject>,
+        ) -> Result<MalisObject, RuntimeError> {
+            Ok(MalisObject::StringValue(format!("header {}", arguments[0])))
+        }
+    }
+
+    fn request(path: &str) -> Host {
+        Host::new(Request {
+            path: path.to_string(),
+            status: 200.0,
+        })
+    }
+
+    #[test]
+    fn scripts_use_host_objects() {
+        let output = SharedBuffer::new();
+        let mut malis =
+            Malis::with_output(output.clone(), SharedBuffer::new()).expect("Failed to create");
+        let ctx = request("/acasa");
+        malis.set_global("ctx", MalisObject::Host(ctx.clone()));
+        malis
+            .run(
+                "print ctx.path; print ctx.header(\"x\"); ctx.status = 404; print ctx;",
+                false,
+            )
+            .expect("Failed to run");
+        assert_eq!(output.contents(), "/acasa\nheader x\n<host Request>\n");
+        assert_eq!(
+            ctx.get("status").ok(),
+            Some(Some(MalisObject::Number(404.0)))
+        );
+        assert!(malis.run("ctx.path = 1;", false).is_err());
+        assert!(malis.run("ctx.lipsa;", false).is_err());
+    }
+
+    #[test]
+    fn classes_inherit_from_host_classes() {
+        let output = SharedBuffer::new();
+        let mut malis =
+            Malis::with_output(output.clone(), SharedBuffer::new()).expect("Failed to create");
+        let class = HostClass::new("Request", 1, |_interpreter, arguments| {
+            Ok(request(&arguments[0].to_string()))
+        });
+        malis.set_global("Request", MalisObject::Class(MalisClass::from_host(class)));
+        malis
+            .run(
+                "\
+class Api < Request {
+    init(path) {
+        print \"init \" + path;
+    }
+    describe() {
+        return \"api\" + self.path + \" \" + self.header(\"y\");
+    }
+}
+var api = Api(\"/v1\");
+print api.describe();
+api.status = 500;
+print api.status;
+print Request(\"/simplu\").path;
+",
+                false,
+            )
+            .expect("Failed to run");
+        assert_eq!(
+            output.contents(),
+            "init /v1\napi/v1 header y\n500\n/simplu\n"
+        );
+    }
+}
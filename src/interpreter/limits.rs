@@ -0,0 +1,204 @@
+use super::{Interpreter, RuntimeError};
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc,
+};
+use std::time::Instant;
+
+/// Bounds on the work a script could do, for running code that is not trusted. Each limit that is
+/// exceeded stops the script with its own `RuntimeError`. Steps and calls are counted for each run
+/// of a script, or each call made by the host, on its own.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Limits {
+    // Number of statements the script could execute, loops counting once per iteration
+    pub max_steps: Option<u64>,
+    // Moment after which the script stops
+    pub deadline: Option<Instant>,
+    // Number of calls to functions and methods that could be nested
+    pub max_call_depth: Option<usize>,
+}
+
+/// Stops a running script from any thread. The script fails with `RuntimeError::Cancelled` before
+/// its next statement, as does every script run by the interpreter afterwards, until `reset`.
+#[derive(Debug, Clone, Default)]
+pub struct CancellationHandle(Arc<AtomicBool>);
+
+impl CancellationHandle {
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+
+    /// Lets the interpreter run scripts again after being cancelled
+    pub fn reset(&self) {
+        self.0.store(false, Ordering::Relaxed);
+    }
+}
+
+// Limits of an interpreter and how much of them was used
+#[derive(Debug, Default)]
+pub(crate) struct Budget {
+    limits: Limits,
+    steps: u64,
+    depth: usize,
+    cancellation: CancellationHandle,
+}
+
+impl Interpreter {
+    /// Limits the work done by the code executed from now on
+    pub fn set_limits(&mut self, limits: Limits) {
+        let budget = self.budget.get_or_insert_with(Default::default);
+        budget.limits = limits;
+    }
+
+    /// Returns the limits currently in place
+    pub fn limits(&self) -> Limits {
+        self.budget
+            .as_ref()
+            .map(|budget| budget.limits)
+            .unwrap_or_default()
+    }
+
+    /// Returns a handle for cancelling the scripts run by this interpreter from another thread
+    pub fn cancellation_handle(&mut self) -> CancellationHandle {
+        let budget = self.budget.get_or_insert_with(Default::default);
+        budget.cancellation.clone()
+    }
+
+    // Called when the host starts running code, which has all the steps and calls to itself
+    pub(crate) fn renew_budget(&mut self) {
+        if let Some(budget) = self.budget.as_mut() {
+            budget.steps = 0;
+            budget.depth = 0;
+        }
+    }
+
+    // Called before executing each statement while there is a budget, failing once it is spent
+    pub(crate) fn spend_step(&mut self) -> Result<(), RuntimeError> {
+        let Some(budget) = self.budget.as_mut() else {
+            return Ok(());
+        };
+        if budget.cancellation.is_cancelled() {
+            return Err(RuntimeError::Cancelled);
+        }
+        budget.steps += 1;
+        if let Some(max_steps) = budget.limits.max_steps {
+            if budget.steps > max_steps {
+                return Err(RuntimeError::StepLimitExceeded(max_steps));
+            }
+        }
+        if let Some(deadline) = budget.limits.deadline {
+            if Instant::now() >= deadline {
+                return Err(RuntimeError::DeadlineExceeded);
+            }
+        }
+        Ok(())
+    }
+
+    // Tracks the start of a call, failing if it nests too deep. Only the calls that started
+    // successfully have to be ended with `end_call`.
+    pub(crate) fn start_call(&mut self) -> Result<(), RuntimeError> {
+        let Some(budget) = self.budget.as_mut() else {
+            return Ok(());
+        };
+        if let Some(max_call_depth) = budget.limits.max_call_depth {
+            if budget.depth >= max_call_depth {
+                return Err(RuntimeError::CallDepthExceeded(max_call_depth));
+            }
+        }
+        budget.depth += 1;
+        Ok(())
+    }
+
+    pub(crate) fn end_call(&mut self) {
+        if let Some(budget) = self.budget.as_mut() {
+            budget.depth = budget.depth.saturating_sub(1);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Limits;
+    use crate::{error::RuntimeError, Malis, MalisError, SharedBuffer};
+    use std::time::{Duration, Instant};
+
+    fn limited(limits: Limits, source: &str) -> Result<(), MalisError> {
+        let mut malis =
+            Malis::with_output(SharedBuffer::new(), SharedBuffer::new()).expect("Failed to create");
+        malis.set_limits(limits);
+        malis.run(source, false)
+    }
+
+    #[test]
+    fn stops_scripts_exceeding_their_limits() {
+        let steps = Limits {
+            max_steps: Some(100),
+            ..Default::default()
+        };
+        assert!(matches!(
+            limited(steps, "while (true) {}"),
+            Err(MalisError::RuntimeError(RuntimeError::StepLimitExceeded(
+                100
+            )))
+        ));
+        assert!(limited(steps, "var i = 0; while (i < 10) i = i + 1;").is_ok());
+        // Each run gets the whole budget
+        let mut malis =
+            Malis::with_output(SharedBuffer::new(), SharedBuffer::new()).expect("Failed to create");
+        malis.set_limits(steps);
+        for _ in 0..10 {
+            assert!(malis
+                .run("var i = 0; while (i < 10) i = i + 1;", false)
+                .is_ok());
+        }
+
+        let deadline = Limits {
+            deadline: Some(Instant::now() + Duration::from_millis(20)),
+            ..Default::default()
+        };
+        assert!(matches!(
+            limited(deadline, "while (true) {}"),
+            Err(MalisError::RuntimeError(RuntimeError::DeadlineExceeded))
+        ));
+
+        let depth = Limits {
+            max_call_depth: Some(10),
+            ..Default::default()
+        };
+        let recursive = "fun f(n) { if (n > 0) f(n - 1); } f(9);";
+        assert!(limited(depth, recursive).is_ok());
+        assert!(matches!(
+            limited(depth, &recursive.replace("f(9)", "f(10)")),
+            Err(MalisError::RuntimeError(RuntimeError::CallDepthExceeded(
+                10
+            )))
+        ));
+    }
+
+    #[test]
+    fn cancels_from_another_thread() {
+        let mut malis =
+            Malis::with_output(SharedBuffer::new(), SharedBuffer::new()).expect("Failed to create");
+        let handle = malis.cancellation_handle();
+        let canceller = std::thread::spawn({
+            let handle = handle.clone();
+            move || {
+                std::thread::sleep(Duration::from_millis(20));
+                handle.cancel();
+            }
+        });
+        let result = malis.run("while (true) {}", false);
+        canceller.join().expect("Failed to cancel");
+        assert!(matches!(
+            result,
+            Err(MalisError::RuntimeError(RuntimeError::Cancelled))
+        ));
+
+        handle.reset();
+        assert!(malis.run("print 1;", false).is_ok());
+    }
+}
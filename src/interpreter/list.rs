@@ -0,0 +1,118 @@
+use super::{convert::FromMalis, IntoMalis, MalisObject, RuntimeError};
+use core::cmp::Ordering;
+use std::{cell::RefCell, fmt, rc::Rc};
+
+/// Shared handle to a list of values, made by the `list` native or by `json.parse`. Copies of a
+/// `MalisObject` holding it refer to the same list, so a list could end up containing itself.
+#[derive(Clone, Default)]
+pub struct List(Rc<RefCell<Vec<MalisObject>>>);
+
+impl List {
+    pub fn new(values: Vec<MalisObject>) -> Self {
+        Self(Rc::new(RefCell::new(values)))
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.borrow().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.borrow().is_empty()
+    }
+
+    /// Returns the value at `idx`, or `None` if the list is shorter
+    pub fn get(&self, idx: usize) -> Option<MalisObject> {
+        self.0.borrow().get(idx).cloned()
+    }
+
+    /// Replaces the value at `idx`, failing if the list is shorter
+    pub fn set(&self, idx: usize, value: MalisObject) -> Result<(), RuntimeError> {
+        let mut values = self.0.borrow_mut();
+        let len = values.len();
+        let slot = values.get_mut(idx).ok_or_else(|| {
+            RuntimeError::InvalidArgument(format!(
+                "Index {idx} is out of bounds for a list of length {len}"
+            ))
+        })?;
+        *slot = value;
+        Ok(())
+    }
+
+    pub fn push(&self, value: MalisObject) {
+        self.0.borrow_mut().push(value)
+    }
+
+    pub fn pop(&self) -> Option<MalisObject> {
+        self.0.borrow_mut().pop()
+    }
+
+    /// Returns a copy of the values, the lists among them still being shared
+    pub fn values(&self) -> Vec<MalisObject> {
+        self.0.borrow().clone()
+    }
+
+    // Runs `f` on the values, unless we are already in the middle of walking them, which only
+    // happens for a list containing itself. The values are held mutably so a walk inside the walk
+    // finds them taken.
+    pub(crate) fn walk<T, F: FnOnce(&[MalisObject]) -> T>(&self, f: F) -> Option<T> {
+        let values = self.0.try_borrow_mut().ok()?;
+        Some(f(&values))
+    }
+}
+
+// Strings are quoted, to tell `["1"]` from `[1]`. A list containing itself shows as `[...]`.
+impl fmt::Display for List {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> Result<(), fmt::Error> {
+        self.walk(|values| {
+            write!(f, "[")?;
+            for (idx, value) in values.iter().enumerate() {
+                if idx > 0 {
+                    write!(f, ", ")?;
+                }
+                match value {
+                    MalisObject::StringValue(text) => write!(f, "{text:?}")?,
+                    value => write!(f, "{value}")?,
+                }
+            }
+            write!(f, "]")
+        })
+        .unwrap_or_else(|| write!(f, "[...]"))
+    }
+}
+
+impl fmt::Debug for List {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> Result<(), fmt::Error> {
+        write!(f, "List({self})")
+    }
+}
+
+impl PartialEq for List {
+    fn eq(&self, other: &Self) -> bool {
+        // Like host objects, lists are only equal to themselves
+        Rc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl PartialOrd for List {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        (self == other).then_some(Ordering::Equal)
+    }
+}
+
+impl IntoMalis for List {
+    fn into_malis(self) -> MalisObject {
+        MalisObject::List(self)
+    }
+}
+
+impl FromMalis for List {
+    fn from_malis(value: MalisObject) -> Result<Self, RuntimeError> {
+        match value {
+            MalisObject::List(list) => Ok(list),
+            value => Err(RuntimeError::TypeMismatch(format!(
+                "Expected a list, got {value} of type {}",
+                value.type_name()
+            ))),
+        }
+    }
+}
@@ -1,12 +1,29 @@
-use super::{Interpreter, MalisCallable, MalisObject, RuntimeError, UserFunction};
+use super::{
+    Arity, Host, HostClass, Interpreter, MalisCallable, MalisObject, RuntimeError, UserFunction,
+};
 use crate::token::Token;
-use std::collections::BTreeMap;
+use std::{
+    collections::BTreeMap,
+    rc::Rc,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+/// Name of the class of plain objects
+pub const OBJECT_CLASS: &str = "Object";
+
+// Source of the ids telling classes apart
+static NEXT_CLASS_ID: AtomicUsize = AtomicUsize::new(0);
 
 #[derive(Debug, PartialEq, PartialOrd, Clone)]
 pub struct MalisClass {
     name: String,
-    methods: BTreeMap<String, UserFunction>,
+    // Shared by the copies of the class, which tells it apart from another class looking the same,
+    // like one declared with the same name in another scope
+    id: usize,
+    methods: Rc<BTreeMap<String, UserFunction>>,
     superclass: Option<Box<MalisClass>>,
+    // Set for classes implemented in Rust, which make a host object for each of their instances
+    host: Option<Box<HostClass>>,
 }
 
 impl MalisClass {
@@ -17,15 +34,60 @@ impl MalisClass {
     ) -> Self {
         Self {
             name: name.to_string(),
-            methods,
+            id: NEXT_CLASS_ID.fetch_add(1, Ordering::Relaxed),
+            methods: Rc::new(methods),
             superclass: superclass.map(Box::new),
+            host: None,
+        }
+    }
+
+    /// Creates a class implemented in Rust, which scripts could call and inherit from
+    pub fn from_host(host: HostClass) -> Self {
+        Self {
+            name: host.name().to_string(),
+            id: NEXT_CLASS_ID.fetch_add(1, Ordering::Relaxed),
+            methods: Rc::default(),
+            superclass: None,
+            host: Some(Box::new(host)),
         }
     }
 
+    // Returns the class implemented in Rust this class is or inherits from, if any
+    fn host_class(&self) -> Option<&HostClass> {
+        self.host.as_deref().or_else(|| {
+            self.superclass
+                .as_ref()
+                .and_then(|superclass| superclass.host_class())
+        })
+    }
+
     pub fn name(&self) -> &str {
         &self.name
     }
 
+    pub fn superclass(&self) -> Option<&MalisClass> {
+        self.superclass.as_deref()
+    }
+
+    /// Returns whether this class is `other` or inherits from it
+    pub fn is_subclass_of(&self, other: &MalisClass) -> bool {
+        self.id == other.id
+            || self
+                .superclass()
+                .is_some_and(|superclass| superclass.is_subclass_of(other))
+    }
+
+    /// Returns the names of the methods of this class, including the inherited ones
+    pub fn method_names(&self) -> Vec<String> {
+        let mut names = self
+            .superclass
+            .as_ref()
+            .map(|superclass| superclass.method_names())
+            .unwrap_or_default();
+        names.extend(self.methods.keys().cloned());
+        names
+    }
+
     fn find_method(&self, name: &str) -> Option<UserFunction> {
         self.methods
             .get(name)
@@ -44,13 +106,15 @@ impl MalisClass {
 }
 
 impl MalisCallable for MalisClass {
-    fn arity(&self) -> Result<usize, RuntimeError> {
+    fn arity(&self) -> Result<Arity, RuntimeError> {
         // If we have an initializer method present, the arity is represented by the number of
         // arguments for the init method
         if let Ok(method) = self.get("init") {
             method.arity()
+        } else if let Some(host) = self.host_class() {
+            Ok(host.arity())
         } else {
-            Ok(0)
+            Ok(Arity::exact(0))
         }
     }
 
@@ -59,8 +123,27 @@ impl MalisCallable for MalisClass {
         interpreter: &mut Interpreter,
         arguments: Vec<MalisObject>,
     ) -> Result<MalisObject, RuntimeError> {
-        // Create a new instance for the class
-        let instance = MalisInstance::new(self.clone());
+        interpreter.observe_entry(|| self.name.clone(), &arguments);
+        interpreter.profile_enter(|| self.name.clone());
+        let value = self.instantiate(interpreter, arguments);
+        interpreter.profile_exit();
+        interpreter.observe_exit(|| self.name.clone(), &value);
+        value
+    }
+}
+
+impl MalisClass {
+    fn instantiate(
+        &self,
+        interpreter: &mut Interpreter,
+        arguments: Vec<MalisObject>,
+    ) -> Result<MalisObject, RuntimeError> {
+        // Create a new instance for the class, wrapping a host object if the class is implemented
+        // in Rust
+        let mut instance = MalisInstance::new(self.clone());
+        if let Some(host) = self.host_class() {
+            instance.host = Some(host.construct(interpreter, arguments.clone())?);
+        }
         // Find the init method and call it to initialise the instance
         if let Ok(method) = self.get("init") {
             // Bind the method to the current instance and call it.
@@ -88,6 +171,8 @@ pub struct MalisInstance {
     class: MalisClass,
     // Each field in this class intance has a property name (key in the map) and a propery value
     fields: BTreeMap<String, MalisObject>,
+    // Object made by the class implemented in Rust this instance's class inherits from
+    host: Option<Host>,
 }
 
 impl MalisInstance {
@@ -95,28 +180,84 @@ impl MalisInstance {
         Self {
             class,
             fields: BTreeMap::new(),
+            host: None,
         }
     }
 
+    /// Creates a plain object, an instance of a class without methods, like the ones made out of
+    /// JSON objects and other maps
+    pub fn object() -> Self {
+        Self::new(MalisClass::new(OBJECT_CLASS, BTreeMap::new(), None))
+    }
+
     pub fn name(&self) -> &str {
         self.class.name()
     }
 
+    /// Returns the names of the fields set on this instance
+    pub fn field_names(&self) -> Vec<String> {
+        self.fields.keys().cloned().collect()
+    }
+
+    /// Returns the fields set on this instance, ordered by name
+    pub fn fields(&self) -> impl Iterator<Item = (&String, &MalisObject)> {
+        self.fields.iter()
+    }
+
+    pub fn class(&self) -> &MalisClass {
+        &self.class
+    }
+
     pub fn get(&self, key: &Token) -> Result<MalisObject, RuntimeError> {
-        let maybe_value = self.fields.get(key.lexeme());
+        self.property(key.lexeme())
+    }
+
+    /// Returns the field or the method called `name`, methods being bound to this instance
+    pub fn property(&self, name: &str) -> Result<MalisObject, RuntimeError> {
+        let maybe_value = self.fields.get(name);
         // If the name is a property of the class, we should find it in the fields map
         if let Some(value) = maybe_value {
             Ok(value.clone())
-        } else {
+        } else if let Ok(method) = self.class.get(name) {
             // Otherwise we want to check if the key does not refer to a class method
-            let method = self.class.get(key.lexeme())?;
             Ok(MalisObject::UserFunction(method.bind(self)?))
+        } else {
+            // Methods defined in Malis override the members of the host object
+            let member = match &self.host {
+                Some(host) => host.get(name)?,
+                None => None,
+            };
+            member.ok_or(RuntimeError::PropertyNotPresent(format!(
+                "Property {:?} not present in instance of class {:?}",
+                name,
+                self.name()
+            )))
         }
     }
 
     // Set the property identified by `key` to `value`
     pub fn set(&mut self, key: &Token, value: MalisObject) -> Result<MalisObject, RuntimeError> {
+        // Properties of the host object are set on it, instead of being shadowed by a field
+        if let Some(host) = self
+            .host
+            .as_ref()
+            .filter(|host| host.has_property(key.lexeme()))
+        {
+            host.set(key.lexeme(), value.clone())?;
+            return Ok(value);
+        }
         self.fields.insert(key.lexeme().to_string(), value.clone());
         Ok(value)
     }
+
+    /// Sets the field `name` to `value`, without going through the host object
+    pub fn set_field(&mut self, name: &str, value: MalisObject) {
+        self.fields.insert(name.to_string(), value);
+    }
+
+    /// Returns the host object of this instance, when its class inherits from one implemented in
+    /// Rust
+    pub fn host(&self) -> Option<&Host> {
+        self.host.as_ref()
+    }
 }
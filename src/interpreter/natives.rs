@@ -0,0 +1,328 @@
+mod json;
+pub(crate) mod list;
+mod math;
+pub(crate) mod string;
+
+use super::{
+    Arity, Host, HostObject, Interpreter, IntoMalis, List, MalisObject, NativeFunction,
+    RuntimeError,
+};
+
+/// Set of native functions and constants installed together in an interpreter, as globals
+#[derive(Debug, Clone, Default)]
+pub struct NativeModule {
+    globals: Vec<(String, MalisObject)>,
+}
+
+impl NativeModule {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a native function called `name`, accepting `arity` arguments. The interpreter checks
+    /// the number of arguments before calling it.
+    pub fn function<A, F>(mut self, name: &str, arity: A, function: F) -> Self
+    where
+        A: Into<Arity>,
+        F: Fn(&mut Interpreter, Vec<MalisObject>) -> Result<MalisObject, RuntimeError> + 'static,
+    {
+        let native = NativeFunction::new(format!("{name} <native fn>"), arity, function);
+        self.globals.push((
+            name.to_string(),
+            MalisObject::NativeFunction(Box::new(native)),
+        ));
+        self
+    }
+
+    /// Adds a constant called `name`
+    pub fn constant(mut self, name: &str, value: MalisObject) -> Self {
+        self.globals.push((name.to_string(), value));
+        self
+    }
+
+    /// Adds a global called `name` through which scripts reach the members of `module` with a
+    /// dot, like `json.parse`
+    pub fn namespace(self, name: &str, module: NativeModule) -> Self {
+        let members = module
+            .globals
+            .into_iter()
+            .map(|(member, value)| match value {
+                // The natives are named after the namespace, for errors and profiles
+                MalisObject::NativeFunction(mut native) => {
+                    native.rename(format!("{name}.{member} <native fn>"));
+                    (member, MalisObject::NativeFunction(native))
+                }
+                value => (member, value),
+            })
+            .collect();
+        let namespace = Namespace {
+            name: name.to_string(),
+            members,
+        };
+        self.constant(name, MalisObject::Host(Host::namespace(namespace)))
+    }
+
+    /// Returns the names of the functions and constants of the module
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.globals.iter().map(|(name, _)| name.as_str())
+    }
+}
+
+// Members of a module installed under a name, which could be read but not set
+struct Namespace {
+    name: String,
+    members: Vec<(String, MalisObject)>,
+}
+
+impl HostObject for Namespace {
+    fn class_name(&self) -> &str {
+        &self.name
+    }
+
+    fn get(&self, name: &str) -> Option<MalisObject> {
+        self.members
+            .iter()
+            .find(|(member, _)| member == name)
+            .map(|(_, value)| value.clone())
+    }
+
+    fn member_names(&self) -> Vec<String> {
+        self.members.iter().map(|(name, _)| name.clone()).collect()
+    }
+}
+
+impl Interpreter {
+    /// Defines every function and constant of `module` as a global, replacing the globals with
+    /// the same names
+    pub fn install(&mut self, module: NativeModule) -> Result<(), RuntimeError> {
+        self.with_globals(|globals| {
+            for (name, value) in module.globals {
+                globals.define(name, value)?;
+            }
+            Ok(())
+        })
+    }
+}
+
+// Natives every interpreter starts with
+pub(crate) fn core() -> NativeModule {
+    NativeModule::new()
+        .function("clock", 0, |_interpreter, _arguments| {
+            Ok(MalisObject::Number(
+                std::time::SystemTime::now()
+                    .duration_since(std::time::UNIX_EPOCH)?
+                    .as_secs_f32(),
+            ))
+        })
+        // Returns a list of the command line arguments passed to the script
+        .function("args", 0, |interpreter, _arguments| {
+            Ok(interpreter.args().to_vec().into_malis())
+        })
+        // Returns the value of the environment variable with the given name or `nil` if it is not
+        // set
+        .function("env", 1, |_interpreter, arguments| {
+            let MalisObject::StringValue(name) = &arguments[0] else {
+                return Err(RuntimeError::InvalidArgument(format!(
+                    "env expects a string as variable name, got {}",
+                    arguments[0]
+                )));
+            };
+            Ok(std::env::var(name).map_or(MalisObject::Nil, MalisObject::StringValue))
+        })
+        // Stops the execution of the script, requesting the process to exit with the given status
+        .function("exit", 1, |_interpreter, arguments| match arguments[0] {
+            MalisObject::Number(code) if code.fract() == 0.0 && (0.0..=255.0).contains(&code) => {
+                // Similar to `return`, we use the error system to unwind the execution, such that
+                // all the environments are restored on the way out
+                Err(RuntimeError::Exit(code as i32))
+            }
+            _ => Err(RuntimeError::InvalidArgument(format!(
+                "exit expects an integer status between 0 and 255, got {}",
+                arguments[0]
+            ))),
+        })
+        // Converts a string, a number or a bool to a number
+        .function("num", 1, |_interpreter, arguments| match &arguments[0] {
+            MalisObject::Number(n) => Ok(MalisObject::Number(*n)),
+            MalisObject::Boolean(b) => Ok(MalisObject::Number(if *b { 1.0 } else { 0.0 })),
+            MalisObject::StringValue(text) => text
+                .trim()
+                .parse::<f32>()
+                .ok()
+                .filter(|n| n.is_finite())
+                .map(MalisObject::Number)
+                .ok_or_else(|| {
+                    RuntimeError::InvalidConversion(format!(
+                        "num cannot convert {text:?}, which is not a finite number"
+                    ))
+                }),
+            other => Err(RuntimeError::TypeMismatch(format!(
+                "num expects a string, a number or a bool, got {other} of type {}",
+                other.type_name()
+            ))),
+        })
+        // Converts any value to the string `print` shows for it
+        .function("str", 1, |_interpreter, arguments| {
+            Ok(MalisObject::StringValue(arguments[0].to_string()))
+        })
+        // Converts any value to whether conditions consider it true
+        .function("bool", 1, |_interpreter, arguments| {
+            Ok(MalisObject::Boolean(arguments[0].is_truthy()))
+        })
+        .function("type", 1, |_interpreter, arguments| {
+            Ok(MalisObject::StringValue(
+                arguments[0].type_name().to_string(),
+            ))
+        })
+        // Returns whether the value is an instance of the class or of a class inheriting from it
+        .function("is_instance", 2, |_interpreter, arguments| {
+            let MalisObject::Class(class) = &arguments[1] else {
+                return Err(RuntimeError::TypeMismatch(format!(
+                    "is_instance expects a class as second argument, got {} of type {}",
+                    arguments[1],
+                    arguments[1].type_name()
+                )));
+            };
+            Ok(MalisObject::Boolean(match &arguments[0] {
+                MalisObject::Instance(instance) => instance.class().is_subclass_of(class),
+                _ => false,
+            }))
+        })
+        .function("callable", 1, |_interpreter, arguments| {
+            Ok(MalisObject::Boolean(arguments[0].is_callable()))
+        })
+        // Makes a list of the arguments
+        .function("list", Arity::at_least(0), |_interpreter, arguments| {
+            Ok(MalisObject::List(List::new(arguments)))
+        })
+        // Returns the string made of the character with the given code point
+        .typed("char", string::char)
+        .namespace("json", json::module())
+        .namespace("math", math::module())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{NativeFunction, NativeModule};
+    use crate::{
+        interpreter::{Arity, MalisObject},
+        Malis, MalisError, RuntimeError, SharedBuffer,
+    };
+    use std::{cell::Cell, rc::Rc};
+
+    #[test]
+    fn converts_and_inspects_values() {
+        let output = SharedBuffer::new();
+        let mut malis =
+            Malis::with_output(output.clone(), SharedBuffer::new()).expect("Failed to create");
+        malis
+            .run(
+                "\
+class Animal {}
+class Caine < Animal {}
+fun latra() {}
+print num(\" 42 \") + num(\"-1.5\") + num(true) + num(3);
+print str(4) + str(nil) + str(latra);
+print bool(0) and !bool(nil);
+print type(1) + \" \" + type(\"a\") + \" \" + type(Caine()) + \" \" + type(clock);
+print is_instance(Caine(), Animal) and is_instance(Caine(), Caine);
+print is_instance(Animal(), Caine) or is_instance(1, Animal);
+print callable(latra) and callable(Caine) and callable(\"a\".len) and !callable(1);
+var vechi;
+{ class Animal {} vechi = Animal(); }
+print is_instance(vechi, Animal) or is_instance(Animal(), Caine);
+var Catel = Caine;
+print is_instance(Caine(), Catel) and is_instance(Catel(), Animal);
+",
+                false,
+            )
+            .expect("Failed to run");
+        assert_eq!(
+            output.contents(),
+            "44.5\n4nil<fn latra:3>\ntrue\nnumber string instance native function\ntrue\nfalse\ntrue\nfalse\ntrue\n"
+        );
+
+        let Err(MalisError::RuntimeError(RuntimeError::InvalidConversion(message))) =
+            malis.run("num(\"42abc\");", false)
+        else {
+            panic!("The string is not a number");
+        };
+        assert_eq!(
+            message,
+            "num cannot convert \"42abc\", which is not a finite number"
+        );
+        assert!(matches!(
+            malis.run("num(nil);", false),
+            Err(MalisError::RuntimeError(RuntimeError::TypeMismatch(_)))
+        ));
+        assert!(matches!(
+            malis.run("is_instance(Caine(), 1);", false),
+            Err(MalisError::RuntimeError(RuntimeError::TypeMismatch(_)))
+        ));
+        assert!(malis.run("num(\"inf\");", false).is_err());
+    }
+
+    #[test]
+    fn installs_stateful_and_variadic_natives() {
+        let calls = Rc::new(Cell::new(0));
+        let module = NativeModule::new()
+            .function("numara", 0, {
+                let calls = calls.clone();
+                move |_interpreter, _arguments| {
+                    calls.set(calls.get() + 1);
+                    Ok(MalisObject::Number(calls.get() as f32))
+                }
+            })
+            .function("suma", Arity::at_least(1), |_interpreter, arguments| {
+                let mut total = 0.0;
+                for argument in arguments {
+                    if let MalisObject::Number(n) = argument {
+                        total += n;
+                    }
+                }
+                Ok(MalisObject::Number(total))
+            })
+            .function("salut", Arity::range(0, 1), |_interpreter, arguments| {
+                let name = arguments
+                    .first()
+                    .map_or("lume".to_string(), |name| name.to_string());
+                Ok(MalisObject::StringValue(format!("salut, {name}")))
+            })
+            .constant("versiune", MalisObject::Number(2.0));
+        assert_eq!(
+            module.names().collect::<Vec<_>>(),
+            ["numara", "suma", "salut", "versiune"]
+        );
+
+        let output = SharedBuffer::new();
+        let mut malis =
+            Malis::with_output(output.clone(), SharedBuffer::new()).expect("Failed to create");
+        malis.install(module).expect("Failed to install");
+        malis
+            .run(
+                "numara(); print numara(); print suma(1, 2, 3); print salut(); \
+                 print salut(\"Ana\"); print versiune;",
+                false,
+            )
+            .expect("Failed to run");
+        assert_eq!(output.contents(), "2\n6\nsalut, lume\nsalut, Ana\n2\n");
+        assert_eq!(calls.get(), 2);
+
+        let Err(MalisError::RuntimeError(err)) = malis.run("suma();", false) else {
+            panic!("Calling with too few arguments should fail");
+        };
+        assert!(err
+            .to_string()
+            .contains("Expected at least 1 arguments but got 0"));
+        assert!(malis.run("salut(1, 2);", false).is_err());
+
+        // Natives are only equal to their copies
+        let unu = NativeFunction::new("unu".to_string(), 0, |_interpreter, _arguments| {
+            Ok(MalisObject::Nil)
+        });
+        let altul = NativeFunction::new("unu".to_string(), 0, |_interpreter, _arguments| {
+            Ok(MalisObject::Nil)
+        });
+        assert!(unu == unu.clone() && unu != altul);
+    }
+}
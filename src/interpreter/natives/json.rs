@@ -0,0 +1,201 @@
+//! The `json` namespace, converting between JSON text and Malis values. JSON objects become plain
+//! objects, whose fields are the members of the JSON object, and arrays become lists.
+use super::NativeModule;
+use crate::{
+    error::RuntimeError,
+    interpreter::{Arity, List, MalisInstance, MalisObject},
+    json::{Json, MAX_DEPTH},
+};
+
+pub(crate) fn module() -> NativeModule {
+    NativeModule::new()
+        // Parses a JSON document, failing with the line and column at which it is not valid
+        .function("parse", 1, |_interpreter, arguments| {
+            let MalisObject::StringValue(source) = &arguments[0] else {
+                return Err(RuntimeError::InvalidArgument(format!(
+                    "json.parse expects a string, got {} of type {}",
+                    arguments[0],
+                    arguments[0].type_name()
+                )));
+            };
+            from_json(Json::parse(source)?)
+        })
+        // Writes a value as JSON, compact or indented by the given number of spaces
+        .function(
+            "stringify",
+            Arity::range(1, 2),
+            |_interpreter, arguments| {
+                let indent = match arguments.get(1) {
+                    None | Some(MalisObject::Nil) => 0,
+                    Some(MalisObject::Number(n))
+                        if n.fract() == 0.0 && (0.0..=10.0).contains(n) =>
+                    {
+                        *n as usize
+                    }
+                    Some(other) => {
+                        return Err(RuntimeError::InvalidArgument(format!(
+                            "json.stringify expects between 0 and 10 spaces to indent, got {other}"
+                        )))
+                    }
+                };
+                let json = to_json(&arguments[0], 0)?;
+                Ok(MalisObject::StringValue(json.pretty(indent)))
+            },
+        )
+}
+
+fn from_json(json: Json) -> Result<MalisObject, RuntimeError> {
+    Ok(match json {
+        Json::Null => MalisObject::Nil,
+        Json::Bool(value) => MalisObject::Boolean(value),
+        Json::Number(value) => MalisObject::Number(value as f32),
+        Json::String(value) => MalisObject::StringValue(value),
+        Json::Array(values) => MalisObject::List(List::new(
+            values
+                .into_iter()
+                .map(from_json)
+                .collect::<Result<_, _>>()?,
+        )),
+        Json::Object(members) => {
+            let mut object = MalisInstance::object();
+            for (name, value) in members {
+                object.set_field(&name, from_json(value)?);
+            }
+            MalisObject::Instance(object)
+        }
+    })
+}
+
+// Lists are shared, so a list could contain itself, which has no JSON text. Instances are copied
+// when stored, so cycles always go through a list. Deep nesting is refused too, as the parser
+// does, such that the recursion could not overflow.
+fn to_json(value: &MalisObject, depth: usize) -> Result<Json, RuntimeError> {
+    if depth > MAX_DEPTH {
+        return Err(RuntimeError::InvalidArgument(format!(
+            "json.stringify cannot convert values nested deeper than {MAX_DEPTH} levels"
+        )));
+    }
+    Ok(match value {
+        MalisObject::Nil => Json::Null,
+        MalisObject::Boolean(value) => Json::Bool(*value),
+        // Going through the text keeps `0.1` from becoming `0.10000000149011612`
+        MalisObject::Number(value) => Json::Number(
+            value
+                .to_string()
+                .parse()
+                .unwrap_or_else(|_| f64::from(*value)),
+        ),
+        MalisObject::StringValue(value) => Json::String(value.clone()),
+        MalisObject::Instance(instance) => {
+            let mut members = vec![];
+            for (name, value) in instance.fields() {
+                members.push((name.clone(), to_json(value, depth + 1)?));
+            }
+            Json::Object(members)
+        }
+        MalisObject::List(list) => Json::Array(
+            list.walk(|values| {
+                values
+                    .iter()
+                    .map(|value| to_json(value, depth + 1))
+                    .collect::<Result<_, _>>()
+            })
+            .ok_or_else(|| {
+                RuntimeError::InvalidArgument(
+                    "json.stringify cannot convert a list containing itself".to_string(),
+                )
+            })??,
+        ),
+        MalisObject::NativeFunction(_)
+        | MalisObject::UserFunction(_)
+        | MalisObject::Class(_)
+        | MalisObject::Host(_) => {
+            return Err(RuntimeError::InvalidArgument(format!(
+                "json.stringify cannot convert {value} of type {}",
+                value.type_name()
+            )))
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{error::RuntimeError, interpreter::MalisObject, Malis, MalisError, SharedBuffer};
+
+    #[test]
+    fn parses_and_stringifies() {
+        let output = SharedBuffer::new();
+        let mut malis =
+            Malis::with_output(output.clone(), SharedBuffer::new()).expect("Failed to create");
+        // Malis strings do not have escapes, so the JSON text comes from Rust
+        let text = |text: &str| MalisObject::StringValue(text.to_string());
+        malis.set_global(
+            "text",
+            text(r#"{"port": 80, "gazda": {"nume": "lo\"cal"}, "porturi": [1, [true, null]]}"#),
+        );
+        malis
+            .run(
+                "\
+var config = json.parse(text);
+print config.port + 1;
+print config.gazda.nume;
+print config.porturi;
+config.porturi.get(1).push(\"nou\");
+config.port = 0.1;
+print json.stringify(config);
+print json.stringify(config, 2);
+print json.stringify(nil, 4);
+",
+                false,
+            )
+            .expect("Failed to run");
+        assert_eq!(
+            output.contents(),
+            "81\nlo\"cal\n[1, [true, nil]]\n\
+             {\"gazda\":{\"nume\":\"lo\\\"cal\"},\"port\":0.1,\"porturi\":[1,[true,null,\"nou\"]]}\n\
+             {\n  \"gazda\": {\n    \"nume\": \"lo\\\"cal\"\n  },\n  \"port\": 0.1,\n  \"porturi\": [\n    1,\n    \
+             [\n      true,\n      null,\n      \"nou\"\n    ]\n  ]\n}\nnull\n"
+        );
+
+        malis.set_global("text", text("{\n  \"a\": tru\n}"));
+        let Err(MalisError::RuntimeError(err @ RuntimeError::JsonError(_))) =
+            malis.run("json.parse(text);", false)
+        else {
+            panic!("The JSON is not valid");
+        };
+        assert_eq!(
+            err.to_string(),
+            "Invalid JSON: Expected `true` at line 2, column 8"
+        );
+        let Err(MalisError::RuntimeError(err)) = malis.run(
+            "var bucla = list(1); bucla.push(bucla); json.stringify(bucla);",
+            false,
+        ) else {
+            panic!("The list contains itself");
+        };
+        assert_eq!(
+            err.to_string(),
+            "json.stringify cannot convert a list containing itself"
+        );
+        assert!(malis.run("json.parse(1);", false).is_err());
+        assert!(malis.run("json.stringify(clock);", false).is_err());
+        assert!(malis.run("json.stringify(1, -1);", false).is_err());
+        assert!(malis.run("json.parse = nil;", false).is_err());
+    }
+
+    #[test]
+    fn refuses_deeply_nested_values() {
+        let mut malis =
+            Malis::with_output(SharedBuffer::new(), SharedBuffer::new()).expect("Failed to create");
+        let source = "\
+class Nod {}
+var nod = Nod();
+for (var i = 0; i < 600; i = i + 1) { var parinte = Nod(); parinte.copil = nod; nod = parinte; }
+json.stringify(nod);
+";
+        let Err(MalisError::RuntimeError(err)) = malis.run(source, false) else {
+            panic!("The value is nested too deep");
+        };
+        assert!(err.to_string().contains("nested deeper than 512 levels"));
+    }
+}
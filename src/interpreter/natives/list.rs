@@ -0,0 +1,103 @@
+//! Methods of lists, called with a dot on any list like `nume.push("Ana")`. Lists are shared, so
+//! the methods changing them are seen through every variable holding the list.
+use crate::{
+    error::RuntimeError,
+    interpreter::{convert::argument, Arity, List, MalisObject, NativeFunction},
+};
+
+// Implementation of a method, getting the list it was called on and the arguments
+type Method = fn(&List, Vec<MalisObject>) -> Result<MalisObject, RuntimeError>;
+
+const METHODS: [(&str, Arity, Method); 6] = [
+    ("len", Arity::exact(0), |list, _arguments| {
+        Ok(MalisObject::Number(list.len() as f32))
+    }),
+    // Value at the given position, or `nil` if the list is shorter
+    ("get", Arity::exact(1), |list, arguments| {
+        let idx = argument("list.get", 0, arguments[0].clone())?;
+        Ok(list.get(idx).unwrap_or(MalisObject::Nil))
+    }),
+    ("set", Arity::exact(2), |list, arguments| {
+        let idx = argument("list.set", 0, arguments[0].clone())?;
+        list.set(idx, arguments[1].clone())?;
+        Ok(MalisObject::Nil)
+    }),
+    ("push", Arity::exact(1), |list, arguments| {
+        list.push(arguments[0].clone());
+        Ok(MalisObject::Nil)
+    }),
+    // Removes the last value and returns it, or `nil` if the list is empty
+    ("pop", Arity::exact(0), |list, _arguments| {
+        Ok(list.pop().unwrap_or(MalisObject::Nil))
+    }),
+    // Position of the first value equal to the argument, or `nil` if there is none
+    ("index_of", Arity::exact(1), |list, arguments| {
+        Ok(list
+            .values()
+            .iter()
+            .position(|value| *value == arguments[0])
+            .map_or(MalisObject::Nil, |idx| MalisObject::Number(idx as f32)))
+    }),
+];
+
+/// Returns the method `name` bound to `list`, if lists have such a method
+pub(crate) fn method(list: &List, name: &str) -> Option<MalisObject> {
+    let (_, arity, method) = METHODS.into_iter().find(|(method, ..)| *method == name)?;
+    let list = list.clone();
+    let native = NativeFunction::new(
+        format!("list.{name} <native fn>"),
+        arity,
+        move |_interpreter, arguments| method(&list, arguments),
+    );
+    Some(MalisObject::NativeFunction(Box::new(native)))
+}
+
+pub(crate) fn method_names() -> Vec<String> {
+    METHODS.iter().map(|(name, ..)| name.to_string()).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Malis, SharedBuffer};
+
+    #[test]
+    fn calls_list_methods() {
+        let output = SharedBuffer::new();
+        let mut malis =
+            Malis::with_output(output.clone(), SharedBuffer::new()).expect("Failed to create");
+        malis
+            .run(
+                "\
+var numere = list(1, \"doi\");
+var acelasi = numere;
+acelasi.push(3);
+print numere;
+print numere.len() + numere.get(2);
+print numere.get(5);
+numere.set(0, list());
+print numere.pop() + numere.len();
+print numere.index_of(\"doi\");
+print numere.index_of(7);
+print type(numere) + \" \" + str(bool(list()));
+numere.push(numere);
+print numere;
+",
+                false,
+            )
+            .expect("Failed to run");
+        assert_eq!(
+            output.contents(),
+            "[1, \"doi\", 3]\n6\nnil\n5\n1\nnil\nlist true\n[[], \"doi\", [...]]\n"
+        );
+
+        let Err(err) = malis.run("list(1).set(1, 2);", false) else {
+            panic!("The index is out of bounds");
+        };
+        assert_eq!(
+            err.to_string(),
+            "Index 1 is out of bounds for a list of length 1"
+        );
+        assert!(malis.run("list().get(-1);", false).is_err());
+        assert!(malis.run("list().lipsa();", false).is_err());
+    }
+}
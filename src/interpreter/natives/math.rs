@@ -0,0 +1,184 @@
+//! The `math` namespace, with the usual functions on numbers and a few for integers
+use super::NativeModule;
+use crate::{
+    error::RuntimeError,
+    interpreter::{convert::argument, Arity, MalisObject},
+};
+use std::f32::consts::{E, PI};
+
+// Native of a single number, by name
+type Unary<T> = (&'static str, fn(f32) -> T);
+
+// Functions of a single number returning a number
+const UNARY: [Unary<f32>; 16] = [
+    ("sqrt", f32::sqrt),
+    ("floor", f32::floor),
+    ("ceil", f32::ceil),
+    // Halves are rounded away from zero
+    ("round", f32::round),
+    ("trunc", f32::trunc),
+    ("abs", f32::abs),
+    // Unlike `f32::signum`, zero has no sign
+    ("sign", |x| if x == 0.0 { 0.0 } else { x.signum() }),
+    ("sin", f32::sin),
+    ("cos", f32::cos),
+    ("tan", f32::tan),
+    ("asin", f32::asin),
+    ("acos", f32::acos),
+    ("atan", f32::atan),
+    ("exp", f32::exp),
+    ("log2", f32::log2),
+    ("log10", f32::log10),
+];
+
+// Functions of a single number answering a question about it
+const PREDICATES: [Unary<bool>; 3] = [
+    ("isnan", f32::is_nan),
+    ("isinf", f32::is_infinite),
+    ("isint", |x| x.is_finite() && x.fract() == 0.0),
+];
+
+pub(crate) fn module() -> NativeModule {
+    let module = NativeModule::new()
+        .constant("pi", MalisObject::Number(PI))
+        .constant("e", MalisObject::Number(E))
+        .function("pow", 2, |_interpreter, arguments| {
+            let n = numbers("pow", arguments)?;
+            Ok(MalisObject::Number(n[0].powf(n[1])))
+        })
+        .function("atan2", 2, |_interpreter, arguments| {
+            let n = numbers("atan2", arguments)?;
+            Ok(MalisObject::Number(n[0].atan2(n[1])))
+        })
+        // Natural logarithm, or the logarithm in the given base
+        .function("log", Arity::range(1, 2), |_interpreter, arguments| {
+            let n = numbers("log", arguments)?;
+            Ok(MalisObject::Number(match n[..] {
+                [x, base] => x.log(base),
+                _ => n[0].ln(),
+            }))
+        })
+        .function("min", Arity::at_least(1), |_interpreter, arguments| {
+            let n = numbers("min", arguments)?;
+            Ok(MalisObject::Number(
+                n.into_iter().fold(f32::INFINITY, f32::min),
+            ))
+        })
+        .function("max", Arity::at_least(1), |_interpreter, arguments| {
+            let n = numbers("max", arguments)?;
+            Ok(MalisObject::Number(
+                n.into_iter().fold(f32::NEG_INFINITY, f32::max),
+            ))
+        })
+        // Integer division, rounding the quotient down
+        .function("div", 2, |_interpreter, arguments| {
+            let n = numbers("div", arguments)?;
+            Ok(MalisObject::Number((n[0] / nonzero("div", n[1])?).floor()))
+        })
+        // Remainder of the integer division, which is never negative
+        .function("mod", 2, |_interpreter, arguments| {
+            let n = numbers("mod", arguments)?;
+            Ok(MalisObject::Number(n[0].rem_euclid(nonzero("mod", n[1])?)))
+        })
+        // Works on the magnitudes, as the one of `i64::MIN` does not fit in an `i64`
+        .function("gcd", 2, |_interpreter, arguments| {
+            let a: i64 = argument("math.gcd", 0, arguments[0].clone())?;
+            let b: i64 = argument("math.gcd", 1, arguments[1].clone())?;
+            let (mut a, mut b) = (a.unsigned_abs(), b.unsigned_abs());
+            while b != 0 {
+                (a, b) = (b, a % b);
+            }
+            Ok(MalisObject::Number(a as f32))
+        });
+    let module = UNARY.into_iter().fold(module, |module, (name, f)| {
+        module.function(name, 1, move |_interpreter, arguments| {
+            Ok(MalisObject::Number(f(numbers(name, arguments)?[0])))
+        })
+    });
+    PREDICATES.into_iter().fold(module, |module, (name, f)| {
+        module.function(name, 1, move |_interpreter, arguments| {
+            Ok(MalisObject::Boolean(f(numbers(name, arguments)?[0])))
+        })
+    })
+}
+
+// Converts the arguments of the native `name`, which all have to be numbers. The interpreter
+// already checked how many there are.
+fn numbers(name: &str, arguments: Vec<MalisObject>) -> Result<Vec<f32>, RuntimeError> {
+    let name = format!("math.{name}");
+    arguments
+        .into_iter()
+        .enumerate()
+        .map(|(idx, value)| argument(&name, idx, value))
+        .collect()
+}
+
+fn nonzero(name: &str, divisor: f32) -> Result<f32, RuntimeError> {
+    if divisor == 0.0 {
+        Err(RuntimeError::Division(format!(
+            "math.{name} cannot divide by zero"
+        )))
+    } else {
+        Ok(divisor)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{error::RuntimeError, Malis, MalisError, SharedBuffer};
+
+    #[test]
+    fn computes() {
+        let output = SharedBuffer::new();
+        let mut malis =
+            Malis::with_output(output.clone(), SharedBuffer::new()).expect("Failed to create");
+        malis
+            .run(
+                "\
+print math.sqrt(16);
+print math.pow(2, 10);
+print math.floor(-1.5) + math.ceil(1.2) + math.round(2.5) + math.trunc(-1.7);
+print math.abs(-3) * math.sign(-2) + math.sign(0);
+print math.min(3, 1, 2) + math.max(4);
+print math.round(math.sin(math.pi / 2) + math.cos(0) + math.atan2(0, 1));
+print math.log(math.e) + math.log(8, 2) + math.log10(100) + math.log2(4) + math.exp(0);
+print math.isnan(math.sqrt(-1));
+print math.isinf(math.log(0));
+print math.isint(3) and !math.isint(3.5);
+print math.div(-7, 2);
+print math.mod(-7, 3);
+print math.gcd(12, -18);
+print math.gcd(-9223372036854775808, -1);
+print math.gcd(-9223372036854775808, 0);
+",
+                false,
+            )
+            .expect("Failed to run");
+        assert_eq!(
+            output.contents(),
+            "4\n1024\n2\n-3\n5\n2\n9\ntrue\ntrue\ntrue\n-4\n2\n6\n1\n9223372000000000000\n"
+        );
+
+        let Err(MalisError::RuntimeError(RuntimeError::TypeMismatch(message))) =
+            malis.run("math.max(1, \"doi\");", false)
+        else {
+            panic!("Strings are not numbers");
+        };
+        assert_eq!(
+            message,
+            "math.max, argument 2: Expected a number, got doi of type string"
+        );
+        assert!(matches!(
+            malis.run("math.sqrt();", false),
+            Err(MalisError::RuntimeError(
+                RuntimeError::InvalidArgumentsNumber(_)
+            ))
+        ));
+        assert!(matches!(
+            malis.run("math.mod(1, 0);", false),
+            Err(MalisError::RuntimeError(RuntimeError::Division(_)))
+        ));
+        assert!(malis.run("math.gcd(1.5, 2);", false).is_err());
+        assert!(malis.run("math.pi = 3;", false).is_err());
+    }
+}
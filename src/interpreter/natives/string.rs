@@ -0,0 +1,213 @@
+//! Methods of strings, called with a dot on any string like `nume.upper()`. Positions and lengths
+//! count characters, not bytes, so they work the same for any text.
+use crate::{
+    error::RuntimeError,
+    interpreter::{convert::argument, Arity, List, MalisObject, NativeFunction},
+};
+
+// Longest string, in bytes, `repeat` is allowed to make, so a script could not ask for more memory
+// than the machine has
+const MAX_LEN: usize = 1 << 28;
+
+// Implementation of a method, getting the string it was called on and the arguments
+type Method = fn(&str, Vec<MalisObject>) -> Result<MalisObject, RuntimeError>;
+
+const METHODS: [(&str, Arity, Method); 13] = [
+    ("len", Arity::exact(0), |text, _arguments| {
+        Ok(MalisObject::Number(text.chars().count() as f32))
+    }),
+    // Characters from `start` up to `end`, or up to the end of the string
+    ("substring", Arity::range(1, 2), |text, arguments| {
+        let len = text.chars().count();
+        let start = position("substring", 0, &arguments)?;
+        let end = match arguments.get(1) {
+            Some(_) => position("substring", 1, &arguments)?,
+            None => len,
+        };
+        if start > end || end > len {
+            return Err(RuntimeError::InvalidArgument(format!(
+                "string.substring expects 0 <= start <= end <= {len}, got {start} and {end}"
+            )));
+        }
+        Ok(string(text.chars().skip(start).take(end - start).collect()))
+    }),
+    // Position of the first occurrence of the argument, or `nil` if there is none
+    ("index_of", Arity::exact(1), |text, arguments| {
+        let needle: String = argument("string.index_of", 0, arguments[0].clone())?;
+        Ok(text.find(&needle).map_or(MalisObject::Nil, |offset| {
+            MalisObject::Number(text[..offset].chars().count() as f32)
+        }))
+    }),
+    // List of the pieces between the occurrences of the separator
+    ("split", Arity::exact(1), |text, arguments| {
+        let separator = pattern("split", &arguments)?;
+        let pieces = text
+            .split(&separator)
+            .map(|piece| string(piece.to_string()));
+        Ok(MalisObject::List(List::new(pieces.collect())))
+    }),
+    // Joins the values of the list, whatever their type, putting the string between them
+    ("join", Arity::exact(1), |text, arguments| {
+        let list: List = argument("string.join", 0, arguments[0].clone())?;
+        let parts: Vec<String> = list.values().iter().map(ToString::to_string).collect();
+        Ok(string(parts.join(text)))
+    }),
+    ("trim", Arity::exact(0), |text, _arguments| {
+        Ok(string(text.trim().to_string()))
+    }),
+    ("upper", Arity::exact(0), |text, _arguments| {
+        Ok(string(text.to_uppercase()))
+    }),
+    ("lower", Arity::exact(0), |text, _arguments| {
+        Ok(string(text.to_lowercase()))
+    }),
+    // Replaces every occurrence of the first argument with the second
+    ("replace", Arity::exact(2), |text, arguments| {
+        let from = pattern("replace", &arguments)?;
+        let to: String = argument("string.replace", 1, arguments[1].clone())?;
+        Ok(string(text.replace(&from, &to)))
+    }),
+    ("starts_with", Arity::exact(1), |text, arguments| {
+        let prefix: String = argument("string.starts_with", 0, arguments[0].clone())?;
+        Ok(MalisObject::Boolean(text.starts_with(&prefix)))
+    }),
+    ("ends_with", Arity::exact(1), |text, arguments| {
+        let suffix: String = argument("string.ends_with", 0, arguments[0].clone())?;
+        Ok(MalisObject::Boolean(text.ends_with(&suffix)))
+    }),
+    ("repeat", Arity::exact(1), |text, arguments| {
+        let times = position("repeat", 0, &arguments)?;
+        match text.len().checked_mul(times) {
+            Some(len) if len <= MAX_LEN => Ok(string(text.repeat(times))),
+            _ => Err(RuntimeError::InvalidArgument(format!(
+                "string.repeat cannot make a string longer than {MAX_LEN} bytes"
+            ))),
+        }
+    }),
+    // Code point of the character at the given position, the first one by default, or `nil` if
+    // the string is shorter. `char` turns it back into a string.
+    ("code_at", Arity::range(0, 1), |text, arguments| {
+        let index = match arguments.first() {
+            Some(_) => position("code_at", 0, &arguments)?,
+            None => 0,
+        };
+        Ok(text
+            .chars()
+            .nth(index)
+            .map_or(MalisObject::Nil, |ch| MalisObject::Number(ch as u32 as f32)))
+    }),
+];
+
+fn string(value: String) -> MalisObject {
+    MalisObject::StringValue(value)
+}
+
+// Converts the argument at `idx` of the method `name` to a position or a count
+fn position(name: &str, idx: usize, arguments: &[MalisObject]) -> Result<usize, RuntimeError> {
+    argument(&format!("string.{name}"), idx, arguments[idx].clone())
+}
+
+// Converts the first argument of the method `name` to a string to look for, which cannot be empty
+fn pattern(name: &str, arguments: &[MalisObject]) -> Result<String, RuntimeError> {
+    let pattern: String = argument(&format!("string.{name}"), 0, arguments[0].clone())?;
+    if pattern.is_empty() {
+        return Err(RuntimeError::InvalidArgument(format!(
+            "string.{name} cannot look for an empty string"
+        )));
+    }
+    Ok(pattern)
+}
+
+/// Returns the method `name` bound to `text`, if strings have such a method
+pub(crate) fn method(text: &str, name: &str) -> Option<MalisObject> {
+    let (_, arity, method) = METHODS.into_iter().find(|(method, ..)| *method == name)?;
+    let text = text.to_string();
+    let native = NativeFunction::new(
+        format!("string.{name} <native fn>"),
+        arity,
+        move |_interpreter, arguments| method(&text, arguments),
+    );
+    Some(MalisObject::NativeFunction(Box::new(native)))
+}
+
+pub(crate) fn method_names() -> Vec<String> {
+    METHODS.iter().map(|(name, ..)| name.to_string()).collect()
+}
+
+// Makes the string made of the character with the given code point
+pub(crate) fn char(code: u32) -> Result<String, RuntimeError> {
+    char::from_u32(code).map(String::from).ok_or_else(|| {
+        RuntimeError::InvalidArgument(format!("char got {code}, which is not a code point"))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{error::RuntimeError, Malis, MalisError, SharedBuffer};
+
+    #[test]
+    fn calls_string_methods() {
+        let output = SharedBuffer::new();
+        let mut malis =
+            Malis::with_output(output.clone(), SharedBuffer::new()).expect("Failed to create");
+        malis
+            .run(
+                "\
+var text = \"  Țară frumoasă 😀  \".trim();
+print text.len();
+print text.substring(5, 13) + \"|\" + text.substring(14);
+print text.index_of(\"frum\");
+print text.index_of(\"munte\");
+print \"a,b,,c\".split(\",\");
+print \"a,b\".split(\";\").len();
+print \", \".join(list(\"unu\", 2, true)) + \"|\" + \"-\".join(list());
+print text.upper() + \" \" + \"ĂÎȘ\".lower();
+print \"ana are mere\".replace(\"a\", \"o\");
+print text.starts_with(\"Țară\") and text.ends_with(\"😀\");
+print \"ha\".repeat(3);
+print text.code_at() + text.code_at(14);
+print text.code_at(15);
+print char(text.code_at(14)) + char(65);
+var upper = \"abc\".upper;
+print upper();
+",
+                false,
+            )
+            .expect("Failed to run");
+        assert_eq!(
+            output.contents(),
+            "15\nfrumoasă|😀\n5\nnil\n[\"a\", \"b\", \"\", \"c\"]\n1\nunu, 2, true|\n\
+             ȚARĂ FRUMOASĂ 😀 ăîș\nono ore mere\ntrue\nhahaha\n129050\nnil\n😀A\nABC\n"
+        );
+
+        let Err(MalisError::RuntimeError(err)) = malis.run("\"abc\".substring(2, 1);", false)
+        else {
+            panic!("The start is after the end");
+        };
+        assert_eq!(
+            err.to_string(),
+            "string.substring expects 0 <= start <= end <= 3, got 2 and 1"
+        );
+        assert!(matches!(
+            malis.run("\"abc\".repeat(-1);", false),
+            Err(MalisError::RuntimeError(RuntimeError::TypeMismatch(_)))
+        ));
+        let Err(MalisError::RuntimeError(err @ RuntimeError::InvalidArgument(_))) =
+            malis.run("\"abc\".repeat(100000000);", false)
+        else {
+            panic!("The string is too long");
+        };
+        assert_eq!(
+            err.to_string(),
+            "string.repeat cannot make a string longer than 268435456 bytes"
+        );
+        assert!(matches!(
+            malis.run("\"abc\".repeat(9000000000000000000);", false),
+            Err(MalisError::RuntimeError(RuntimeError::InvalidArgument(_)))
+        ));
+        assert!(malis.run("\"abc\".split(\"\");", false).is_err());
+        assert!(malis.run("\",\".join(\"a\", \"b\");", false).is_err());
+        assert!(malis.run("\"abc\".lipsa();", false).is_err());
+        assert!(malis.run("char(1114112);", false).is_err());
+    }
+}
@@ -1,6 +1,7 @@
 use super::{
-    Interpreter, MalisCallable, MalisClass, MalisInstance, NativeFunction, RuntimeError,
-    UserFunction,
+    natives::{list, string},
+    Arity, Host, Interpreter, List, MalisCallable, MalisClass, MalisInstance, NativeFunction,
+    RuntimeError, UserFunction,
 };
 use core::ops::{Add, Div, Mul, Neg, Not, Sub};
 use std::fmt;
@@ -14,6 +15,10 @@ pub enum MalisObject {
     UserFunction(UserFunction),
     Class(MalisClass),
     Instance(MalisInstance),
+    // Values shared by every copy of the list
+    List(List),
+    // Object implemented in Rust, handed to scripts by the host
+    Host(Host),
     Nil,
 }
 
@@ -28,6 +33,8 @@ impl fmt::Display for MalisObject {
             Self::UserFunction(value) => write!(f, "<fn {}>", value.name()),
             Self::Class(value) => write!(f, "<class {}>", value.name()),
             Self::Instance(value) => write!(f, "<class instance {}>", value.name()),
+            Self::List(value) => write!(f, "{value}"),
+            Self::Host(value) => write!(f, "<host {}>", value.class_name()),
         }
     }
 }
@@ -45,12 +52,59 @@ impl MalisObject {
             MalisObject::NativeFunction(_)
             | MalisObject::UserFunction(_)
             | MalisObject::Class(_)
-            | MalisObject::Instance(_) => true,
+            | MalisObject::Instance(_)
+            | MalisObject::List(_)
+            | MalisObject::Host(_) => true,
             // We consider null as false
             MalisObject::Nil => false,
         }
     }
 
+    /// Returns whether this is provided by natives: a native function or a namespace of them
+    pub fn is_native(&self) -> bool {
+        match self {
+            MalisObject::NativeFunction(_) => true,
+            MalisObject::Host(host) => host.is_namespace(),
+            _ => false,
+        }
+    }
+
+    /// Returns the name of the type of this object
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            MalisObject::Boolean(_) => "bool",
+            MalisObject::Number(_) => "number",
+            MalisObject::StringValue(_) => "string",
+            MalisObject::NativeFunction(_) => "native function",
+            MalisObject::UserFunction(_) => "function",
+            MalisObject::Class(_) => "class",
+            MalisObject::Instance(_) => "instance",
+            MalisObject::List(_) => "list",
+            MalisObject::Host(_) => "host object",
+            MalisObject::Nil => "nil",
+        }
+    }
+
+    /// Returns the names that could follow a dot after this object: the fields and methods of an
+    /// instance or the methods of a class, a string or a list
+    pub fn member_names(&self) -> Vec<String> {
+        match self {
+            MalisObject::Instance(instance) => {
+                let mut names = instance.field_names();
+                names.extend(instance.class().method_names());
+                if let Some(host) = instance.host() {
+                    names.extend(host.member_names());
+                }
+                names
+            }
+            MalisObject::Host(host) => host.member_names(),
+            MalisObject::Class(class) => class.method_names(),
+            MalisObject::StringValue(_) => string::method_names(),
+            MalisObject::List(_) => list::method_names(),
+            _ => vec![],
+        }
+    }
+
     pub fn is_callable(&self) -> bool {
         matches!(self, MalisObject::NativeFunction(_))
             || matches!(self, MalisObject::UserFunction(_))
@@ -59,7 +113,7 @@ impl MalisObject {
 }
 
 impl MalisCallable for MalisObject {
-    fn arity(&self) -> Result<usize, RuntimeError> {
+    fn arity(&self) -> Result<Arity, RuntimeError> {
         match self {
             MalisObject::NativeFunction(f) => f.arity(),
             MalisObject::UserFunction(f) => f.arity(),
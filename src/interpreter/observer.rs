@@ -0,0 +1,174 @@
+use super::{Interpreter, MalisInstance, MalisObject, RuntimeError};
+use crate::ast::Stmt;
+
+/// Watches the execution of scripts, for logging, auditing or building tools on top of the
+/// interpreter. Every method does nothing unless implemented, and none of them is called when no
+/// observer is set.
+pub trait Observer {
+    /// Called before executing each statement
+    fn statement(&mut self, _stmt: &Stmt) {}
+
+    /// Called when a function, a method or a native is called, before executing it
+    fn function_entered(&mut self, _name: &str, _arguments: &[MalisObject]) {}
+
+    /// Called when a function, a method or a native finishes, either returning a value or failing
+    fn function_exited(&mut self, _name: &str, _result: Result<&MalisObject, &RuntimeError>) {}
+
+    /// Called after reading the property `name` of `instance`, which could be a field or a method
+    fn property_get(&mut self, _instance: &MalisInstance, _name: &str, _value: &MalisObject) {}
+
+    /// Called after setting the field `name` of `instance` to `value`
+    fn property_set(&mut self, _instance: &MalisInstance, _name: &str, _value: &MalisObject) {}
+
+    /// Called when a runtime error stops the script, or a call the host made into it
+    fn runtime_error(&mut self, _error: &RuntimeError) {}
+}
+
+impl Interpreter {
+    /// Sets the observer notified of everything executed from now on, replacing the previous one
+    pub fn set_observer<O: Observer + 'static>(&mut self, observer: O) {
+        self.observer = Some(Box::new(observer));
+    }
+
+    /// Removes the observer, if there is one
+    pub fn clear_observer(&mut self) {
+        self.observer = None;
+    }
+
+    // Notifies the observer that a function is about to be called. The name is only built when
+    // needed.
+    pub(crate) fn observe_entry<F: FnOnce() -> String>(
+        &mut self,
+        name: F,
+        arguments: &[MalisObject],
+    ) {
+        if let Some(observer) = self.observer.as_mut() {
+            observer.function_entered(&name(), arguments);
+        }
+    }
+
+    pub(crate) fn observe_exit<F: FnOnce() -> String>(
+        &mut self,
+        name: F,
+        result: &Result<MalisObject, RuntimeError>,
+    ) {
+        if let Some(observer) = self.observer.as_mut() {
+            observer.function_exited(&name(), result.as_ref());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Observer;
+    use crate::{
+        ast::Stmt,
+        error::RuntimeError,
+        interpreter::{MalisInstance, MalisObject, NativeModule},
+        Malis, SharedBuffer,
+    };
+    use std::{cell::RefCell, rc::Rc};
+
+    // Writes down everything it is told
+    struct Recorder(Rc<RefCell<Vec<String>>>);
+
+    impl Observer for Recorder {
+        fn statement(&mut self, stmt: &Stmt) {
+            self.0
+                .borrow_mut()
+                .push(format!("line {}", stmt.line().unwrap_or_default()));
+        }
+
+        fn function_entered(&mut self, name: &str, arguments: &[MalisObject]) {
+            self.0
+                .borrow_mut()
+                .push(format!("enter {name} {}", arguments.len()));
+        }
+
+        fn function_exited(&mut self, name: &str, result: Result<&MalisObject, &RuntimeError>) {
+            let result = match result {
+                Ok(value) => value.to_string(),
+                Err(_) => "error".to_string(),
+            };
+            self.0.borrow_mut().push(format!("exit {name} {result}"));
+        }
+
+        fn property_get(&mut self, instance: &MalisInstance, name: &str, value: &MalisObject) {
+            self.0
+                .borrow_mut()
+                .push(format!("get {}.{name} {value}", instance.name()));
+        }
+
+        fn property_set(&mut self, instance: &MalisInstance, name: &str, value: &MalisObject) {
+            self.0
+                .borrow_mut()
+                .push(format!("set {}.{name} {value}", instance.name()));
+        }
+
+        fn runtime_error(&mut self, _error: &RuntimeError) {
+            self.0.borrow_mut().push("error".to_string());
+        }
+    }
+
+    #[test]
+    fn observes_the_execution() {
+        let source = "\
+class Punct {
+    aduna(n) {
+        return n + 1;
+    }
+}
+var p = Punct();
+p.x = p.aduna(2);
+print p.x - nil;
+";
+        let events = Rc::new(RefCell::new(vec![]));
+        let mut malis =
+            Malis::with_output(SharedBuffer::new(), SharedBuffer::new()).expect("Failed to create");
+        malis.set_observer(Recorder(events.clone()));
+        assert!(malis.run(source, false).is_err());
+
+        let expected = [
+            "line 1",
+            "line 6",
+            "enter Punct 0",
+            "exit Punct <class instance Punct>",
+            "line 7",
+            "get Punct.aduna <fn aduna:2>",
+            "enter Punct.aduna 1",
+            "line 3",
+            "exit Punct.aduna 3",
+            "set Punct.x 3",
+            "line 8",
+            "get Punct.x 3",
+            "error",
+        ];
+        assert_eq!(*events.borrow(), expected);
+    }
+
+    #[test]
+    fn observes_errors_of_calls_from_the_host() {
+        let events = Rc::new(RefCell::new(vec![]));
+        let mut malis =
+            Malis::with_output(SharedBuffer::new(), SharedBuffer::new()).expect("Failed to create");
+        // Natives calling back into the interpreter do not report the error twice
+        let module = NativeModule::new().function("aplica", 1, |interpreter, arguments| {
+            interpreter.call(&arguments[0], vec![])
+        });
+        malis.install(module).expect("Failed to install");
+        malis
+            .run("fun rau() { return 1 / nil; }", false)
+            .expect("Failed to run");
+        malis.set_observer(Recorder(events.clone()));
+
+        assert!(malis.call_function("rau", vec![]).is_err());
+        assert!(malis.call_function("lipsa", vec![]).is_err());
+        assert!(malis.run("aplica(rau);", false).is_err());
+        let errors = events
+            .borrow()
+            .iter()
+            .filter(|event| *event == "error")
+            .count();
+        assert_eq!(errors, 3);
+    }
+}
@@ -0,0 +1,75 @@
+use std::cell::RefCell;
+use std::io::{self, Write};
+use std::rc::Rc;
+
+/// In-memory sink that could be handed to the `Interpreter` as an output while keeping a handle
+/// to read what was written into it. Clones share the same underlying buffer.
+#[derive(Debug, Default, Clone)]
+pub struct SharedBuffer {
+    buffer: Rc<RefCell<Vec<u8>>>,
+}
+
+impl SharedBuffer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns everything written so far, with invalid UTF-8 sequences replaced
+    pub fn contents(&self) -> String {
+        String::from_utf8_lossy(&self.buffer.borrow()).into_owned()
+    }
+
+    /// Returns everything written so far and empties the buffer
+    pub fn take(&self) -> String {
+        let bytes = self.buffer.take();
+        String::from_utf8_lossy(&bytes).into_owned()
+    }
+}
+
+impl Write for SharedBuffer {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.buffer.borrow_mut().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Sink which forwards every line written into it to a callback. A line is passed without its
+/// ending newline once it is complete or when the sink is flushed.
+pub struct LineCallback<F: FnMut(&str)> {
+    callback: F,
+    // Bytes of the line that was not yet completed
+    pending: Vec<u8>,
+}
+
+impl<F: FnMut(&str)> LineCallback<F> {
+    pub fn new(callback: F) -> Self {
+        Self {
+            callback,
+            pending: vec![],
+        }
+    }
+}
+
+impl<F: FnMut(&str)> Write for LineCallback<F> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.pending.extend_from_slice(buf);
+        // Hand over every line that is complete
+        while let Some(idx) = self.pending.iter().position(|&b| b == b'\n') {
+            let line = self.pending.drain(..=idx).collect::<Vec<_>>();
+            (self.callback)(&String::from_utf8_lossy(&line[..idx]));
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        if !self.pending.is_empty() {
+            let line = std::mem::take(&mut self.pending);
+            (self.callback)(&String::from_utf8_lossy(&line));
+        }
+        Ok(())
+    }
+}
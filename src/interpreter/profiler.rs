@@ -0,0 +1,355 @@
+use super::Interpreter;
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::collections::HashMap;
+use std::fmt::Write;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+
+// Name of the frame for the code outside of any function
+const SCRIPT_FRAME: &str = "<script>";
+
+// Number of heap allocations made by the process while profiling, when `CountingAllocator` is in
+// use
+static ALLOCATIONS: AtomicU64 = AtomicU64::new(0);
+// Number of profilers running, in any thread. Allocations are only counted while there is one.
+static PROFILERS: AtomicUsize = AtomicUsize::new(0);
+
+/// Global allocator counting the allocations made, such that the profiler could attribute them to
+/// the functions making them. Binaries embedding the interpreter install it with
+/// `#[global_allocator]`, otherwise the profiler reports no allocations.
+///
+/// Nothing is counted unless a profiler is running, so code that does not profile only pays for
+/// reading a flag. The count is shared by the whole process though, so allocations made by other
+/// threads while profiling are attributed to the function running at the time.
+pub struct CountingAllocator;
+
+impl CountingAllocator {
+    fn count(&self) {
+        if PROFILERS.load(Ordering::Relaxed) > 0 {
+            ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+}
+
+// Safety: every call is forwarded as is to the system allocator
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        self.count();
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+        self.count();
+        System.alloc_zeroed(layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        self.count();
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+fn allocations() -> u64 {
+    ALLOCATIONS.load(Ordering::Relaxed)
+}
+
+/// What the profiler measured for a single function
+#[derive(Debug, Clone, PartialEq)]
+pub struct FunctionProfile {
+    pub name: String,
+    pub calls: u64,
+    // Time spent in the function, including the functions it called
+    pub inclusive: Duration,
+    // Time spent in the function itself
+    pub exclusive: Duration,
+    // Allocations made by the function itself
+    pub allocations: u64,
+}
+
+/// Result of profiling a script
+#[derive(Debug, Clone, PartialEq)]
+pub struct Profile {
+    // Sorted by exclusive time, the most expensive first
+    pub functions: Vec<FunctionProfile>,
+    pub total: Duration,
+    // Exclusive time for each distinct stack of calls, as `<script>;outer;inner`
+    pub stacks: Vec<(String, Duration)>,
+}
+
+impl Profile {
+    /// Returns a table with the measurements of each function, the most expensive first
+    pub fn report(&self) -> String {
+        let width = self
+            .functions
+            .iter()
+            .map(|function| function.name.chars().count())
+            .chain(["function".len()])
+            .max()
+            .unwrap_or_default();
+        let mut report = format!(
+            "{:<width$} {:>8} {:>12} {:>12} {:>7} {:>12}\n",
+            "function", "calls", "total (ms)", "self (ms)", "self %", "allocations"
+        );
+        for function in self.functions.iter() {
+            let share = if self.total.is_zero() {
+                0.0
+            } else {
+                function.exclusive.as_secs_f64() / self.total.as_secs_f64() * 100.0
+            };
+            let _ = writeln!(
+                report,
+                "{:<width$} {:>8} {:>12.3} {:>12.3} {:>6.1}% {:>12}",
+                function.name,
+                function.calls,
+                function.inclusive.as_secs_f64() * 1000.0,
+                function.exclusive.as_secs_f64() * 1000.0,
+                share,
+                function.allocations
+            );
+        }
+        let _ = writeln!(report, "total: {:.3} ms", self.total.as_secs_f64() * 1000.0);
+        report
+    }
+
+    /// Returns the stacks in the collapsed format read by flame graph tools, one stack per line
+    /// followed by the microseconds spent in its innermost function
+    pub fn collapsed_stacks(&self) -> String {
+        let mut collapsed = String::new();
+        for (stack, time) in self.stacks.iter() {
+            let _ = writeln!(collapsed, "{stack} {}", time.as_micros());
+        }
+        collapsed
+    }
+}
+
+// A call that did not return yet
+struct ActiveCall {
+    name: String,
+    started: Instant,
+    allocations: u64,
+    // Time and allocations of the calls it made, which are not its own
+    children_time: Duration,
+    children_allocations: u64,
+}
+
+// Measurements collected while a script runs
+pub(crate) struct Profiler {
+    calls: Vec<ActiveCall>,
+    functions: HashMap<String, FunctionProfile>,
+    stacks: HashMap<String, Duration>,
+    // Allocations made by the profiler itself, which are not attributed to anyone
+    overhead: u64,
+}
+
+impl Profiler {
+    fn new() -> Self {
+        PROFILERS.fetch_add(1, Ordering::Relaxed);
+        let mut profiler = Self {
+            calls: vec![],
+            functions: HashMap::new(),
+            stacks: HashMap::new(),
+            overhead: 0,
+        };
+        profiler.enter(SCRIPT_FRAME.to_string());
+        profiler
+    }
+
+    // Returns the allocations made so far by everyone but the profiler
+    fn allocations(&self) -> u64 {
+        allocations().saturating_sub(self.overhead)
+    }
+
+    fn enter(&mut self, name: String) {
+        let before = allocations();
+        self.calls.push(ActiveCall {
+            name,
+            started: Instant::now(),
+            allocations: self.allocations(),
+            children_time: Duration::ZERO,
+            children_allocations: 0,
+        });
+        self.overhead += allocations() - before;
+    }
+
+    fn exit(&mut self) {
+        // The script frame only ends when profiling stops
+        if self.calls.len() > 1 {
+            if let Some(call) = self.calls.pop() {
+                self.record(call);
+            }
+        }
+    }
+
+    // Attributes the measurements of a finished call to its function and to its caller
+    fn record(&mut self, call: ActiveCall) {
+        let elapsed = call.started.elapsed();
+        let allocated = self.allocations().saturating_sub(call.allocations);
+        let before = allocations();
+        let exclusive = elapsed.saturating_sub(call.children_time);
+
+        let stack = self
+            .calls
+            .iter()
+            .map(|caller| caller.name.as_str())
+            .chain([call.name.as_str()])
+            .collect::<Vec<_>>()
+            .join(";");
+        *self.stacks.entry(stack).or_default() += exclusive;
+
+        // Time spent in recursive calls is already part of the outermost one
+        let is_recursive = self.calls.iter().any(|caller| caller.name == call.name);
+        let function = self
+            .functions
+            .entry(call.name.clone())
+            .or_insert_with(|| FunctionProfile {
+                name: call.name.clone(),
+                calls: 0,
+                inclusive: Duration::ZERO,
+                exclusive: Duration::ZERO,
+                allocations: 0,
+            });
+        function.calls += 1;
+        function.exclusive += exclusive;
+        function.allocations += allocated.saturating_sub(call.children_allocations);
+        if !is_recursive {
+            function.inclusive += elapsed;
+        }
+
+        if let Some(caller) = self.calls.last_mut() {
+            caller.children_time += elapsed;
+            caller.children_allocations += allocated;
+        }
+        self.overhead += allocations() - before;
+    }
+
+    fn finish(mut self) -> Profile {
+        // Calls interrupted by an error never returned
+        while self.calls.len() > 1 {
+            self.exit();
+        }
+        let total = self
+            .calls
+            .pop()
+            .map(|script| {
+                let total = script.started.elapsed();
+                self.record(script);
+                total
+            })
+            .unwrap_or_default();
+
+        let mut functions = std::mem::take(&mut self.functions)
+            .into_values()
+            .collect::<Vec<_>>();
+        functions.sort_by(|first, second| {
+            second
+                .exclusive
+                .cmp(&first.exclusive)
+                .then_with(|| first.name.cmp(&second.name))
+        });
+        let mut stacks = std::mem::take(&mut self.stacks)
+            .into_iter()
+            .collect::<Vec<_>>();
+        stacks.sort();
+        Profile {
+            functions,
+            total,
+            stacks,
+        }
+    }
+}
+
+// Profilers which are dropped without finishing, like the one of an interpreter going away while
+// profiling, stop the counting too
+impl Drop for Profiler {
+    fn drop(&mut self) {
+        PROFILERS.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+impl Interpreter {
+    /// Starts measuring the calls made by the code executed from now on
+    pub fn start_profiling(&mut self) {
+        self.profiler = Some(Profiler::new());
+    }
+
+    /// Stops profiling, returning what was measured since it started
+    pub fn stop_profiling(&mut self) -> Option<Profile> {
+        self.profiler.take().map(Profiler::finish)
+    }
+
+    // Tracks the start of a call to a function, a native or a class, while profiling. The name is
+    // only built when needed.
+    pub(crate) fn profile_enter<F: FnOnce() -> String>(&mut self, name: F) {
+        if let Some(profiler) = self.profiler.as_mut() {
+            profiler.enter(name());
+        }
+    }
+
+    pub(crate) fn profile_exit(&mut self) {
+        if let Some(profiler) = self.profiler.as_mut() {
+            profiler.exit();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Malis, SharedBuffer};
+
+    #[test]
+    fn profiles_calls() {
+        let source = "\
+fun fib(n) {
+    if (n < 2) return n;
+    return fib(n - 1) + fib(n - 2);
+}
+class Tort {
+    init(aroma) {
+        clock();
+        print aroma;
+    }
+}
+fib(5);
+Tort(1);
+Tort(2);
+";
+        let mut malis =
+            Malis::with_output(SharedBuffer::new(), SharedBuffer::new()).expect("Failed to create");
+        malis.set_profiling(true);
+        malis.run(source, false).expect("Failed to run");
+        let profile = malis.take_profile().expect("No profile");
+
+        let calls = |name: &str| {
+            profile
+                .functions
+                .iter()
+                .find(|function| function.name == name)
+                .map_or(0, |function| function.calls)
+        };
+        assert_eq!(calls("fib"), 15);
+        assert_eq!(calls("Tort"), 2);
+        assert_eq!(calls("Tort.init"), 2);
+        assert_eq!(calls("clock"), 2);
+        assert_eq!(calls("<script>"), 1);
+
+        let fib = profile
+            .functions
+            .iter()
+            .find(|function| function.name == "fib")
+            .expect("No fib");
+        assert!(fib.inclusive >= fib.exclusive);
+        assert!(profile.total >= fib.inclusive);
+
+        let stacks = profile.collapsed_stacks();
+        assert!(
+            stacks.contains("<script>;Tort;Tort.init;clock "),
+            "{stacks}"
+        );
+        assert!(stacks.contains("<script>;fib;fib;fib "), "{stacks}");
+        assert!(profile.report().starts_with("function "));
+    }
+}
@@ -0,0 +1,199 @@
+//! Serde support for Malis values, behind the `serde` feature. Numbers, strings, booleans and
+//! `nil` map to their serde counterparts, lists to sequences and instances to maps of their fields.
+//! Functions, classes and host objects could not be serialized.
+use super::{List, MalisInstance, MalisObject};
+use serde::{
+    de::{self, MapAccess, SeqAccess, Visitor},
+    ser, Deserialize, Deserializer, Serialize, Serializer,
+};
+use std::fmt;
+
+impl Serialize for MalisObject {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            MalisObject::Nil => serializer.serialize_unit(),
+            MalisObject::Boolean(value) => serializer.serialize_bool(*value),
+            // Numbers without a fractional part are written as integers, like `print` does
+            MalisObject::Number(value) if value.fract() == 0.0 && value.abs() < i64::MAX as f32 => {
+                serializer.serialize_i64(*value as i64)
+            }
+            MalisObject::Number(value) => serializer.serialize_f32(*value),
+            MalisObject::StringValue(value) => serializer.serialize_str(value),
+            MalisObject::Instance(instance) => instance.serialize(serializer),
+            MalisObject::List(list) => list.serialize(serializer),
+            MalisObject::NativeFunction(_)
+            | MalisObject::UserFunction(_)
+            | MalisObject::Class(_)
+            | MalisObject::Host(_) => Err(ser::Error::custom(format!(
+                "Cannot serialize {self} of type {}, only values and instances could be serialized",
+                self.type_name()
+            ))),
+        }
+    }
+}
+
+// Only the fields are written, the class and its methods stay behind
+impl Serialize for MalisInstance {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_map(self.fields())
+    }
+}
+
+// A list containing itself has no end, so it fails instead
+impl Serialize for List {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.walk(|values| serializer.collect_seq(values))
+            .unwrap_or_else(|| {
+                Err(ser::Error::custom(
+                    "Cannot serialize a list containing itself",
+                ))
+            })
+    }
+}
+
+impl<'de> Deserialize<'de> for MalisObject {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_any(MalisVisitor)
+    }
+}
+
+struct MalisVisitor;
+
+impl<'de> Visitor<'de> for MalisVisitor {
+    type Value = MalisObject;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "a number, string, boolean, null, sequence or map")
+    }
+
+    fn visit_bool<E: de::Error>(self, value: bool) -> Result<Self::Value, E> {
+        Ok(MalisObject::Boolean(value))
+    }
+
+    // Malis numbers are `f32`, so wider numbers lose precision on the way in
+    fn visit_i64<E: de::Error>(self, value: i64) -> Result<Self::Value, E> {
+        Ok(MalisObject::Number(value as f32))
+    }
+
+    fn visit_u64<E: de::Error>(self, value: u64) -> Result<Self::Value, E> {
+        Ok(MalisObject::Number(value as f32))
+    }
+
+    fn visit_f64<E: de::Error>(self, value: f64) -> Result<Self::Value, E> {
+        Ok(MalisObject::Number(value as f32))
+    }
+
+    fn visit_str<E: de::Error>(self, value: &str) -> Result<Self::Value, E> {
+        Ok(MalisObject::StringValue(value.to_string()))
+    }
+
+    fn visit_string<E: de::Error>(self, value: String) -> Result<Self::Value, E> {
+        Ok(MalisObject::StringValue(value))
+    }
+
+    fn visit_unit<E: de::Error>(self) -> Result<Self::Value, E> {
+        Ok(MalisObject::Nil)
+    }
+
+    fn visit_none<E: de::Error>(self) -> Result<Self::Value, E> {
+        Ok(MalisObject::Nil)
+    }
+
+    fn visit_some<D: Deserializer<'de>>(self, deserializer: D) -> Result<Self::Value, D::Error> {
+        MalisObject::deserialize(deserializer)
+    }
+
+    // Maps become plain objects, whose fields are the entries of the map
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+        let mut instance = MalisInstance::object();
+        while let Some((name, value)) = map.next_entry::<String, MalisObject>()? {
+            instance.set_field(&name, value);
+        }
+        Ok(MalisObject::Instance(instance))
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+        let mut values = vec![];
+        while let Some(value) = seq.next_element()? {
+            values.push(value);
+        }
+        Ok(MalisObject::List(List::new(values)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{interpreter::MalisObject, Malis, SharedBuffer};
+
+    #[test]
+    fn serializes_script_results() {
+        let mut malis =
+            Malis::with_output(SharedBuffer::new(), SharedBuffer::new()).expect("Failed to create");
+        malis
+            .run(
+                "\
+class Comanda {}
+var comanda = Comanda();
+comanda.produs = \"carte\";
+comanda.pret = 12.5;
+comanda.cantitate = 2;
+comanda.livrata = false;
+comanda.nota = nil;
+",
+                false,
+            )
+            .expect("Failed to run");
+        let comanda = malis.get_global("comanda").expect("No order");
+        assert_eq!(
+            serde_json::to_string(&comanda).expect("Failed to serialize"),
+            r#"{"cantitate":2,"livrata":false,"nota":null,"pret":12.5,"produs":"carte"}"#
+        );
+
+        let comanda = malis.eval("Comanda").expect("No class");
+        let err = serde_json::to_string(&comanda).expect_err("Classes are not values");
+        assert_eq!(
+            err.to_string(),
+            "Cannot serialize <class Comanda> of type class, only values and instances could be \
+             serialized"
+        );
+
+        malis
+            .run("var bucla = list(1); bucla.push(bucla);", false)
+            .expect("Failed to run");
+        let bucla = malis.get_global("bucla").expect("No list");
+        let err = serde_json::to_string(&bucla).expect_err("The list has no end");
+        assert_eq!(err.to_string(), "Cannot serialize a list containing itself");
+    }
+
+    #[test]
+    fn deserializes_configs() {
+        let config: MalisObject =
+            serde_json::from_str(r#"{"port": 8080, "gazda": {"nume": "local", "sigur": true}}"#)
+                .expect("Failed to deserialize");
+        let output = SharedBuffer::new();
+        let mut malis =
+            Malis::with_output(output.clone(), SharedBuffer::new()).expect("Failed to create");
+        malis.set_global("config", config);
+        malis
+            .run(
+                "print config.port + 1; print config.gazda.nume; print config.gazda.sigur;",
+                false,
+            )
+            .expect("Failed to run");
+        assert_eq!(output.contents(), "8081\nlocal\ntrue\n");
+
+        assert_eq!(
+            serde_json::from_str::<MalisObject>("null").ok(),
+            Some(MalisObject::Nil)
+        );
+        let Ok(MalisObject::List(list)) = serde_json::from_str::<MalisObject>("[1, [\"doi\"]]")
+        else {
+            panic!("Sequences are lists");
+        };
+        assert_eq!(list.to_string(), r#"[1, ["doi"]]"#);
+        assert_eq!(
+            serde_json::to_string(&MalisObject::List(list)).expect("Failed to serialize"),
+            r#"[1,["doi"]]"#
+        );
+    }
+}
@@ -1,4 +1,8 @@
-use super::{Interpreter, MalisCallable, MalisClass, MalisObject, UserFunction};
+use super::{
+    coverage::BranchKind,
+    natives::{list, string},
+    Interpreter, MalisCallable, MalisClass, MalisObject, UserFunction,
+};
 use crate::{
     ast::{
         Binary, Call, ClassDeclaration, Expr, FunctionDeclaration, GetExpr, Group, IfStmt, Literal,
@@ -11,6 +15,7 @@ use crate::{
 };
 use std::cell::RefCell;
 use std::collections::BTreeMap;
+use std::io::Write;
 use std::rc::Rc;
 
 impl StmtVisitor<Result<(), RuntimeError>> for Interpreter {
@@ -21,7 +26,7 @@ impl StmtVisitor<Result<(), RuntimeError>> for Interpreter {
 
     fn visit_print_stmt(&mut self, stmt: &Expr) -> Result<(), RuntimeError> {
         let expr = self.evaluate(stmt)?;
-        println!("{expr}");
+        writeln!(self.output, "{expr}")?;
         Ok(())
     }
 
@@ -44,9 +49,12 @@ impl StmtVisitor<Result<(), RuntimeError>> for Interpreter {
     }
 
     fn visit_if_stmt(&mut self, if_stmt: &IfStmt) -> Result<(), RuntimeError> {
-        let cond = self.evaluate(&if_stmt.condition)?;
+        let cond = self.evaluate(&if_stmt.condition)?.is_truthy();
+        if self.coverage.is_some() {
+            self.cover_condition(BranchKind::If, &if_stmt.condition, cond);
+        }
 
-        if cond.is_truthy() {
+        if cond {
             self.execute(&if_stmt.then_branch)?;
         } else if let Some(branch) = &if_stmt.else_branch {
             self.execute(branch)?;
@@ -56,7 +64,14 @@ impl StmtVisitor<Result<(), RuntimeError>> for Interpreter {
     }
 
     fn visit_while_stmt(&mut self, while_stmt: &WhileStmt) -> Result<(), RuntimeError> {
-        while self.evaluate(&while_stmt.condition)?.is_truthy() {
+        loop {
+            let cond = self.evaluate(&while_stmt.condition)?.is_truthy();
+            if self.coverage.is_some() {
+                self.cover_condition(BranchKind::While, &while_stmt.condition, cond);
+            }
+            if !cond {
+                break;
+            }
             self.execute(&while_stmt.stmt)?;
         }
 
@@ -252,9 +267,12 @@ impl ExprVisitor<Result<MalisObject, RuntimeError>> for Interpreter {
         }
     }
     fn visit_ternary(&mut self, ternary: &Ternary) -> Result<MalisObject, RuntimeError> {
-        let cond = self.evaluate(&ternary.first)?;
+        let cond = self.evaluate(&ternary.first)?.is_truthy();
+        if self.coverage.is_some() {
+            self.cover_ternary(ternary, cond);
+        }
 
-        if cond.is_truthy() {
+        if cond {
             ternary.second.walk(self)
         } else {
             ternary.third.walk(self)
@@ -299,7 +317,7 @@ impl ExprVisitor<Result<MalisObject, RuntimeError>> for Interpreter {
                 .borrow_mut()
                 .insert_at(*distance, ident.lexeme(), malis_object)?
         } else {
-            self._globals
+            self.globals
                 .borrow_mut()
                 .insert(ident.lexeme(), malis_object)?
         };
@@ -318,18 +336,16 @@ impl ExprVisitor<Result<MalisObject, RuntimeError>> for Interpreter {
         let left_object = logical.left.walk(self)?;
         let left_object_is_true = left_object.is_truthy();
 
-        match logical.operator.t_type() {
-            TokenType::Keyword(Keyword::Or) => {
-                if left_object_is_true {
-                    return Ok(left_object);
-                }
-            }
-            TokenType::Keyword(Keyword::And) => {
-                if !left_object_is_true {
-                    return Ok(left_object);
-                }
-            }
+        let short_circuits = match logical.operator.t_type() {
+            TokenType::Keyword(Keyword::Or) => left_object_is_true,
+            TokenType::Keyword(Keyword::And) => !left_object_is_true,
             _ => unreachable!(),
+        };
+        if self.coverage.is_some() {
+            self.cover_logical(logical, short_circuits);
+        }
+        if short_circuits {
+            return Ok(left_object);
         }
 
         let right_object = logical.right.walk(self)?;
@@ -353,11 +369,12 @@ impl ExprVisitor<Result<MalisObject, RuntimeError>> for Interpreter {
             )));
         }
         // Check if the number of arguments matches the function's arity
-        if arguments.len() != callee.arity()? {
+        let arity = callee.arity()?;
+        if !arity.accepts(arguments.len()) {
             return Err(RuntimeError::InvalidArgumentsNumber(format!(
                 "[{:?}] Expected {} arguments but got {}.",
                 call.paren,
-                callee.arity()?,
+                arity,
                 arguments.len()
             )));
         }
@@ -372,7 +389,28 @@ impl ExprVisitor<Result<MalisObject, RuntimeError>> for Interpreter {
         // And only instances have properties
         if let MalisObject::Instance(instance) = object {
             // We access the property
-            instance.get(get.name())
+            let value = instance.get(get.name())?;
+            if let Some(observer) = self.observer.as_mut() {
+                observer.property_get(&instance, get.name().lexeme(), &value);
+            }
+            Ok(value)
+        } else if let MalisObject::Host(host) = object {
+            host.get(get.name().lexeme())?
+                .ok_or(RuntimeError::PropertyNotPresent(format!(
+                    "Property {:?} not present in {}",
+                    get.name().lexeme(),
+                    host.class_name()
+                )))
+        } else if let MalisObject::StringValue(text) = object {
+            // Strings have methods, implemented as natives bound to the string
+            string::method(&text, get.name().lexeme()).ok_or(RuntimeError::PropertyNotPresent(
+                format!("Method {:?} not present in strings", get.name().lexeme()),
+            ))
+        } else if let MalisObject::List(values) = object {
+            // And so do lists
+            list::method(&values, get.name().lexeme()).ok_or(RuntimeError::PropertyNotPresent(
+                format!("Method {:?} not present in lists", get.name().lexeme()),
+            ))
         } else {
             Err(RuntimeError::InvalidAccess(format!(
                 "Only instances have properties: {:?}",
@@ -391,7 +429,13 @@ impl ExprVisitor<Result<MalisObject, RuntimeError>> for Interpreter {
             // Evaluate the value we want to set
             let value = self.evaluate(set.value())?;
             // We set the property to the new value
-            instance.set(set.name(), value)?;
+            let value = instance.set(set.name(), value)?;
+            if let Some(observer) = self.observer.as_mut() {
+                observer.property_set(instance, set.name().lexeme(), &value);
+            }
+        } else if let MalisObject::Host(host) = &object {
+            let value = self.evaluate(set.value())?;
+            host.set(set.name().lexeme(), value)?;
         } else {
             return Err(RuntimeError::InvalidAccess(format!(
                 "Only instances have properties: {:?}",
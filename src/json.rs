@@ -0,0 +1,551 @@
+//! Minimal JSON support: a value type, a parser reporting where the input is wrong and
+//! a serializer. It is what we use to talk with editors and debuggers, which keeps `malis` free
+//! of dependencies for it.
+use crate::error::JsonError;
+use std::fmt;
+use std::iter::Peekable;
+use std::str::Chars;
+
+// How deep arrays and objects could be nested, such that malicious input could not overflow
+// the stack of the parser
+pub(crate) const MAX_DEPTH: usize = 512;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Json {
+    Null,
+    Bool(bool),
+    Number(f64),
+    String(String),
+    Array(Vec<Json>),
+    // Members are kept in the order they were written or inserted in
+    Object(Vec<(String, Json)>),
+}
+
+impl Json {
+    /// Parses a JSON document
+    ///
+    /// # Errors
+    ///
+    /// Fails with the line and column at which the input stops being valid JSON
+    pub fn parse(source: &str) -> Result<Json, JsonError> {
+        let mut parser = JsonParser {
+            chars: source.chars().peekable(),
+            line: 1,
+            column: 1,
+            depth: 0,
+        };
+        parser.skip_whitespace();
+        let value = parser.value()?;
+        parser.skip_whitespace();
+        if parser.chars.peek().is_some() {
+            return Err(parser.error("Unexpected characters after the JSON value"));
+        }
+        Ok(value)
+    }
+
+    /// Creates an object out of its members
+    pub fn object<K: Into<String>, I: IntoIterator<Item = (K, Json)>>(members: I) -> Json {
+        Json::Object(
+            members
+                .into_iter()
+                .map(|(key, value)| (key.into(), value))
+                .collect(),
+        )
+    }
+
+    /// Returns the member named `key`, if this is an object which has it
+    pub fn get(&self, key: &str) -> Option<&Json> {
+        match self {
+            Json::Object(members) => members
+                .iter()
+                .find(|(name, _)| name == key)
+                .map(|(_, value)| value),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Json::String(value) => Some(value.as_str()),
+            _ => None,
+        }
+    }
+
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            Json::Number(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    /// Returns the number held, if it is a non-negative integer
+    pub fn as_usize(&self) -> Option<usize> {
+        self.as_f64()
+            .filter(|value| value.fract() == 0.0 && *value >= 0.0)
+            .map(|value| value as usize)
+    }
+
+    pub fn as_bool(&self) -> Option<bool> {
+        match self {
+            Json::Bool(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    pub fn as_array(&self) -> Option<&[Json]> {
+        match self {
+            Json::Array(values) => Some(values.as_slice()),
+            _ => None,
+        }
+    }
+
+    /// Writes the value with every member of arrays and objects on its own line, indented by
+    /// `indent` spaces for each level. Without indentation it is the same as the compact form.
+    pub fn pretty(&self, indent: usize) -> String {
+        let mut out = String::new();
+        if indent == 0 {
+            out = self.to_string();
+        } else {
+            // Writing to a `String` does not fail
+            let _ = self.write_pretty(&mut out, indent, 0);
+        }
+        out
+    }
+
+    fn write_pretty<W: fmt::Write>(
+        &self,
+        f: &mut W,
+        indent: usize,
+        level: usize,
+    ) -> Result<(), fmt::Error> {
+        let new_line =
+            |f: &mut W, level: usize| write!(f, "\n{:width$}", "", width = indent * level);
+        match self {
+            Json::Array(values) if !values.is_empty() => {
+                write!(f, "[")?;
+                for (idx, value) in values.iter().enumerate() {
+                    if idx != 0 {
+                        write!(f, ",")?;
+                    }
+                    new_line(f, level + 1)?;
+                    value.write_pretty(f, indent, level + 1)?;
+                }
+                new_line(f, level)?;
+                write!(f, "]")
+            }
+            Json::Object(members) if !members.is_empty() => {
+                write!(f, "{{")?;
+                for (idx, (key, value)) in members.iter().enumerate() {
+                    if idx != 0 {
+                        write!(f, ",")?;
+                    }
+                    new_line(f, level + 1)?;
+                    write_string(f, key)?;
+                    write!(f, ": ")?;
+                    value.write_pretty(f, indent, level + 1)?;
+                }
+                new_line(f, level)?;
+                write!(f, "}}")
+            }
+            value => write!(f, "{value}"),
+        }
+    }
+}
+
+impl fmt::Display for Json {
+    // Writes the value as compact JSON
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> Result<(), fmt::Error> {
+        match self {
+            Json::Null => write!(f, "null"),
+            Json::Bool(value) => write!(f, "{value}"),
+            // JSON has no representation for infinities and NaN
+            Json::Number(value) if !value.is_finite() => write!(f, "null"),
+            Json::Number(value) => write!(f, "{value}"),
+            Json::String(value) => write_string(f, value),
+            Json::Array(values) => {
+                write!(f, "[")?;
+                for (idx, value) in values.iter().enumerate() {
+                    if idx != 0 {
+                        write!(f, ",")?;
+                    }
+                    write!(f, "{value}")?;
+                }
+                write!(f, "]")
+            }
+            Json::Object(members) => {
+                write!(f, "{{")?;
+                for (idx, (key, value)) in members.iter().enumerate() {
+                    if idx != 0 {
+                        write!(f, ",")?;
+                    }
+                    write_string(f, key)?;
+                    write!(f, ":{value}")?;
+                }
+                write!(f, "}}")
+            }
+        }
+    }
+}
+
+// Writes `value` as a quoted JSON string, escaping what needs to be escaped
+pub fn write_string<W: fmt::Write>(f: &mut W, value: &str) -> Result<(), fmt::Error> {
+    f.write_char('"')?;
+    for ch in value.chars() {
+        match ch {
+            '"' => f.write_str("\\\"")?,
+            '\\' => f.write_str("\\\\")?,
+            '\n' => f.write_str("\\n")?,
+            '\r' => f.write_str("\\r")?,
+            '\t' => f.write_str("\\t")?,
+            ch if (ch as u32) < 0x20 => write!(f, "\\u{:04x}", ch as u32)?,
+            ch => f.write_char(ch)?,
+        }
+    }
+    f.write_char('"')
+}
+
+impl From<&str> for Json {
+    fn from(value: &str) -> Self {
+        Json::String(value.to_string())
+    }
+}
+
+impl From<String> for Json {
+    fn from(value: String) -> Self {
+        Json::String(value)
+    }
+}
+
+impl From<bool> for Json {
+    fn from(value: bool) -> Self {
+        Json::Bool(value)
+    }
+}
+
+impl From<f64> for Json {
+    fn from(value: f64) -> Self {
+        Json::Number(value)
+    }
+}
+
+impl From<usize> for Json {
+    fn from(value: usize) -> Self {
+        Json::Number(value as f64)
+    }
+}
+
+impl From<i64> for Json {
+    fn from(value: i64) -> Self {
+        Json::Number(value as f64)
+    }
+}
+
+impl From<Vec<Json>> for Json {
+    fn from(values: Vec<Json>) -> Self {
+        Json::Array(values)
+    }
+}
+
+impl<T: Into<Json>> From<Option<T>> for Json {
+    fn from(value: Option<T>) -> Self {
+        value.map_or(Json::Null, Into::into)
+    }
+}
+
+// Recursive descent parser over the characters of a JSON document
+struct JsonParser<'a> {
+    chars: Peekable<Chars<'a>>,
+    // Position of the next character
+    line: usize,
+    column: usize,
+    // How many arrays and objects we are currently in
+    depth: usize,
+}
+
+impl JsonParser<'_> {
+    fn value(&mut self) -> Result<Json, JsonError> {
+        match self.chars.peek().copied() {
+            Some('n') => self.keyword("null", Json::Null),
+            Some('t') => self.keyword("true", Json::Bool(true)),
+            Some('f') => self.keyword("false", Json::Bool(false)),
+            Some('"') => Ok(Json::String(self.string()?)),
+            Some('[') => self.array(),
+            Some('{') => self.object(),
+            Some(ch) if ch == '-' || ch.is_ascii_digit() => self.number(),
+            Some(ch) => Err(self.error(&format!("Unexpected character {ch:?}"))),
+            None => Err(self.error("Unexpected end of input")),
+        }
+    }
+
+    fn keyword(&mut self, keyword: &str, value: Json) -> Result<Json, JsonError> {
+        // A misspelled keyword is reported where it starts
+        let start = (self.line, self.column);
+        for expected in keyword.chars() {
+            if self.chars.peek() != Some(&expected) {
+                return Err(self.error_at(&format!("Expected `{keyword}`"), start));
+            }
+            self.next();
+        }
+        Ok(value)
+    }
+
+    fn array(&mut self) -> Result<Json, JsonError> {
+        self.enter()?;
+        // The `[`
+        self.next();
+        let mut values = vec![];
+        self.skip_whitespace();
+        if self.chars.peek() == Some(&']') {
+            self.next();
+            self.depth -= 1;
+            return Ok(Json::Array(values));
+        }
+
+        loop {
+            self.skip_whitespace();
+            values.push(self.value()?);
+            self.skip_whitespace();
+            match self.next() {
+                Some(',') => continue,
+                Some(']') => break,
+                _ => return Err(self.error_before("Expected ',' or ']' in array")),
+            }
+        }
+        self.depth -= 1;
+        Ok(Json::Array(values))
+    }
+
+    fn object(&mut self) -> Result<Json, JsonError> {
+        self.enter()?;
+        // The `{`
+        self.next();
+        let mut members = vec![];
+        self.skip_whitespace();
+        if self.chars.peek() == Some(&'}') {
+            self.next();
+            self.depth -= 1;
+            return Ok(Json::Object(members));
+        }
+
+        loop {
+            self.skip_whitespace();
+            if self.chars.peek() != Some(&'"') {
+                return Err(self.error("Expected a string as object key"));
+            }
+            let key = self.string()?;
+            self.skip_whitespace();
+            if self.next() != Some(':') {
+                return Err(self.error_before("Expected ':' after object key"));
+            }
+            self.skip_whitespace();
+            let value = self.value()?;
+            members.push((key, value));
+            self.skip_whitespace();
+            match self.next() {
+                Some(',') => continue,
+                Some('}') => break,
+                _ => return Err(self.error_before("Expected ',' or '}' in object")),
+            }
+        }
+        self.depth -= 1;
+        Ok(Json::Object(members))
+    }
+
+    fn string(&mut self) -> Result<String, JsonError> {
+        // The opening quote
+        self.next();
+        let mut value = String::new();
+        loop {
+            match self.next() {
+                Some('"') => return Ok(value),
+                Some('\\') => match self.next() {
+                    Some('"') => value.push('"'),
+                    Some('\\') => value.push('\\'),
+                    Some('/') => value.push('/'),
+                    Some('b') => value.push('\u{8}'),
+                    Some('f') => value.push('\u{c}'),
+                    Some('n') => value.push('\n'),
+                    Some('r') => value.push('\r'),
+                    Some('t') => value.push('\t'),
+                    Some('u') => value.push(self.unicode_escape()?),
+                    _ => return Err(self.error_before("Invalid escape sequence in string")),
+                },
+                Some(ch) if (ch as u32) < 0x20 => {
+                    return Err(self.error_before("Control character in string"))
+                }
+                Some(ch) => value.push(ch),
+                None => return Err(self.error("Unterminated string")),
+            }
+        }
+    }
+
+    // Parses the code unit following `\u`, combining surrogate pairs into a single character
+    fn unicode_escape(&mut self) -> Result<char, JsonError> {
+        let first = self.hex_code_unit()?;
+        if !(0xD800..0xDC00).contains(&first) {
+            return char::from_u32(first).ok_or_else(|| self.error("Invalid unicode escape"));
+        }
+        // A high surrogate has to be followed by a low one
+        if self.next() != Some('\\') || self.next() != Some('u') {
+            return Err(self.error_before("Expected a low surrogate after a high surrogate"));
+        }
+        let second = self.hex_code_unit()?;
+        if !(0xDC00..0xE000).contains(&second) {
+            return Err(self.error("Invalid low surrogate"));
+        }
+        let code = 0x10000 + ((first - 0xD800) << 10) + (second - 0xDC00);
+        char::from_u32(code).ok_or_else(|| self.error("Invalid unicode escape"))
+    }
+
+    fn hex_code_unit(&mut self) -> Result<u32, JsonError> {
+        let mut code = 0;
+        for _ in 0..4 {
+            let digit = self
+                .next()
+                .and_then(|ch| ch.to_digit(16))
+                .ok_or_else(|| self.error_before("Expected 4 hexadecimal digits"))?;
+            code = code * 16 + digit;
+        }
+        Ok(code)
+    }
+
+    fn number(&mut self) -> Result<Json, JsonError> {
+        let start = (self.line, self.column);
+        let mut text = String::new();
+        if self.chars.peek() == Some(&'-') {
+            text.push('-');
+            self.next();
+        }
+        // Leading zeros are not allowed
+        match self.chars.peek() {
+            Some('0') => {
+                text.push('0');
+                self.next();
+            }
+            Some(ch) if ch.is_ascii_digit() => self.digits(&mut text),
+            _ => return Err(self.error("Expected a digit")),
+        }
+        if self.chars.peek() == Some(&'.') {
+            text.push('.');
+            self.next();
+            if !self.chars.peek().is_some_and(char::is_ascii_digit) {
+                return Err(self.error("Expected a digit after the decimal point"));
+            }
+            self.digits(&mut text);
+        }
+        if let Some(exponent @ ('e' | 'E')) = self.chars.peek().copied() {
+            text.push(exponent);
+            self.next();
+            if let Some(sign @ ('+' | '-')) = self.chars.peek().copied() {
+                text.push(sign);
+                self.next();
+            }
+            if !self.chars.peek().is_some_and(char::is_ascii_digit) {
+                return Err(self.error("Expected a digit in the exponent"));
+            }
+            self.digits(&mut text);
+        }
+        text.parse::<f64>()
+            .map(Json::Number)
+            .map_err(|_| self.error_at("Invalid number", start))
+    }
+
+    fn digits(&mut self, text: &mut String) {
+        while let Some(ch) = self.chars.peek().copied().filter(char::is_ascii_digit) {
+            text.push(ch);
+            self.next();
+        }
+    }
+
+    fn skip_whitespace(&mut self) {
+        while matches!(self.chars.peek(), Some(' ' | '\t' | '\n' | '\r')) {
+            self.next();
+        }
+    }
+
+    fn enter(&mut self) -> Result<(), JsonError> {
+        self.depth += 1;
+        if self.depth > MAX_DEPTH {
+            return Err(self.error("Too many nested arrays and objects"));
+        }
+        Ok(())
+    }
+
+    // Consumes the next character, keeping track of its position
+    fn next(&mut self) -> Option<char> {
+        let ch = self.chars.next()?;
+        if ch == '\n' {
+            self.line += 1;
+            self.column = 1;
+        } else {
+            self.column += 1;
+        }
+        Some(ch)
+    }
+
+    // Error at the position of the next character
+    fn error(&self, message: &str) -> JsonError {
+        JsonError {
+            message: message.to_string(),
+            line: self.line,
+            column: self.column,
+        }
+    }
+
+    // Error at the given line and column, like the start of a value
+    fn error_at(&self, message: &str, (line, column): (usize, usize)) -> JsonError {
+        JsonError {
+            message: message.to_string(),
+            line,
+            column,
+        }
+    }
+
+    // Error at the position of the character that was just consumed
+    fn error_before(&self, message: &str) -> JsonError {
+        JsonError {
+            message: message.to_string(),
+            line: self.line,
+            column: self.column.saturating_sub(1).max(1),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Json;
+
+    #[test]
+    fn parse_and_serialize() {
+        let source = r#" {"a": [1, -2.5e1, true, null], "b": "x\"é😀\n", "c": {}} "#;
+        let value = Json::parse(source).expect("Failed to parse");
+        assert_eq!(
+            value.get("a").and_then(Json::as_array).map(<[_]>::len),
+            Some(4)
+        );
+        assert_eq!(value.get("b").and_then(Json::as_str), Some("x\"é😀\n"));
+        assert_eq!(
+            value.to_string(),
+            r#"{"a":[1,-25,true,null],"b":"x\"é😀\n","c":{}}"#
+        );
+        assert_eq!(Json::parse(&value.to_string()), Ok(value.clone()));
+        assert_eq!(
+            value.pretty(2),
+            "{\n  \"a\": [\n    1,\n    -25,\n    true,\n    null\n  ],\n  \"b\": \"x\\\"é😀\\n\",\n  \"c\": {}\n}"
+        );
+        assert_eq!(Json::parse(&value.pretty(4)), Ok(value));
+    }
+
+    #[test]
+    fn errors_have_positions() {
+        let err = Json::parse("{\n  \"a\": [1,\n  2,]\n}").expect_err("Parsed invalid JSON");
+        assert_eq!((err.line, err.column), (3, 5));
+        let err = Json::parse("[01]").expect_err("Parsed invalid JSON");
+        assert_eq!((err.line, err.column), (1, 3));
+        let err = Json::parse("{\n  \"a\": tru\n}").expect_err("Parsed invalid JSON");
+        assert_eq!((err.line, err.column), (2, 8));
+        assert!(Json::parse(&"[".repeat(1000)).is_err());
+        assert!(Json::parse("\"abc").is_err());
+        assert!(Json::parse("1 2").is_err());
+    }
+}
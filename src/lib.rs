@@ -1,123 +1,519 @@
 pub mod ast;
+pub mod dap;
+mod debug;
 mod environment;
 mod error;
+mod formatter;
 pub mod interpreter;
+mod json;
+pub mod lsp;
 mod parser;
+mod repl;
 pub mod resolver;
 mod scanner;
 mod token;
 mod visit;
 
-pub use error::MalisError;
-pub use interpreter::Interpreter;
+use ast::Stmt;
+use environment::EnvironmentError;
+pub use error::{MalisError, RuntimeError};
+use formatter::Formatter;
+pub use interpreter::{
+    Callable, CancellationHandle, Coverage, FromMalis, Interpreter, IntoMalis, Limits,
+    LineCallback, MalisObject, NativeModule, Observer, Profile, SharedBuffer,
+};
 use parser::Parser;
+use repl::{Completions, MalisHelper, ReplLine};
 use resolver::Resolver;
+use rustyline::{error::ReadlineError, history::DefaultHistory, Editor};
 use scanner::Scanner;
 use std::{
+    collections::HashMap,
     fs,
-    io::{self, Write},
+    io::{self, BufRead, IsTerminal, Write},
     path::Path,
 };
 use visit::AstPrinter;
 
+/// Output format used when printing the syntax tree of a script
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum AstFormat {
+    /// One S-expression per statement, as produced by the `AstPrinter`
+    #[default]
+    SExpr,
+    /// The same S-expressions, broken into indented lines for each nested node
+    Tree,
+}
+
 #[derive(Default)]
 pub struct Malis {
     // Keeps track of wheather the code has an error and to avoid executing it.
     _had_error: bool,
     interpreter: Interpreter,
+    // How much of the pipeline's intermediate state is reported while running code. At 1 we print
+    // the syntax tree of the executed code and at 2 we also print the scanned tokens.
+    verbosity: u8,
+    // Inputs of the interactive prompt that executed successfully, which could be saved to a
+    // script with `:save`
+    session: Vec<String>,
 }
 
 impl Malis {
+    /// Creates a new instance, where the values printed by scripts are written to `output` and
+    /// errors and debugging information are written to `diagnostics`
+    pub fn with_output<O: Write + 'static, D: Write + 'static>(
+        output: O,
+        diagnostics: D,
+    ) -> Result<Self, MalisError> {
+        Ok(Self {
+            _had_error: false,
+            interpreter: Interpreter::with_output(output, diagnostics)?,
+            verbosity: 0,
+            session: vec![],
+        })
+    }
+
     pub fn execute<P: AsRef<Path>>(path: P) -> Result<(), MalisError> {
         let mut malis = Self::default();
         let source = fs::read_to_string(path)?;
         malis.run(source.as_str(), false)
     }
 
+    pub fn set_verbosity(&mut self, verbosity: u8) {
+        self.verbosity = verbosity;
+    }
+
+    /// Sets the command line arguments that are passed to the script
+    pub fn set_args(&mut self, args: Vec<String>) {
+        self.interpreter.set_args(args);
+    }
+
+    /// Starts or stops profiling the code executed from now on
+    pub fn set_profiling(&mut self, enabled: bool) {
+        if enabled {
+            self.interpreter.start_profiling();
+        } else {
+            self.interpreter.stop_profiling();
+        }
+    }
+
+    /// Stops profiling, returning what was measured since it started
+    pub fn take_profile(&mut self) -> Option<Profile> {
+        self.interpreter.stop_profiling()
+    }
+
+    /// Starts or stops recording the coverage of the code executed from now on
+    pub fn set_coverage(&mut self, enabled: bool) {
+        if enabled {
+            self.interpreter.start_coverage();
+        } else {
+            self.interpreter.stop_coverage();
+        }
+    }
+
+    /// Stops recording coverage, returning what was covered since it started
+    pub fn take_coverage(&mut self) -> Option<Coverage> {
+        self.interpreter.stop_coverage()
+    }
+
+    /// Limits the work done by the scripts run from now on
+    pub fn set_limits(&mut self, limits: Limits) {
+        self.interpreter.set_limits(limits);
+    }
+
+    /// Returns a handle for cancelling the running script from another thread
+    pub fn cancellation_handle(&mut self) -> CancellationHandle {
+        self.interpreter.cancellation_handle()
+    }
+
+    /// Sets the observer notified of everything the scripts execute from now on
+    pub fn set_observer<O: Observer + 'static>(&mut self, observer: O) {
+        self.interpreter.set_observer(observer);
+    }
+
+    /// Removes the observer, if there is one
+    pub fn clear_observer(&mut self) {
+        self.interpreter.clear_observer();
+    }
+
     pub fn run(&mut self, bytes: &str, is_repl: bool) -> Result<(), MalisError> {
         let mut scanner = Scanner::new(bytes);
-        let maybe_tokens = scanner.scan_tokens();
+        let tokens = scanner.scan_tokens()?;
 
-        match maybe_tokens {
-            Ok(tokens) => {
-                let mut parser = Parser::new(tokens);
-                let stmts = parser.parse()?;
+        if self.verbosity > 1 {
+            writeln!(
+                self.interpreter.diagnostics(),
+                "Tokens\n{}",
+                format_tokens(&tokens)
+            )?;
+        }
+
+        let mut parser = Parser::new(tokens);
+        let mut ast_printer = AstPrinter;
+
+        let ast = match parser.parse() {
+            Ok(stmts) => {
                 let mut resolver = Resolver::new(&mut self.interpreter);
                 resolver.resolve(&stmts)?;
-                let mut ast_printer = AstPrinter;
-
-                let ast = if !stmts.is_empty() || !is_repl {
-                    let ast = ast_printer.print_stmt(&stmts);
-                    self.interpreter.interpret(stmts.as_slice())?;
-                    ast
+                let ast = ast_printer.print_stmt(&stmts);
+                self.interpreter.interpret(stmts.as_slice())?;
+                ast
+            }
+            Err(err) => {
+                // In the REPL, input which is not a valid statement could still be a bare
+                // expression, in which case we evaluate it and print its value
+                let maybe_expr = if is_repl {
+                    parser.parse_expression().ok()
                 } else {
-                    // Reset the parser such that we could parse in expression form
-                    parser.reset();
-                    let expr = parser.separator()?;
-                    println!("{}", self.interpreter.evaluate(&expr)?);
-                    ast_printer.print_expr(&expr)
+                    None
                 };
-
-                println!("Ast {}", ast);
+                let Some(expr) = maybe_expr else {
+                    return Err(err.into());
+                };
+                let value = self.interpreter.evaluate(&expr)?;
+                writeln!(self.interpreter.output(), "{}", value)?;
+                ast_printer.print_expr(&expr)
             }
-            // Print all the errors we found during scanning
-            Err(scanner_errors) => scanner_errors.iter().for_each(|e| println!("{e:?}")),
+        };
+
+        if self.verbosity > 0 {
+            writeln!(self.interpreter.diagnostics(), "Ast {}", ast)?;
         }
         Ok(())
     }
 
+    /// Executes `source`, returning the value of its last statement when it is an expression, or
+    /// `nil` otherwise. A lone expression does not need to end with a semicolon.
+    pub fn eval(&mut self, source: &str) -> Result<MalisObject, MalisError> {
+        let mut scanner = Scanner::new(source);
+        let tokens = scanner.scan_tokens()?;
+        let mut parser = Parser::new(tokens);
+        let stmts = match parser.parse() {
+            Ok(stmts) => stmts,
+            // The expression runs like a statement would, hooks and all
+            Err(err) => vec![Stmt::Expr(parser.parse_expression().map_err(|_| err)?)],
+        };
+        let mut resolver = Resolver::new(&mut self.interpreter);
+        resolver.resolve(&stmts)?;
+        Ok(self.interpreter.interpret_value(stmts.as_slice())?)
+    }
+
+    /// Defines every function and constant of `module` as a global, for the scripts run from now on
+    pub fn install(&mut self, module: NativeModule) -> Result<(), MalisError> {
+        Ok(self.interpreter.install(module)?)
+    }
+
+    /// Defines the global `name` for the scripts run from now on, replacing its previous value
+    pub fn set_global(&mut self, name: &str, value: MalisObject) {
+        self.interpreter.set_global(name, value);
+    }
+
+    /// Returns the value of the global named `name`, if there is one
+    pub fn get_global(&self, name: &str) -> Option<MalisObject> {
+        self.interpreter.get_global(name)
+    }
+
+    /// Calls the global function, native or class named `name` with `arguments`, returning what
+    /// it returns
+    pub fn call_function(
+        &mut self,
+        name: &str,
+        arguments: Vec<MalisObject>,
+    ) -> Result<MalisObject, MalisError> {
+        Ok(self.interpreter.call_function(name, arguments)?)
+    }
+
+    /// Returns the global function, native or class named `name`, for calling it later
+    pub fn callable(&self, name: &str) -> Result<Callable, MalisError> {
+        let value = self.get_global(name).ok_or_else(|| {
+            RuntimeError::EnvironmentError(EnvironmentError::UndefinedVariable(name.to_string()))
+        })?;
+        Ok(Callable::new(value)?)
+    }
+
+    /// Calls `callable` with `arguments`, returning what it returns
+    pub fn call(
+        &mut self,
+        callable: &Callable,
+        arguments: Vec<MalisObject>,
+    ) -> Result<MalisObject, MalisError> {
+        Ok(callable.call(&mut self.interpreter, arguments)?)
+    }
+
+    /// Scans, parses and resolves `source` without executing it
+    pub fn check(source: &str) -> Result<(), MalisError> {
+        let mut scanner = Scanner::new(source);
+        let tokens = scanner.scan_tokens()?;
+        let mut parser = Parser::new(tokens);
+        let stmts = parser.parse()?;
+        let mut interpreter = Interpreter::new()?;
+        let mut resolver = Resolver::new(&mut interpreter);
+        resolver.resolve(&stmts)?;
+        Ok(())
+    }
+
+    /// Returns the tokens scanned from `source`, one per line
+    pub fn tokens(source: &str) -> Result<String, MalisError> {
+        let mut scanner = Scanner::new(source);
+        let tokens = scanner.scan_tokens()?;
+        Ok(format_tokens(&tokens))
+    }
+
+    /// Returns the syntax tree parsed from `source`, printed in the desired `format`
+    pub fn ast(source: &str, format: AstFormat) -> Result<String, MalisError> {
+        let mut scanner = Scanner::new(source);
+        let tokens = scanner.scan_tokens()?;
+        let mut parser = Parser::new(tokens);
+        let stmts = parser.parse()?;
+        let mut ast_printer = AstPrinter;
+
+        let ast = match format {
+            AstFormat::SExpr => ast_printer.print_stmt(&stmts),
+            AstFormat::Tree => stmts
+                .iter()
+                .map(|stmt| ast_printer.print_tree(stmt))
+                .collect::<Vec<_>>()
+                .join("\n"),
+        };
+        Ok(ast)
+    }
+
+    /// Returns `source` formatted in the canonical style of Malis, keeping its comments.
+    /// Formatting is idempotent: formatting the result again does not change it.
+    ///
+    /// # Errors
+    ///
+    /// Only code that parses could be formatted
+    pub fn format(source: &str) -> Result<String, MalisError> {
+        let mut scanner = Scanner::with_comments(source);
+        let tokens = scanner.scan_tokens()?;
+        let code = tokens
+            .iter()
+            .filter(|token| token.t_type() != &token::TokenType::Comment)
+            .cloned()
+            .collect::<Vec<_>>();
+        let stmts = Parser::new(code).parse()?;
+
+        let formatted = Formatter::new(tokens).format();
+
+        // The formatter only moves whitespace and comments around, so the code has to parse into
+        // the same tree as before
+        let mut scanner = Scanner::new(formatted.as_str());
+        let formatted_stmts = Parser::new(scanner.scan_tokens()?).parse()?;
+        let mut ast_printer = AstPrinter;
+        if ast_printer.print_stmt(&stmts) != ast_printer.print_stmt(&formatted_stmts) {
+            return Err(MalisError::FormatterError(
+                "Formatting changed the meaning of the code".to_string(),
+            ));
+        }
+        Ok(formatted)
+    }
+
     /// Fires up an interactive command prompt which is capable of executing code one line at
     /// a time.
+    ///
+    /// When used from a terminal, lines could be edited, the history of previous sessions is
+    /// available (and searchable with `Ctrl-R`) and `Tab` completes keywords, global names and
+    /// the fields of global instances.
     // Also known as "REPL", from Lisp:
     // - Read a line of input
     // - Evaluate it
     // - Print the result
     // - Loop and do it all over again
-    pub fn interactive() -> Result<(), MalisError> {
-        let mut malis = Malis::default();
-        // Get new handles to the stdin and stdout streams
-        let stdin = io::stdin();
-        let mut stdout = io::stdout();
+    pub fn interactive(&mut self) -> Result<(), MalisError> {
+        if !io::stdin().is_terminal() {
+            return self.repl(io::stdin().lock());
+        }
+
+        let mut editor = Editor::<MalisHelper, DefaultHistory>::new()?;
+        editor.set_helper(Some(MalisHelper::default()));
+        let history_path = repl::history_path();
+        if let Some(path) = history_path.as_ref() {
+            // There is no history yet the first time the prompt is used
+            let _ = editor.load_history(path);
+        }
+
+        let result = self.repl_loop(|malis, prompt| {
+            if let Some(helper) = editor.helper_mut() {
+                helper.set_completions(malis.completions());
+            }
+            match editor.readline(prompt) {
+                Ok(line) => {
+                    if !line.trim().is_empty() {
+                        editor.add_history_entry(line.as_str())?;
+                    }
+                    Ok(ReplLine::Line(line + "\n"))
+                }
+                Err(ReadlineError::Interrupted) => Ok(ReplLine::Interrupted),
+                Err(ReadlineError::Eof) => Ok(ReplLine::Eof),
+                Err(err) => Err(err.into()),
+            }
+        });
+
+        if let Some(path) = history_path.as_ref() {
+            if let Err(err) = editor.save_history(path) {
+                writeln!(
+                    self.interpreter.diagnostics(),
+                    "Could not save history to {}: {err}",
+                    path.display()
+                )?;
+            }
+        }
+        result
+    }
+
+    /// Runs the interactive prompt over the lines read from `input`. The results are written to
+    /// the interpreter's output, while the prompt and errors go to its diagnostics, such that the
+    /// output only holds what the code printed.
+    ///
+    /// Input which is not yet complete, such as a function whose body is not closed, is
+    /// gathered over several lines under a `...` continuation prompt before it is executed.
+    pub fn repl<R: BufRead>(&mut self, mut input: R) -> Result<(), MalisError> {
+        self.repl_loop(|malis, prompt| {
+            // Write the prompt and flush it to make sure we print it
+            let diagnostics = malis.interpreter.diagnostics();
+            diagnostics.write_all(prompt.as_bytes())?;
+            diagnostics.flush()?;
+
+            let mut line = String::new();
+            // If no bytes were read, it means we reached `End-of-File` or `Ctrl-D` was pressed.
+            if input.read_line(&mut line)? == 0 {
+                Ok(ReplLine::Eof)
+            } else {
+                Ok(ReplLine::Line(line))
+            }
+        })
+    }
+
+    // Drives the interactive prompt, using `read_line` to show a prompt and read the next line
+    fn repl_loop<F>(&mut self, mut read_line: F) -> Result<(), MalisError>
+    where
+        F: FnMut(&mut Self, &str) -> Result<ReplLine, MalisError>,
+    {
         // Create a new buffer to store the input
         let mut buffer = String::new();
 
         loop {
-            // Write the new line identifier
-            let _ = stdout.write(b"> ")?;
-            // Flush it to make sure we print it
-            stdout.flush()?;
-            // Read the next line
-            let bread = stdin.read_line(&mut buffer)?;
+            // Show the new line identifier, or the continuation one if we are in the middle of
+            // a statement
+            let prompt = if buffer.is_empty() { "> " } else { "... " };
 
-            // If no bytes were read, it means we reached `End-of-File` or `Ctrl-D` was pressed.
-            if bread == 0 {
-                break;
+            match read_line(self, prompt)? {
+                ReplLine::Line(line) => buffer.push_str(line.as_str()),
+                // Drop whatever was typed so far and start over
+                ReplLine::Interrupted => {
+                    buffer.clear();
+                    continue;
+                }
+                ReplLine::Eof => {
+                    // Whatever was left unfinished still gets run, such that its error is
+                    // reported
+                    if !buffer.trim().is_empty() {
+                        self.run_repl_input(buffer.as_str())?;
+                    }
+                    break;
+                }
             }
 
             match buffer.as_str().trim() {
                 "q" | "quit" | "exit" => break,
+                // Commands for the prompt itself always fit on a single line
+                command if command.starts_with(':') => {
+                    if let Err(err) = self.meta_command(command) {
+                        writeln!(self.interpreter.diagnostics(), "Interpreter: {err}")?;
+                    }
+                    buffer.clear();
+                    continue;
+                }
                 _ => {}
             }
 
-            // If a line is invalid, we report the error and go to the next iteration. We also
-            // specify the `is_repl` true such that we could evaluate both expressions and
-            // statements
-            if let Err(err) = malis.run(buffer.as_str(), true) {
-                println!("Interpreter: {err}");
-                stdout.flush()?;
+            // Keep reading lines until the statement is complete
+            if is_incomplete(buffer.as_str()) {
+                continue;
             }
 
+            self.run_repl_input(buffer.as_str())?;
+
             // Make sure to clean the buffer for the next iteration
             buffer.clear();
         }
 
         Ok(())
     }
+
+    // Gathers the names the interactive prompt could complete from the global environment
+    fn completions(&self) -> Completions {
+        let names = self.interpreter.global_names();
+        let members = names
+            .iter()
+            .filter_map(|name| {
+                let members = self.interpreter.get_global(name)?.member_names();
+                (!members.is_empty()).then(|| (name.clone(), members))
+            })
+            .collect::<HashMap<_, _>>();
+        Completions::new(names, members)
+    }
+
+    // Runs a complete input of the REPL. If it is invalid, we report the error and let the REPL
+    // go on with the next input.
+    fn run_repl_input(&mut self, source: &str) -> Result<(), MalisError> {
+        // We specify the `is_repl` true such that we could evaluate both expressions and
+        // statements
+        match self.run(source, true) {
+            Ok(()) => self.record(source),
+            // The script asked to stop, so we hand the exit status to our caller
+            Err(err) if err.exit_status().is_some() => return Err(err),
+            Err(err) => writeln!(self.interpreter.diagnostics(), "Interpreter: {err}")?,
+        }
+        Ok(())
+    }
+}
+
+// Returns whether `source` is the beginning of some code that needs more lines in order to be
+// complete, like an unterminated string, an unclosed block or a statement missing its `;`
+fn is_incomplete(source: &str) -> bool {
+    let mut scanner = Scanner::new(source);
+    let tokens = match scanner.scan_tokens() {
+        Ok(tokens) => tokens,
+        Err(errors) => {
+            return errors
+                .iter()
+                .any(|err| matches!(err, error::ScannerError::UnterminatedString(_)))
+        }
+    };
+
+    let mut parser = Parser::new(tokens);
+    if parser.parse().is_ok() {
+        return false;
+    }
+    let ended_early = parser.ended_early();
+    // A bare expression is complete without a `;`, since the REPL prints its value
+    ended_early && parser.parse_expression().is_err()
+}
+
+// Formats each token on its own line as the line it was found on, its type and its lexeme
+fn format_tokens(tokens: &[token::Token]) -> String {
+    tokens
+        .iter()
+        .map(|token| {
+            format!(
+                "{:>4} {:?} {:?}",
+                token.line(),
+                token.t_type(),
+                token.lexeme()
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
 }
 
 #[cfg(test)]
 mod tests {
-    use super::{AstPrinter, Parser, Scanner};
+    use super::{
+        AstPrinter, Limits, LineCallback, Malis, MalisError, MalisObject, NativeModule, Parser,
+        RuntimeError, Scanner, SharedBuffer,
+    };
 
     #[test]
     fn block_scope_test() {
@@ -132,8 +528,207 @@ mod tests {
         let mut ast_printer = AstPrinter;
 
         assert!(
-            format!("{}", ast_printer.print_stmt(&expr))
+            ast_printer.print_stmt(&expr)
                 == r#"(block scope (var decl (var a) LitString("global a")) (var decl (var b) LitString("global b")) (var decl (var c) LitString("global c")) (block scope (var decl (var a) LitString("outer a")) (var decl (var b) LitString("outer b")) (block scope (var decl (var a) LitString("inner a")) (print_stmt (var a)) (print_stmt (var b)) (print_stmt (var c))) (print_stmt (var a)) (print_stmt (var b)) (print_stmt (var c))) (print_stmt (var a)) (print_stmt (var b)) (print_stmt (var c)))"#
         );
     }
+
+    #[test]
+    fn check_reports_resolver_errors() {
+        let source =
+            std::fs::read_to_string("testdata/double_assign.ms").expect("Failed to read test file");
+        assert!(matches!(
+            Malis::check(source.as_str()),
+            Err(MalisError::ResolverError(_))
+        ));
+
+        let source =
+            std::fs::read_to_string("testdata/class_example.ms").expect("Failed to read test file");
+        assert!(Malis::check(source.as_str()).is_ok());
+    }
+
+    #[test]
+    fn resolves_names_across_scopes() {
+        // Globals could still be used by code resolved later on, unlike locals
+        assert!(Malis::check("var a = 1;").is_ok());
+        assert!(matches!(
+            Malis::check("fun f() { var a = 1; }"),
+            Err(MalisError::ResolverError(_))
+        ));
+        // Names are used where they are declared, even from a nested scope
+        assert!(Malis::check("fun f(n) { { return n; } }").is_ok());
+
+        // Calling the undefined `esec` fails the script if a value is not the expected one
+        let mut malis = Malis::default();
+        malis
+            .run("var c = 1; { c = 2; } if (c != 2) esec();", false)
+            .expect("Failed to assign from a block");
+        malis
+            .run(
+                "fun f() { var x = 1; { x = 2; var x = 3; if (x != 3) esec(); } if (x != 2) esec(); } f();",
+                false,
+            )
+            .expect("Failed to assign before shadowing");
+    }
+
+    #[test]
+    fn exit_status_is_reported() {
+        let mut malis = Malis::default();
+        malis.set_args(vec!["7".to_string(), "-v".to_string()]);
+        let err = malis
+            .run(
+                "var a = args(); if (a.len() == 2 and a.get(0) == \"7\") exit(7); print \"unreachable\";",
+                false,
+            )
+            .expect_err("Script should have exited");
+        assert_eq!(err.exit_status(), Some(7));
+    }
+
+    #[test]
+    fn output_is_captured() {
+        let output = SharedBuffer::new();
+        let diagnostics = SharedBuffer::new();
+        let mut malis = Malis::with_output(output.clone(), diagnostics.clone())
+            .expect("Failed to create interpreter");
+        malis.set_verbosity(1);
+
+        let source = std::fs::read_to_string("testdata/block_scope_test.ms")
+            .expect("Failed to read test file");
+        malis.run(source.as_str(), false).expect("Failed to run");
+
+        assert_eq!(
+            output.contents(),
+            "inner a\nouter b\nglobal c\nouter a\nouter b\nglobal c\nglobal a\nglobal b\nglobal c\n"
+        );
+        assert!(diagnostics.contents().starts_with("Ast (block scope"));
+    }
+
+    #[test]
+    fn repl_output_is_captured() {
+        let lines = std::rc::Rc::new(std::cell::RefCell::new(vec![]));
+        let captured = lines.clone();
+        let diagnostics = SharedBuffer::new();
+        let mut malis = Malis::with_output(
+            LineCallback::new(move |line| captured.borrow_mut().push(line.to_string())),
+            diagnostics.clone(),
+        )
+        .expect("Failed to create interpreter");
+
+        malis
+            .repl("var a = 2;\na * 3\nprint a +;\n".as_bytes())
+            .expect("Failed to run the REPL");
+
+        // Only the values reach the output, the prompts go with the errors
+        assert_eq!(*lines.borrow(), vec!["6"]);
+        assert!(diagnostics.contents().starts_with("> > > Interpreter: "));
+    }
+
+    #[test]
+    fn repl_continues_incomplete_input() {
+        let output = SharedBuffer::new();
+        let diagnostics = SharedBuffer::new();
+        let mut malis = Malis::with_output(output.clone(), diagnostics.clone())
+            .expect("Failed to create interpreter");
+
+        malis
+            .repl("fun add(a, b) {\n  return a + b;\n}\nvar s = \"multi\nline\";\nadd(1,\n2)\nprint\n s;\n".as_bytes())
+            .expect("Failed to run the REPL");
+
+        assert_eq!(output.contents(), "3\nmulti\nline\n");
+        assert_eq!(diagnostics.contents(), "> ... ... > ... > ... > ... > ");
+
+        // Input which is wrong before its end is reported right away
+        assert!(!super::is_incomplete("print a +;"));
+        assert!(!super::is_incomplete("a * 3"));
+        assert!(super::is_incomplete("class A {"));
+        assert!(super::is_incomplete("var a = (1 +"));
+        assert!(super::is_incomplete("var a = 1"));
+    }
+
+    #[test]
+    fn embedding_api() {
+        let output = SharedBuffer::new();
+        let mut malis =
+            Malis::with_output(output.clone(), SharedBuffer::new()).expect("Failed to create");
+
+        malis.set_global("pret", MalisObject::Number(10.0));
+        assert_eq!(
+            malis.eval("pret * 2").expect("Failed to evaluate"),
+            MalisObject::Number(20.0)
+        );
+        assert_eq!(
+            malis
+                .eval("fun reducere(p) { return pret - p; } var total = reducere(3); total;")
+                .expect("Failed to evaluate"),
+            MalisObject::Number(7.0)
+        );
+        assert_eq!(
+            malis.eval("var x = 1;").expect("Failed to evaluate"),
+            MalisObject::Nil
+        );
+        assert_eq!(malis.get_global("total"), Some(MalisObject::Number(7.0)));
+        assert_eq!(malis.get_global("nedefinit"), None);
+
+        assert_eq!(
+            malis
+                .call_function("reducere", vec![MalisObject::Number(4.0)])
+                .expect("Failed to call"),
+            MalisObject::Number(6.0)
+        );
+        assert!(matches!(
+            malis.call_function("reducere", vec![]),
+            Err(MalisError::RuntimeError(
+                RuntimeError::InvalidArgumentsNumber(_)
+            ))
+        ));
+        assert!(matches!(
+            malis.call_function("pret", vec![]),
+            Err(MalisError::RuntimeError(RuntimeError::NotCallable(_)))
+        ));
+        assert!(malis.call_function("nedefinit", vec![]).is_err());
+        // Nothing was printed along the way
+        assert!(output.contents().is_empty());
+
+        // Lone expressions run like statements, within the limits
+        malis.set_limits(Limits {
+            max_steps: Some(0),
+            ..Default::default()
+        });
+        assert!(matches!(
+            malis.eval("pret * 2"),
+            Err(MalisError::RuntimeError(RuntimeError::StepLimitExceeded(0)))
+        ));
+    }
+
+    #[test]
+    fn natives_reach_globals_from_inside_functions() {
+        let output = SharedBuffer::new();
+        let mut malis =
+            Malis::with_output(output.clone(), SharedBuffer::new()).expect("Failed to create");
+        let module = NativeModule::new().function("gazda", 0, |interpreter, _arguments| {
+            let dublu = interpreter.call_function("ajutor", vec![MalisObject::Number(2.0)])?;
+            let names = interpreter.global_names();
+            let total = interpreter.get_global("total").unwrap_or(MalisObject::Nil);
+            interpreter.set_global("x", dublu);
+            Ok(MalisObject::Boolean(
+                names.contains(&"ajutor".to_string()) && total == MalisObject::Number(1.0),
+            ))
+        });
+        malis.install(module).expect("Failed to install");
+        malis
+            .run(
+                "\
+var total = 1;
+fun ajutor(n) { return n * 2; }
+fun f() { var local = 3; { return gazda() and local == 3; } }
+print f();
+print x;
+",
+                false,
+            )
+            .expect("Failed to run");
+        assert_eq!(output.contents(), "true\n4\n");
+        assert_eq!(malis.get_global("x"), Some(MalisObject::Number(4.0)));
+        assert_eq!(malis.get_global("local"), None);
+    }
 }
@@ -0,0 +1,738 @@
+//! Language server for Malis scripts, talking the Language Server Protocol over any pair of
+//! streams, usually the standard input and output of `malis-lsp`
+//!
+//! The server keeps every open document in memory and analyses it again on each change, which is
+//! cheap enough for scripts. It supports diagnostics, go to definition, find references, hover,
+//! document symbols and completion.
+mod analysis;
+
+use crate::{error::MalisError, json::Json, scanner::Scanner, token::Token, Interpreter};
+use analysis::{Analysis, Diagnostic, SymbolKind};
+use std::collections::HashMap;
+use std::io::{BufRead, Write};
+
+// Error codes defined by JSON-RPC and the protocol
+const PARSE_ERROR: i64 = -32700;
+const INVALID_REQUEST: i64 = -32600;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+const SERVER_NOT_INITIALIZED: i64 = -32002;
+
+// Kinds of symbols and completion items, as numbered by the protocol
+const SYMBOL_CLASS: f64 = 5.0;
+const SYMBOL_METHOD: f64 = 6.0;
+const SYMBOL_FUNCTION: f64 = 12.0;
+const SYMBOL_VARIABLE: f64 = 13.0;
+const COMPLETION_METHOD: f64 = 2.0;
+const COMPLETION_FUNCTION: f64 = 3.0;
+const COMPLETION_VARIABLE: f64 = 6.0;
+const COMPLETION_CLASS: f64 = 7.0;
+const COMPLETION_KEYWORD: f64 = 14.0;
+
+// Severity of the diagnostics we publish, which are all errors
+const SEVERITY_ERROR: f64 = 1.0;
+// Documents are always sent whole on change
+const SYNC_FULL: f64 = 1.0;
+
+/// Reads a message framed by a `Content-Length` header, like the ones of the Language Server and
+/// the Debug Adapter protocols. Returns `None` once the input ends.
+pub(crate) fn read_message<R: BufRead>(input: &mut R) -> Result<Option<String>, MalisError> {
+    let mut length = None;
+    loop {
+        let mut header = String::new();
+        if input.read_line(&mut header)? == 0 {
+            return Ok(None);
+        }
+        let header = header.trim_end();
+        if header.is_empty() {
+            // Stray empty lines between messages
+            if length.is_none() {
+                continue;
+            }
+            break;
+        }
+        if let Some((name, value)) = header.split_once(':') {
+            if name.eq_ignore_ascii_case("Content-Length") {
+                length = Some(value.trim().parse::<usize>().map_err(|_| {
+                    MalisError::ProtocolError(format!("Invalid content length `{}`", value.trim()))
+                })?);
+            }
+        }
+    }
+
+    let mut content = vec![0; length.unwrap_or_default()];
+    input.read_exact(&mut content)?;
+    String::from_utf8(content)
+        .map(Some)
+        .map_err(|_| MalisError::ProtocolError("Message is not valid UTF-8".to_string()))
+}
+
+/// Writes `message` framed by a `Content-Length` header
+pub(crate) fn write_message<W: Write>(output: &mut W, message: &Json) -> Result<(), MalisError> {
+    let content = message.to_string();
+    write!(output, "Content-Length: {}\r\n\r\n{content}", content.len())?;
+    output.flush()?;
+    Ok(())
+}
+
+/// Serves requests read from `input` until the client asks us to exit or the input ends
+///
+/// # Errors
+///
+/// Fails if the streams could not be read from or written to, or if the input is not framed
+/// properly. Malformed requests are answered with an error instead.
+pub fn serve<R: BufRead, W: Write>(mut input: R, mut output: W) -> Result<(), MalisError> {
+    let mut server = Server::new();
+    while let Some(message) = read_message(&mut input)? {
+        let replies = match Json::parse(&message) {
+            Ok(message) => server.handle(&message),
+            Err(err) => vec![error_response(Json::Null, PARSE_ERROR, &err.to_string())],
+        };
+        for reply in replies {
+            write_message(&mut output, &reply)?;
+        }
+        if server.exited {
+            break;
+        }
+    }
+    Ok(())
+}
+
+struct Server {
+    // Open documents, by their URI
+    documents: HashMap<String, Document>,
+    initialized: bool,
+    shutdown: bool,
+    exited: bool,
+    // Names defined by the interpreter itself, like `clock`
+    natives: Vec<String>,
+}
+
+struct Document {
+    source: String,
+    analysis: Analysis,
+}
+
+impl Server {
+    fn new() -> Self {
+        let natives = Interpreter::with_output(std::io::sink(), std::io::sink())
+            .map(|interpreter| interpreter.global_names())
+            .unwrap_or_default();
+        Self {
+            documents: HashMap::new(),
+            initialized: false,
+            shutdown: false,
+            exited: false,
+            natives,
+        }
+    }
+
+    // Handles a request or a notification, returning the messages to be sent back
+    fn handle(&mut self, message: &Json) -> Vec<Json> {
+        let Some(method) = message.get("method").and_then(Json::as_str) else {
+            // Responses to requests we never send are ignored
+            if message.get("id").is_some() && message.get("result").is_none() {
+                return vec![error_response(
+                    Json::Null,
+                    INVALID_REQUEST,
+                    "Missing method",
+                )];
+            }
+            return vec![];
+        };
+        let params = message.get("params").unwrap_or(&Json::Null);
+        // Notifications have no id and get no response
+        let Some(id) = message.get("id").cloned() else {
+            return self.notification(method, params);
+        };
+
+        if self.shutdown {
+            return vec![error_response(
+                id,
+                INVALID_REQUEST,
+                "The server is shutting down",
+            )];
+        }
+        if !self.initialized && method != "initialize" {
+            return vec![error_response(
+                id,
+                SERVER_NOT_INITIALIZED,
+                "The server was not initialized",
+            )];
+        }
+
+        let result = match method {
+            "initialize" => {
+                self.initialized = true;
+                Ok(capabilities())
+            }
+            "shutdown" => {
+                self.shutdown = true;
+                Ok(Json::Null)
+            }
+            "textDocument/definition" => self.definition(params),
+            "textDocument/references" => self.references(params),
+            "textDocument/hover" => self.hover(params),
+            "textDocument/documentSymbol" => self.document_symbols(params),
+            "textDocument/completion" => self.completion(params),
+            _ => {
+                return vec![error_response(
+                    id,
+                    METHOD_NOT_FOUND,
+                    &format!("Unknown method `{method}`"),
+                )]
+            }
+        };
+        match result {
+            Ok(result) => vec![Json::object([
+                ("jsonrpc", Json::from("2.0")),
+                ("id", id),
+                ("result", result),
+            ])],
+            Err(message) => vec![error_response(id, INVALID_PARAMS, &message)],
+        }
+    }
+
+    fn notification(&mut self, method: &str, params: &Json) -> Vec<Json> {
+        match method {
+            "exit" => {
+                self.exited = true;
+                vec![]
+            }
+            "textDocument/didOpen" => {
+                let document = params.get("textDocument");
+                let uri = document.and_then(|document| document.get("uri"));
+                let text = document.and_then(|document| document.get("text"));
+                match (uri.and_then(Json::as_str), text.and_then(Json::as_str)) {
+                    (Some(uri), Some(text)) => self.update(uri, text),
+                    _ => vec![],
+                }
+            }
+            "textDocument/didChange" => {
+                let uri = params
+                    .get("textDocument")
+                    .and_then(|document| document.get("uri"))
+                    .and_then(Json::as_str);
+                // We only asked for whole documents, so the last change has the whole text
+                let text = params
+                    .get("contentChanges")
+                    .and_then(Json::as_array)
+                    .and_then(<[Json]>::last)
+                    .and_then(|change| change.get("text"))
+                    .and_then(Json::as_str);
+                match (uri, text) {
+                    (Some(uri), Some(text)) => self.update(uri, text),
+                    _ => vec![],
+                }
+            }
+            "textDocument/didClose" => {
+                let uri = params
+                    .get("textDocument")
+                    .and_then(|document| document.get("uri"))
+                    .and_then(Json::as_str);
+                match uri {
+                    Some(uri) => {
+                        self.documents.remove(uri);
+                        // Clear the diagnostics of the closed document
+                        vec![publish_diagnostics(uri, "", &[])]
+                    }
+                    None => vec![],
+                }
+            }
+            // Everything else, like `initialized`, needs nothing from us
+            _ => vec![],
+        }
+    }
+
+    // Analyses the new text of a document and publishes its diagnostics
+    fn update(&mut self, uri: &str, text: &str) -> Vec<Json> {
+        let analysis = Analysis::new(text);
+        let notification = publish_diagnostics(uri, text, &analysis.diagnostics);
+        self.documents.insert(
+            uri.to_string(),
+            Document {
+                source: text.to_string(),
+                analysis,
+            },
+        );
+        vec![notification]
+    }
+
+    // Finds the document and the position, as a 1-based line and column counting characters, that
+    // a request is about
+    fn position<'a>(
+        &'a self,
+        params: &Json,
+    ) -> Result<(&'a str, &'a Document, usize, usize), String> {
+        let uri = params
+            .get("textDocument")
+            .and_then(|document| document.get("uri"))
+            .and_then(Json::as_str)
+            .ok_or("Missing document")?;
+        let (uri, document) = self
+            .documents
+            .get_key_value(uri)
+            .ok_or_else(|| format!("Document `{uri}` is not open"))?;
+        let position = params.get("position").ok_or("Missing position")?;
+        let line = position.get("line").and_then(Json::as_usize);
+        let character = position.get("character").and_then(Json::as_usize);
+        match (line, character) {
+            (Some(line), Some(character)) => {
+                let line_text = document.source.lines().nth(line).unwrap_or_default();
+                Ok((
+                    uri,
+                    document,
+                    line + 1,
+                    from_utf16(line_text, character) + 1,
+                ))
+            }
+            _ => Err("Invalid position".to_string()),
+        }
+    }
+
+    fn definition(&self, params: &Json) -> Result<Json, String> {
+        let (uri, document, line, column) = self.position(params)?;
+        let analysis = &document.analysis;
+        let declarations = match analysis.declaration_at(line, column) {
+            Some(idx) => vec![idx],
+            None => analysis.methods_at(line, column),
+        };
+        Ok(Json::Array(
+            declarations
+                .into_iter()
+                .map(|idx| location(uri, &document.source, &analysis.declarations[idx].name))
+                .collect(),
+        ))
+    }
+
+    fn references(&self, params: &Json) -> Result<Json, String> {
+        let (uri, document, line, column) = self.position(params)?;
+        let analysis = &document.analysis;
+        let include_declaration = params
+            .get("context")
+            .and_then(|context| context.get("includeDeclaration"))
+            .and_then(Json::as_bool)
+            .unwrap_or(false);
+        let declarations = match analysis.declaration_at(line, column) {
+            Some(idx) => vec![idx],
+            None => analysis.methods_at(line, column),
+        };
+
+        let mut tokens = vec![];
+        for idx in declarations {
+            if include_declaration {
+                tokens.push(&analysis.declarations[idx].name);
+            }
+            tokens.extend(analysis.references_to(idx));
+        }
+        // Methods sharing a name share their references too, so we list each of them once
+        tokens.sort_by_key(|token| (token.line(), token.column()));
+        tokens.dedup_by_key(|token| (token.line(), token.column()));
+        Ok(Json::Array(
+            tokens
+                .into_iter()
+                .map(|token| location(uri, &document.source, token))
+                .collect(),
+        ))
+    }
+
+    fn hover(&self, params: &Json) -> Result<Json, String> {
+        let (_, document, line, column) = self.position(params)?;
+        let analysis = &document.analysis;
+        let declarations = match analysis.declaration_at(line, column) {
+            Some(idx) => vec![idx],
+            None => analysis.methods_at(line, column),
+        };
+        if declarations.is_empty() {
+            return Ok(Json::Null);
+        }
+        let details = declarations
+            .iter()
+            .map(|idx| analysis.declarations[*idx].detail.as_str())
+            .collect::<Vec<_>>()
+            .join("\n");
+        Ok(Json::object([(
+            "contents",
+            Json::object([
+                ("kind", Json::from("markdown")),
+                ("value", Json::from(format!("```malis\n{details}\n```"))),
+            ]),
+        )]))
+    }
+
+    fn document_symbols(&self, params: &Json) -> Result<Json, String> {
+        let uri = params
+            .get("textDocument")
+            .and_then(|document| document.get("uri"))
+            .and_then(Json::as_str)
+            .ok_or("Missing document")?;
+        let document = self
+            .documents
+            .get(uri)
+            .ok_or_else(|| format!("Document `{uri}` is not open"))?;
+        let analysis = &document.analysis;
+
+        // Only declarations at the top level of the document are listed, with methods nested
+        // into their classes. Variables and functions inside functions are not interesting enough.
+        let symbol = |idx: usize, children: Vec<Json>| {
+            let declaration = &analysis.declarations[idx];
+            let kind = match declaration.kind {
+                SymbolKind::Class => SYMBOL_CLASS,
+                SymbolKind::Method => SYMBOL_METHOD,
+                SymbolKind::Function => SYMBOL_FUNCTION,
+                SymbolKind::Variable | SymbolKind::Parameter => SYMBOL_VARIABLE,
+            };
+            let range = range(&document.source, &declaration.name);
+            Json::object([
+                ("name", Json::from(declaration.name.lexeme())),
+                ("detail", Json::from(declaration.detail.as_str())),
+                ("kind", Json::Number(kind)),
+                ("range", range.clone()),
+                ("selectionRange", range),
+                ("children", Json::Array(children)),
+            ])
+        };
+        let symbols = analysis
+            .top_level()
+            .map(|idx| {
+                let methods = analysis
+                    .declarations
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, declaration)| declaration.container == Some(idx))
+                    .map(|(method, _)| symbol(method, vec![]))
+                    .collect();
+                symbol(idx, methods)
+            })
+            .collect();
+        Ok(Json::Array(symbols))
+    }
+
+    fn completion(&self, params: &Json) -> Result<Json, String> {
+        let (_, document, line, column) = self.position(params)?;
+        let analysis = &document.analysis;
+        let line_text = document.source.lines().nth(line - 1).unwrap_or_default();
+        let before_cursor = line_text
+            .chars()
+            .take(column - 1)
+            .collect::<String>()
+            .trim_end_matches(|ch: char| ch.is_ascii_alphanumeric() || ch == '_')
+            .to_string();
+
+        let item = |label: &str, kind: f64, detail: Option<&str>| {
+            let mut members = vec![("label", Json::from(label)), ("kind", Json::Number(kind))];
+            if let Some(detail) = detail {
+                members.push(("detail", Json::from(detail)));
+            }
+            Json::object(members)
+        };
+
+        let mut items = vec![];
+        let mut seen = std::collections::HashSet::new();
+        if before_cursor.ends_with('.') {
+            // We do not know the type of the object, so any method or property could follow
+            for declaration in analysis.declarations.iter() {
+                if declaration.kind == SymbolKind::Method
+                    && seen.insert(declaration.name.lexeme().to_string())
+                {
+                    items.push(item(
+                        declaration.name.lexeme(),
+                        COMPLETION_METHOD,
+                        Some(&declaration.detail),
+                    ));
+                }
+            }
+            for property in analysis.properties.iter() {
+                if seen.insert(property.lexeme().to_string()) {
+                    items.push(item(property.lexeme(), COMPLETION_VARIABLE, None));
+                }
+            }
+        } else {
+            for keyword in Scanner::keywords() {
+                items.push(item(keyword, COMPLETION_KEYWORD, None));
+            }
+            for declaration in analysis.declarations.iter() {
+                let kind = match declaration.kind {
+                    SymbolKind::Method => continue,
+                    SymbolKind::Class => COMPLETION_CLASS,
+                    SymbolKind::Function => COMPLETION_FUNCTION,
+                    SymbolKind::Variable | SymbolKind::Parameter => COMPLETION_VARIABLE,
+                };
+                if seen.insert(declaration.name.lexeme().to_string()) {
+                    items.push(item(
+                        declaration.name.lexeme(),
+                        kind,
+                        Some(&declaration.detail),
+                    ));
+                }
+            }
+            for native in self.natives.iter() {
+                if seen.insert(native.clone()) {
+                    items.push(item(native, COMPLETION_FUNCTION, Some("native function")));
+                }
+            }
+        }
+        Ok(Json::Array(items))
+    }
+}
+
+fn capabilities() -> Json {
+    Json::object([
+        (
+            "capabilities",
+            Json::object([
+                ("textDocumentSync", Json::Number(SYNC_FULL)),
+                ("definitionProvider", Json::Bool(true)),
+                ("referencesProvider", Json::Bool(true)),
+                ("hoverProvider", Json::Bool(true)),
+                ("documentSymbolProvider", Json::Bool(true)),
+                (
+                    "completionProvider",
+                    Json::object([("triggerCharacters", Json::Array(vec![Json::from(".")]))]),
+                ),
+            ]),
+        ),
+        (
+            "serverInfo",
+            Json::object([
+                ("name", Json::from("malis-lsp")),
+                ("version", Json::from(env!("CARGO_PKG_VERSION"))),
+            ]),
+        ),
+    ])
+}
+
+fn error_response(id: Json, code: i64, message: &str) -> Json {
+    Json::object([
+        ("jsonrpc", Json::from("2.0")),
+        ("id", id),
+        (
+            "error",
+            Json::object([("code", Json::from(code)), ("message", Json::from(message))]),
+        ),
+    ])
+}
+
+fn publish_diagnostics(uri: &str, source: &str, diagnostics: &[Diagnostic]) -> Json {
+    let diagnostics = diagnostics
+        .iter()
+        .map(|diagnostic| {
+            let range = match &diagnostic.token {
+                Some(token) => range(source, token),
+                // Without a token, the whole line is to blame
+                None => {
+                    let line = diagnostic.line.saturating_sub(1);
+                    Json::object([("start", position(line, 0)), ("end", position(line + 1, 0))])
+                }
+            };
+            Json::object([
+                ("range", range),
+                ("severity", Json::Number(SEVERITY_ERROR)),
+                ("source", Json::from("malis")),
+                ("message", Json::from(diagnostic.message.as_str())),
+            ])
+        })
+        .collect();
+    Json::object([
+        ("jsonrpc", Json::from("2.0")),
+        ("method", Json::from("textDocument/publishDiagnostics")),
+        (
+            "params",
+            Json::object([
+                ("uri", Json::from(uri)),
+                ("diagnostics", Json::Array(diagnostics)),
+            ]),
+        ),
+    ])
+}
+
+fn position(line: usize, character: usize) -> Json {
+    Json::object([
+        ("line", Json::from(line)),
+        ("character", Json::from(character)),
+    ])
+}
+
+// Returns the range covered by `token` in `source`. The protocol counts from 0, while tokens
+// count from 1. Tokens also count characters, while the protocol counts UTF-16 code units by
+// default, which differ for characters like emoji.
+fn range(source: &str, token: &Token) -> Json {
+    let line = token.line().saturating_sub(1);
+    let line_text = source.lines().nth(line).unwrap_or_default();
+    let start = to_utf16(line_text, token.column().saturating_sub(1));
+    let end = start + token.lexeme().encode_utf16().count();
+    Json::object([
+        ("start", position(line, start)),
+        ("end", position(line, end)),
+    ])
+}
+
+fn location(uri: &str, source: &str, token: &Token) -> Json {
+    Json::object([("uri", Json::from(uri)), ("range", range(source, token))])
+}
+
+// Returns how many UTF-16 code units the first `characters` of the line take
+fn to_utf16(line_text: &str, characters: usize) -> usize {
+    line_text
+        .chars()
+        .take(characters)
+        .map(char::len_utf16)
+        .sum()
+}
+
+// Returns how many characters of the line fit in the first `units` UTF-16 code units
+fn from_utf16(line_text: &str, units: usize) -> usize {
+    let mut taken = 0;
+    line_text
+        .chars()
+        .take_while(|ch| {
+            taken += ch.len_utf16();
+            taken <= units
+        })
+        .count()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{read_message, serve, write_message};
+    use crate::json::Json;
+
+    // Frames each of the requests, as a client would send them
+    fn frame(requests: &[&str]) -> Vec<u8> {
+        let mut input = vec![];
+        for request in requests {
+            let request = Json::parse(request).expect("Invalid request");
+            write_message(&mut input, &request).expect("Failed to frame request");
+        }
+        input
+    }
+
+    #[test]
+    fn serves_requests() {
+        let uri = "file:///tort.ms";
+        let source = "class Tort {\\n    gust(aroma) {\\n        return aroma;\\n    }\\n}\\nvar t = Tort();\\nprint t.gust(1);\\nreturn t;\\n";
+        let did_open = format!(
+            r#"{{"jsonrpc":"2.0","method":"textDocument/didOpen","params":{{"textDocument":{{"uri":"{uri}","languageId":"malis","version":1,"text":"{source}"}}}}}}"#
+        );
+        let at = |id: usize, method: &str, line: usize, character: usize| {
+            format!(
+                r#"{{"jsonrpc":"2.0","id":{id},"method":"{method}","params":{{"textDocument":{{"uri":"{uri}"}},"position":{{"line":{line},"character":{character}}},"context":{{"includeDeclaration":true}}}}}}"#
+            )
+        };
+        let requests = [
+            r#"{"jsonrpc":"2.0","id":1,"method":"initialize","params":{}}"#.to_string(),
+            did_open,
+            // `Tort` in `Tort()`
+            at(2, "textDocument/definition", 5, 9),
+            // `gust` in `t.gust(1)`
+            at(3, "textDocument/hover", 6, 9),
+            at(4, "textDocument/references", 0, 7),
+            format!(
+                r#"{{"jsonrpc":"2.0","id":5,"method":"textDocument/documentSymbol","params":{{"textDocument":{{"uri":"{uri}"}}}}}}"#
+            ),
+            at(6, "textDocument/completion", 6, 8),
+            r#"{"jsonrpc":"2.0","id":7,"method":"textDocument/formatting","params":{}}"#
+                .to_string(),
+            r#"{"jsonrpc":"2.0","id":8,"method":"shutdown"}"#.to_string(),
+            r#"{"jsonrpc":"2.0","method":"exit"}"#.to_string(),
+        ];
+        let input = frame(&requests.iter().map(String::as_str).collect::<Vec<_>>());
+
+        let mut output = vec![];
+        serve(input.as_slice(), &mut output).expect("Failed to serve");
+
+        let mut output = output.as_slice();
+        let mut replies = vec![];
+        while let Some(message) = read_message(&mut output).expect("Invalid framing") {
+            replies.push(Json::parse(&message).expect("Invalid reply"));
+        }
+        let result = |idx: usize| replies[idx].get("result").expect("No result").to_string();
+
+        assert_eq!(replies.len(), 9);
+        assert!(result(0).contains(r#""definitionProvider":true"#));
+        // `return` outside of a function is found by the resolver
+        let diagnostics = replies[1].to_string();
+        assert!(
+            diagnostics.contains(
+                r#""range":{"start":{"line":7,"character":0},"end":{"line":7,"character":6}}"#
+            ),
+            "{diagnostics}"
+        );
+        assert_eq!(
+            result(2),
+            r#"[{"uri":"file:///tort.ms","range":{"start":{"line":0,"character":6},"end":{"line":0,"character":10}}}]"#
+        );
+        assert!(result(3).contains("Tort.gust(aroma)"), "{}", result(3));
+        // The declaration of `Tort` and the call creating the instance
+        assert_eq!(result(4).matches(r#""uri""#).count(), 2);
+        let symbols = result(5);
+        assert!(
+            symbols.starts_with(r#"[{"name":"Tort","detail":"class Tort","kind":5"#),
+            "{symbols}"
+        );
+        assert!(
+            symbols.contains(r#""children":[{"name":"gust""#),
+            "{symbols}"
+        );
+        assert!(result(6).contains(r#""label":"gust""#));
+        assert!(!result(6).contains(r#""label":"class""#));
+        assert!(replies[7].get("error").is_some());
+        assert_eq!(result(8), "null");
+    }
+
+    #[test]
+    fn counts_positions_in_utf16() {
+        let uri = "file:///emoji.ms";
+        let requests = [
+            r#"{"jsonrpc":"2.0","id":1,"method":"initialize","params":{}}"#.to_string(),
+            format!(
+                r#"{{"jsonrpc":"2.0","method":"textDocument/didOpen","params":{{"textDocument":{{"uri":"{uri}","text":"var s = \"😀😀\"; print s;\n"}}}}}}"#
+            ),
+            // `s` in `print s`, after four UTF-16 code units of emoji
+            format!(
+                r#"{{"jsonrpc":"2.0","id":2,"method":"textDocument/references","params":{{"textDocument":{{"uri":"{uri}"}},"position":{{"line":0,"character":22}},"context":{{"includeDeclaration":true}}}}}}"#
+            ),
+        ];
+        let input = frame(&requests.iter().map(String::as_str).collect::<Vec<_>>());
+
+        let mut output = vec![];
+        serve(input.as_slice(), &mut output).expect("Failed to serve");
+        let mut output = output.as_slice();
+        let mut replies = vec![];
+        while let Some(message) = read_message(&mut output).expect("Invalid framing") {
+            replies.push(Json::parse(&message).expect("Invalid reply"));
+        }
+        assert_eq!(
+            replies[2].get("result").expect("No result").to_string(),
+            r#"[{"uri":"file:///emoji.ms","range":{"start":{"line":0,"character":4},"end":{"line":0,"character":5}}},{"uri":"file:///emoji.ms","range":{"start":{"line":0,"character":22},"end":{"line":0,"character":23}}}]"#
+        );
+    }
+
+    #[test]
+    fn lists_shared_references_once() {
+        let uri = "file:///gust.ms";
+        let requests = [
+            r#"{"jsonrpc":"2.0","id":1,"method":"initialize","params":{}}"#.to_string(),
+            format!(
+                r#"{{"jsonrpc":"2.0","method":"textDocument/didOpen","params":{{"textDocument":{{"uri":"{uri}","text":"class A {{ gust() {{}} }}\nclass B {{ gust() {{}} }}\nA().gust();\n"}}}}}}"#
+            ),
+            // `gust` in `A().gust()`, which could be either method
+            format!(
+                r#"{{"jsonrpc":"2.0","id":2,"method":"textDocument/references","params":{{"textDocument":{{"uri":"{uri}"}},"position":{{"line":2,"character":5}},"context":{{"includeDeclaration":true}}}}}}"#
+            ),
+        ];
+        let input = frame(&requests.iter().map(String::as_str).collect::<Vec<_>>());
+
+        let mut output = vec![];
+        serve(input.as_slice(), &mut output).expect("Failed to serve");
+        let mut output = output.as_slice();
+        let mut replies = vec![];
+        while let Some(message) = read_message(&mut output).expect("Invalid framing") {
+            replies.push(Json::parse(&message).expect("Invalid reply"));
+        }
+        let result = replies[2].get("result").expect("No result").to_string();
+        // Both declarations and the call, once
+        assert_eq!(result.matches(r#""uri""#).count(), 3, "{result}");
+        assert_eq!(result.matches(r#""line":2"#).count(), 2, "{result}");
+    }
+}
@@ -0,0 +1,476 @@
+use crate::{
+    ast::{
+        Binary, Call, ClassDeclaration, Expr, FunctionDeclaration, GetExpr, Group, IfStmt, Literal,
+        Logical, ReturnStmt, SetExpr, Stmt, SuperExpr, Ternary, Unary, VarStmt, WhileStmt,
+    },
+    error::{ParserError, ResolverError, ScannerError},
+    parser::Parser,
+    resolver::{Bindings, Resolver},
+    scanner::Scanner,
+    token::{Token, TokenType},
+    visit::{ExprVisitor, StmtVisitor},
+    Interpreter,
+};
+use std::collections::{HashMap, HashSet};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SymbolKind {
+    Variable,
+    Parameter,
+    Function,
+    Class,
+    Method,
+}
+
+/// A name introduced by the code
+#[derive(Debug, Clone)]
+pub struct Declaration {
+    pub name: Token,
+    pub kind: SymbolKind,
+    // How the declaration reads in code, shown when hovering over the name
+    pub detail: String,
+    // Index of the class declaring this method
+    pub container: Option<usize>,
+}
+
+/// A problem found in the code. Not all errors know the token they were found at, in which case
+/// we only know the line, if even that.
+#[derive(Debug, Clone)]
+pub struct Diagnostic {
+    pub token: Option<Token>,
+    pub line: usize,
+    pub message: String,
+}
+
+/// Everything an editor could ask about a document, computed once per change
+#[derive(Debug, Default)]
+pub struct Analysis {
+    pub declarations: Vec<Declaration>,
+    // Each use of a name, along with the index of its declaration
+    pub references: Vec<(Token, usize)>,
+    // Names following a dot, which are only known at runtime
+    pub properties: Vec<Token>,
+    pub diagnostics: Vec<Diagnostic>,
+    // Declarations made at the top level of the document
+    top_level: Vec<usize>,
+}
+
+impl Analysis {
+    pub fn new(source: &str) -> Self {
+        let mut analysis = Self::default();
+
+        let tokens = match Scanner::new(source).scan_tokens() {
+            Ok(tokens) => tokens,
+            Err(errors) => {
+                analysis.diagnostics = errors.iter().map(scanner_diagnostic).collect();
+                return analysis;
+            }
+        };
+
+        let stmts = match Parser::new(tokens).parse() {
+            Ok(stmts) => stmts,
+            Err(err) => {
+                parser_diagnostics(err, &mut analysis.diagnostics);
+                return analysis;
+            }
+        };
+
+        // The resolver knows which declaration each name refers to. While recording, it carries
+        // on after errors, so one mistake does not leave the rest of the document unindexed.
+        let mut bindings = Bindings::default();
+        if let Ok(mut interpreter) = Interpreter::with_output(std::io::sink(), std::io::sink()) {
+            let mut resolver = Resolver::new(&mut interpreter).recording();
+            if let Err(err) = resolver.resolve(&stmts) {
+                analysis.diagnostics.push(resolver_diagnostic(&err));
+            }
+            bindings = resolver.into_bindings();
+        }
+        analysis
+            .diagnostics
+            .extend(bindings.errors.iter().map(resolver_diagnostic));
+
+        let mut indexer = Indexer {
+            analysis: &mut analysis,
+            current_class: None,
+        };
+        for stmt in stmts.iter() {
+            stmt.walk(&mut indexer);
+        }
+        analysis.link(&bindings);
+        analysis
+    }
+
+    // Fills in the references and the top level declarations from what the resolver found
+    fn link(&mut self, bindings: &Bindings) {
+        // Methods are not names the resolver knows about
+        let declarations: HashMap<&Token, usize> = self
+            .declarations
+            .iter()
+            .enumerate()
+            .filter(|(_, declaration)| declaration.kind != SymbolKind::Method)
+            .map(|(idx, declaration)| (&declaration.name, idx))
+            .collect();
+        self.references = bindings
+            .uses
+            .iter()
+            .filter_map(|(token, declaration)| {
+                let idx = declarations.get(declaration.as_ref()?)?;
+                Some((token.clone(), *idx))
+            })
+            .collect();
+
+        let globals: HashSet<&Token> = bindings
+            .declarations
+            .iter()
+            .filter(|(_, depth)| *depth == 0)
+            .map(|(token, _)| token)
+            .collect();
+        self.top_level = (0..self.declarations.len())
+            .filter(|idx| globals.contains(&self.declarations[*idx].name))
+            .collect();
+    }
+
+    /// Returns the index of the declaration of the name at the given position, where both the
+    /// line and the column start from 1
+    pub fn declaration_at(&self, line: usize, column: usize) -> Option<usize> {
+        self.declarations
+            .iter()
+            .enumerate()
+            .find(|(_, declaration)| contains(&declaration.name, line, column))
+            .map(|(idx, _)| idx)
+            .or_else(|| {
+                self.references
+                    .iter()
+                    .find(|(token, _)| contains(token, line, column))
+                    .map(|(_, idx)| *idx)
+            })
+    }
+
+    /// Returns the declarations of the methods that the property at the given position could
+    /// refer to. Properties are looked up at runtime, so the best we could do is match by name.
+    pub fn methods_at(&self, line: usize, column: usize) -> Vec<usize> {
+        let Some(property) = self
+            .properties
+            .iter()
+            .find(|token| contains(token, line, column))
+        else {
+            return vec![];
+        };
+        self.declarations
+            .iter()
+            .enumerate()
+            .filter(|(_, declaration)| {
+                declaration.kind == SymbolKind::Method
+                    && declaration.name.lexeme() == property.lexeme()
+            })
+            .map(|(idx, _)| idx)
+            .collect()
+    }
+
+    /// Returns the declarations made at the top level of the document, in order
+    pub fn top_level(&self) -> impl Iterator<Item = usize> + '_ {
+        self.top_level.iter().copied()
+    }
+
+    /// Returns every use of the declaration at `idx`
+    pub fn references_to(&self, idx: usize) -> impl Iterator<Item = &Token> {
+        let declaration = &self.declarations[idx];
+        let uses = self
+            .references
+            .iter()
+            .filter(move |(_, declaration)| *declaration == idx)
+            .map(|(token, _)| token);
+        // Methods are used through properties with their name
+        let properties = self.properties.iter().filter(move |token| {
+            declaration.kind == SymbolKind::Method && token.lexeme() == declaration.name.lexeme()
+        });
+        uses.chain(properties)
+    }
+}
+
+// Returns whether `token` covers the given position
+fn contains(token: &Token, line: usize, column: usize) -> bool {
+    token.line() == line
+        && column >= token.column()
+        && column < token.column() + token.lexeme().chars().count().max(1)
+}
+
+fn scanner_diagnostic(err: &ScannerError) -> Diagnostic {
+    let line = match err {
+        ScannerError::UnexpectedCharacter(_, line) | ScannerError::UnterminatedString(line) => {
+            *line
+        }
+        _ => 1,
+    };
+    Diagnostic {
+        token: None,
+        line,
+        message: err.to_string(),
+    }
+}
+
+fn parser_diagnostics(err: ParserError, diagnostics: &mut Vec<Diagnostic>) {
+    match err {
+        ParserError::Multiple(errors) => {
+            for err in errors {
+                parser_diagnostics(err, diagnostics);
+            }
+        }
+        ParserError::PanicMode(message, token) => diagnostics.push(Diagnostic {
+            line: token.line(),
+            // The end of the input has no text to be highlighted
+            token: (token.t_type() != &TokenType::Eof).then_some(token),
+            message,
+        }),
+        err => diagnostics.push(Diagnostic {
+            token: None,
+            line: 1,
+            message: err.to_string(),
+        }),
+    }
+}
+
+fn resolver_diagnostic(err: &ResolverError) -> Diagnostic {
+    let token = err.token().cloned();
+    Diagnostic {
+        line: token.as_ref().map_or(1, Token::line),
+        token,
+        message: err.to_string(),
+    }
+}
+
+// Walks the syntax tree, recording what each declaration is and the properties used. Which
+// declaration a name refers to is left to the `Resolver`.
+struct Indexer<'a> {
+    analysis: &'a mut Analysis,
+    // Index of the declaration of the class whose methods we are in
+    current_class: Option<usize>,
+}
+
+impl Indexer<'_> {
+    fn declare(&mut self, name: &Token, kind: SymbolKind, detail: String) -> usize {
+        let idx = self.analysis.declarations.len();
+        self.analysis.declarations.push(Declaration {
+            name: name.clone(),
+            kind,
+            detail,
+            container: self.current_class.filter(|_| kind == SymbolKind::Method),
+        });
+        idx
+    }
+
+    fn function(&mut self, function: &FunctionDeclaration, kind: SymbolKind) {
+        let parameters = function
+            .parameters
+            .iter()
+            .map(Token::lexeme)
+            .collect::<Vec<_>>()
+            .join(", ");
+        let detail = match self.current_class.filter(|_| kind == SymbolKind::Method) {
+            Some(class) => format!(
+                "{}.{}({parameters})",
+                self.analysis.declarations[class].name.lexeme(),
+                function.name.lexeme()
+            ),
+            None => format!("fun {}({parameters})", function.name.lexeme()),
+        };
+        self.declare(&function.name, kind, detail);
+
+        for parameter in function.parameters.iter() {
+            self.declare(
+                parameter,
+                SymbolKind::Parameter,
+                format!("parameter {}", parameter.lexeme()),
+            );
+        }
+        for stmt in function.body.iter() {
+            stmt.walk(self);
+        }
+    }
+}
+
+impl ExprVisitor<()> for Indexer<'_> {
+    fn visit_unary(&mut self, unary: &Unary) {
+        unary.right.walk(self)
+    }
+
+    fn visit_binary(&mut self, binary: &Binary) {
+        binary.left.walk(self);
+        binary.right.walk(self)
+    }
+
+    fn visit_ternary(&mut self, ternary: &Ternary) {
+        ternary.first.walk(self);
+        ternary.second.walk(self);
+        ternary.third.walk(self)
+    }
+
+    fn visit_literal(&mut self, _literal: &Literal) {}
+
+    fn visit_group(&mut self, group: &Group) {
+        group.expr.walk(self)
+    }
+
+    fn visit_variable(&mut self, _variable: &Token) {}
+
+    fn visit_assign(&mut self, _ident: &Token, expr: &Expr) {
+        expr.walk(self)
+    }
+
+    fn visit_logical(&mut self, logical: &Logical) {
+        logical.left.walk(self);
+        logical.right.walk(self)
+    }
+
+    fn visit_call(&mut self, call: &Call) {
+        call.callee.walk(self);
+        for argument in call.arguments.iter() {
+            argument.walk(self);
+        }
+    }
+
+    fn visit_get(&mut self, get: &GetExpr) {
+        get.object().walk(self);
+        self.analysis.properties.push(get.name().clone())
+    }
+
+    fn visit_set(&mut self, set: &SetExpr) {
+        set.value().walk(self);
+        set.object().walk(self);
+        self.analysis.properties.push(set.name().clone())
+    }
+
+    fn visit_self(&mut self, _class_self: &Token) {}
+
+    fn visit_super(&mut self, super_expr: &SuperExpr) {
+        self.analysis.properties.push(super_expr.method().clone())
+    }
+}
+
+impl StmtVisitor<()> for Indexer<'_> {
+    fn visit_expr_stmt(&mut self, stmt: &Expr) {
+        stmt.walk(self)
+    }
+
+    fn visit_print_stmt(&mut self, stmt: &Expr) {
+        stmt.walk(self)
+    }
+
+    fn visit_var_stmt(&mut self, stmt: &VarStmt) {
+        if let Some(expr) = stmt.expr() {
+            expr.walk(self);
+        }
+        let name = stmt.identifier();
+        self.declare(name, SymbolKind::Variable, format!("var {}", name.lexeme()));
+    }
+
+    fn visit_block_stmt(&mut self, stmts: &[Stmt]) {
+        for stmt in stmts {
+            stmt.walk(self);
+        }
+    }
+
+    fn visit_if_stmt(&mut self, stmt: &IfStmt) {
+        stmt.condition.walk(self);
+        stmt.then_branch.walk(self);
+        if let Some(else_branch) = &stmt.else_branch {
+            else_branch.walk(self);
+        }
+    }
+
+    fn visit_while_stmt(&mut self, stmt: &WhileStmt) {
+        stmt.condition.walk(self);
+        stmt.stmt.walk(self)
+    }
+
+    fn visit_return_stmt(&mut self, stmt: &ReturnStmt) {
+        if let Some(expr) = stmt.expr() {
+            expr.walk(self);
+        }
+    }
+
+    fn visit_function(&mut self, function: &FunctionDeclaration) {
+        self.function(function, SymbolKind::Function)
+    }
+
+    fn visit_class(&mut self, class: &ClassDeclaration) {
+        let detail = match &class.superclass {
+            Some(superclass) => format!("class {} < {}", class.name.lexeme(), superclass.lexeme()),
+            None => format!("class {}", class.name.lexeme()),
+        };
+        let idx = self.declare(&class.name, SymbolKind::Class, detail);
+
+        let enclosing_class = self.current_class.replace(idx);
+        for method in class.methods.iter() {
+            if let Stmt::Function(function) = method {
+                self.function(function, SymbolKind::Method);
+            }
+        }
+        self.current_class = enclosing_class;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Analysis, SymbolKind};
+
+    #[test]
+    fn indexes_declarations_and_references() {
+        let source = "fun suma(a, b) {\n    return a + b;\n}\nclass Tort {\n    gust() {\n        return suma(1, 2);\n    }\n}\nvar t = Tort();\nprint t.gust();\n";
+        let analysis = Analysis::new(source);
+        assert!(
+            analysis.diagnostics.is_empty(),
+            "{:?}",
+            analysis.diagnostics
+        );
+
+        // `suma` inside the method refers to the function
+        let suma = analysis.declaration_at(6, 16).expect("No declaration");
+        assert_eq!(analysis.declarations[suma].detail, "fun suma(a, b)");
+        assert_eq!(analysis.declarations[suma].name.line(), 1);
+        assert_eq!(analysis.references_to(suma).count(), 1);
+
+        // `a` in the body refers to the parameter
+        let a = analysis.declaration_at(2, 12).expect("No declaration");
+        assert_eq!(analysis.declarations[a].kind, SymbolKind::Parameter);
+
+        // The property `gust` could only be the method
+        let methods = analysis.methods_at(10, 9);
+        assert_eq!(methods.len(), 1);
+        assert_eq!(analysis.declarations[methods[0]].detail, "Tort.gust()");
+    }
+
+    #[test]
+    fn follows_the_resolver_past_errors() {
+        let source = "fun f() {\n    var x = 1;\n}\nvar a = 1;\n{\n    var a = 2;\n    print a;\n}\nprint a;\n";
+        let analysis = Analysis::new(source);
+        assert_eq!(analysis.diagnostics.len(), 1);
+
+        // The inner `a` shadows the global one
+        let inner = analysis.declaration_at(7, 11).expect("No declaration");
+        assert_eq!(analysis.declarations[inner].name.line(), 6);
+        let global = analysis.declaration_at(9, 7).expect("No declaration");
+        assert_eq!(analysis.declarations[global].name.line(), 4);
+        assert_eq!(
+            analysis
+                .top_level()
+                .map(|idx| analysis.declarations[idx].name.lexeme())
+                .collect::<Vec<_>>(),
+            ["f", "a"]
+        );
+    }
+
+    #[test]
+    fn reports_diagnostics_with_positions() {
+        let analysis = Analysis::new("fun f() {\n    var x = 1;\n}\n");
+        let diagnostic = &analysis.diagnostics[0];
+        let token = diagnostic
+            .token
+            .as_ref()
+            .expect("No token for the unused variable");
+        assert_eq!((token.line(), token.column()), (2, 9));
+
+        let analysis = Analysis::new("print 1;\nprint @;\n");
+        assert_eq!(analysis.diagnostics[0].line, 2);
+    }
+}
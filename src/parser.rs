@@ -15,15 +15,34 @@ const FUNCTION_ARG_LIMIT: usize = 255;
 pub struct Parser {
     tokens: Vec<Token>,
     current: usize,
+    // Errors reported while parsing. The parser synchronizes after each of them such that we
+    // could report as many errors as possible in one go.
+    errors: Vec<ParserError>,
+    // Whether the first error was found because the tokens ran out in the middle of a
+    // declaration, meaning the source is likely incomplete rather than wrong
+    ended_early: bool,
 }
 
 impl Parser {
     pub fn new(tokens: Vec<Token>) -> Self {
-        Self { tokens, current: 0 }
+        Self {
+            tokens,
+            current: 0,
+            errors: vec![],
+            ended_early: false,
+        }
     }
 
     pub fn reset(&mut self) {
-        self.current = 0
+        self.current = 0;
+        self.errors.clear();
+        self.ended_early = false;
+    }
+
+    /// Returns whether the last parse failed only because the input ended before the
+    /// declaration it was parsing was complete, as with an unclosed brace or a missing `;`
+    pub fn ended_early(&self) -> bool {
+        self.ended_early
     }
 
     pub fn parse(&mut self) -> Result<Vec<Stmt>, ParserError> {
@@ -33,9 +52,27 @@ impl Parser {
                 statements.push(declaration);
             }
         }
+        // If we found any error along the way, the statements are not safe to be executed
+        if !self.errors.is_empty() {
+            return Err(ParserError::Multiple(std::mem::take(&mut self.errors)));
+        }
         Ok(statements)
     }
 
+    // Parses the entire token list as a single expression
+    pub fn parse_expression(&mut self) -> Result<Expr, ParserError> {
+        self.reset();
+        let expr = self.separator()?;
+        // Any tokens left after the expression means the input was not a lone expression
+        if self.tokens_left()? {
+            return Err(ParserError::PanicMode(
+                "Expect end of input after expression".to_string(),
+                self.peek()?.clone(),
+            ));
+        }
+        Ok(expr)
+    }
+
     // Parses a Malis Declaration, which is in fact a node of statement
     fn declaration(&mut self) -> Result<Option<Stmt>, ParserError> {
         // We could have 1 type of declaration as a statement: variable declaration
@@ -63,12 +100,17 @@ impl Parser {
             self.statement()
         };
 
-        if maybe_declaration.is_err() {
-            println!("{:?}", maybe_declaration.err());
-            self.synchronize()?;
-            return Ok(None);
+        match maybe_declaration {
+            Ok(declaration) => Ok(Some(declaration)),
+            Err(err) => {
+                if self.errors.is_empty() && !self.tokens_left()? {
+                    self.ended_early = true;
+                }
+                self.errors.push(err);
+                self.synchronize()?;
+                Ok(None)
+            }
         }
-        maybe_declaration.map(Some)
     }
 
     // Parses a Malis class declaration and it's respective methods
@@ -114,7 +156,7 @@ impl Parser {
         let mut methods = vec![];
         // Here we consume methods of the class, as long as we do not reach the ending brace
         let right_brace = TokenType::SingleChar(SingleChar::RightBrace);
-        while !self.any(&[&right_brace])? {
+        while !self.any(&[&right_brace])? && self.tokens_left()? {
             methods.push(self.function_declaration(FunctionKind::Method)?);
         }
         // We need to consume the left parenthesis `(` in order to parse a proper parameter
@@ -401,9 +443,7 @@ impl Parser {
             body = Stmt::While(WhileStmt::new(condition, body));
         } else {
             body = Stmt::While(WhileStmt::new(
-                Expr::Literal(Literal {
-                    l_type: LiteralType::True,
-                }),
+                Expr::Literal(Literal::from(LiteralType::True)),
                 body,
             ));
         }
@@ -414,11 +454,6 @@ impl Parser {
             body = Stmt::Block(vec![initialiser, body]);
         }
 
-        println!(
-            "For loop body {:#?}",
-            crate::AstPrinter.print_stmt(&[body.clone()])
-        );
-
         Ok(body)
     }
 
@@ -482,8 +517,8 @@ impl Parser {
 
         let right_brace = TokenType::SingleChar(SingleChar::RightBrace);
 
-        // While we did not reach the ending right brace
-        while !self.any(&[&right_brace])? {
+        // While we did not reach the ending right brace or the end of the input
+        while !self.any(&[&right_brace])? && self.tokens_left()? {
             // Consume the next declaration
             if let Some(declaration) = self.declaration()? {
                 // Add it to the list of statements
@@ -0,0 +1,339 @@
+use crate::{
+    error::MalisError, interpreter::MalisObject, parser::Parser, scanner::Scanner,
+    visit::AstPrinter, Malis,
+};
+use rustyline::{
+    completion::{Completer, Pair},
+    highlight::Highlighter,
+    hint::Hinter,
+    validate::Validator,
+    Context, Helper,
+};
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+use std::time::Instant;
+
+// Name of the file, in the user's home directory, in which the REPL history is kept between
+// sessions
+const HISTORY_FILE: &str = ".malis_history";
+
+const HELP: &str = "\
+Commands:
+    :help           Print this message
+    :env            List the globals and their types
+    :type <expr>    Print the type of the value of an expression
+    :ast <code>     Print the syntax tree of some code
+    :tokens <code>  Print the tokens of some code
+    :load <file>    Execute a script in the current session
+    :reset          Start over with a fresh interpreter
+    :time <code>    Execute some code and print how long it took
+    :save <file>    Write every input executed successfully so far to a script
+    q, quit, exit   Leave the prompt";
+
+/// A line read by the interactive prompt
+pub enum ReplLine {
+    Line(String),
+    // The user pressed `Ctrl-C`, abandoning what they typed so far
+    Interrupted,
+    // The input ended or the user pressed `Ctrl-D`
+    Eof,
+}
+
+// Returns the path of the history file, if we know where the user's home directory is
+pub fn history_path() -> Option<PathBuf> {
+    std::env::var_os("HOME")
+        .or_else(|| std::env::var_os("USERPROFILE"))
+        .filter(|home| !home.is_empty())
+        .map(|home| PathBuf::from(home).join(HISTORY_FILE))
+}
+
+impl Malis {
+    /// Executes a command of the interactive prompt, which starts with `:`
+    pub(crate) fn meta_command(&mut self, command: &str) -> Result<(), MalisError> {
+        let (name, argument) = command
+            .split_once(char::is_whitespace)
+            .map_or((command, ""), |(name, argument)| (name, argument.trim()));
+
+        let needs_argument = |what: &str| {
+            if argument.is_empty() {
+                Err(MalisError::InvalidCommand(format!(
+                    "`{name}` expects {what}"
+                )))
+            } else {
+                Ok(argument)
+            }
+        };
+
+        match name {
+            ":help" => writeln!(self.interpreter.output(), "{HELP}")?,
+            ":env" => {
+                let mut names = self.interpreter.global_names();
+                names.sort();
+                for name in names {
+                    let Some(value) = self.interpreter.get_global(&name) else {
+                        continue;
+                    };
+                    writeln!(
+                        self.interpreter.output(),
+                        "{name}: {}",
+                        describe_type(&value)
+                    )?;
+                }
+            }
+            ":type" => {
+                let source = needs_argument("an expression")?;
+                let tokens = Scanner::new(source).scan_tokens()?;
+                let expr = Parser::new(tokens).parse_expression()?;
+                let value = self.interpreter.evaluate(&expr)?;
+                writeln!(self.interpreter.output(), "{}", describe_type(&value))?;
+            }
+            ":ast" => {
+                let source = needs_argument("some code")?;
+                let tokens = Scanner::new(source).scan_tokens()?;
+                let mut parser = Parser::new(tokens);
+                // Just like the prompt, we accept both statements and bare expressions
+                let ast = match parser.parse() {
+                    Ok(stmts) => AstPrinter.print_stmt(&stmts),
+                    Err(err) => {
+                        let expr = parser.parse_expression().map_err(|_| err)?;
+                        AstPrinter.print_expr(&expr)
+                    }
+                };
+                writeln!(self.interpreter.output(), "{ast}")?;
+            }
+            ":tokens" => {
+                let tokens = Malis::tokens(needs_argument("some code")?)?;
+                writeln!(self.interpreter.output(), "{tokens}")?;
+            }
+            ":load" => {
+                let source = fs::read_to_string(needs_argument("a script path")?)?;
+                self.run(source.as_str(), false)?;
+                self.record(source.as_str());
+            }
+            ":reset" => {
+                self.interpreter.reset()?;
+                self.session.clear();
+            }
+            ":time" => {
+                let source = needs_argument("some code")?;
+                let start = Instant::now();
+                self.run(source, true)?;
+                let elapsed = start.elapsed();
+                self.record(source);
+                writeln!(self.interpreter.output(), "Elapsed: {elapsed:?}")?;
+            }
+            ":save" => {
+                let path = needs_argument("a script path")?;
+                fs::write(path, self.session.concat())?;
+                writeln!(
+                    self.interpreter.output(),
+                    "Saved {} inputs to {path}",
+                    self.session.len()
+                )?;
+            }
+            _ => {
+                return Err(MalisError::InvalidCommand(format!(
+                    "Unknown command `{name}`, use `:help` to list the available ones"
+                )))
+            }
+        }
+        Ok(())
+    }
+
+    // Remembers an input which executed successfully, such that it could be saved later. Bare
+    // expressions are saved as `print` statements, since the prompt printed their value.
+    pub(crate) fn record(&mut self, source: &str) {
+        let is_expression = Scanner::new(source)
+            .scan_tokens()
+            .is_ok_and(|tokens| Parser::new(tokens).parse().is_err());
+
+        let source = source.trim_end();
+        if source.is_empty() {
+            return;
+        }
+        if is_expression {
+            self.session.push(format!("print {source};\n"));
+        } else {
+            self.session.push(format!("{source}\n"));
+        }
+    }
+}
+
+// Describes the type of `value`, including the class of instances
+fn describe_type(value: &MalisObject) -> String {
+    match value {
+        MalisObject::Instance(instance) => format!("instance of {}", instance.name()),
+        _ => value.type_name().to_string(),
+    }
+}
+
+/// Names that the interactive prompt could complete, gathered from the state of the interpreter
+#[derive(Debug, Default)]
+pub struct Completions {
+    // Keywords and names defined in the global environment
+    names: Vec<String>,
+    // Fields and methods which could follow a dot, for each global instance or class
+    members: HashMap<String, Vec<String>>,
+}
+
+impl Completions {
+    pub fn new(names: Vec<String>, members: HashMap<String, Vec<String>>) -> Self {
+        let mut names = Scanner::keywords()
+            .map(str::to_string)
+            .chain(names)
+            .collect::<Vec<_>>();
+        names.sort();
+        names.dedup();
+        Self { names, members }
+    }
+
+    // Returns the position where the word under the cursor at `pos` starts and the candidates
+    // that it could be completed with
+    fn complete(&self, line: &str, pos: usize) -> (usize, Vec<String>) {
+        let line = &line[..pos];
+        let start = word_start(line);
+        let prefix = &line[start..];
+
+        // After a dot, we complete the members of the object that precedes it
+        let candidates = match line[..start].strip_suffix('.') {
+            Some(object) => {
+                let object = &object[word_start(object)..];
+                self.members.get(object).map_or(&[][..], Vec::as_slice)
+            }
+            None => self.names.as_slice(),
+        };
+
+        let mut matches = candidates
+            .iter()
+            .filter(|candidate| candidate.starts_with(prefix))
+            .cloned()
+            .collect::<Vec<_>>();
+        matches.sort();
+        matches.dedup();
+        (start, matches)
+    }
+}
+
+// Returns the index where the identifier that ends `line` starts
+fn word_start(line: &str) -> usize {
+    line.char_indices()
+        .rev()
+        .take_while(|(_, ch)| ch.is_ascii_alphanumeric() || *ch == '_')
+        .last()
+        .map_or(line.len(), |(idx, _)| idx)
+}
+
+/// Line editor helper for the interactive prompt, providing tab completion
+#[derive(Debug, Default)]
+pub struct MalisHelper {
+    completions: Completions,
+}
+
+impl MalisHelper {
+    pub fn set_completions(&mut self, completions: Completions) {
+        self.completions = completions;
+    }
+}
+
+impl Completer for MalisHelper {
+    type Candidate = Pair;
+
+    fn complete(
+        &self,
+        line: &str,
+        pos: usize,
+        _ctx: &Context<'_>,
+    ) -> rustyline::Result<(usize, Vec<Pair>)> {
+        let (start, matches) = self.completions.complete(line, pos);
+        let pairs = matches
+            .into_iter()
+            .map(|name| Pair {
+                display: name.clone(),
+                replacement: name,
+            })
+            .collect();
+        Ok((start, pairs))
+    }
+}
+
+impl Hinter for MalisHelper {
+    type Hint = String;
+}
+
+impl Highlighter for MalisHelper {}
+
+impl Validator for MalisHelper {}
+
+impl Helper for MalisHelper {}
+
+#[cfg(test)]
+mod tests {
+    use super::Completions;
+    use crate::{Malis, SharedBuffer};
+    use std::collections::HashMap;
+
+    #[test]
+    fn meta_commands() {
+        let output = SharedBuffer::new();
+        let diagnostics = SharedBuffer::new();
+        let mut malis = Malis::with_output(output.clone(), diagnostics.clone())
+            .expect("Failed to create interpreter");
+        let session = std::env::temp_dir().join(format!("malis_session_{}.ms", std::process::id()));
+
+        let input = format!(
+            "var a = 2;\nclass Tort {{}}\nvar t = Tort();\na * 3\n:type t\n:type a\n:ast a + 1\n\
+             :save {}\n:reset\n:type a\n:nimic\n",
+            session.display()
+        );
+        malis
+            .repl(input.as_bytes())
+            .expect("Failed to run the REPL");
+
+        let output = output.contents();
+        assert!(output.contains("instance of Tort\nnumber\n(+ (var a) Number("));
+        assert_eq!(
+            std::fs::read_to_string(&session).expect("Failed to read the saved session"),
+            "var a = 2;\nclass Tort {}\nvar t = Tort();\nprint a * 3;\n"
+        );
+        let _ = std::fs::remove_file(session);
+
+        // After `:reset`, `a` is no longer defined
+        let diagnostics = diagnostics.contents();
+        assert!(
+            diagnostics.contains("UndefinedVariable(\"a\")"),
+            "{diagnostics}"
+        );
+        assert!(
+            diagnostics.contains("Unknown command `:nimic`"),
+            "{diagnostics}"
+        );
+    }
+
+    #[test]
+    fn completes_names_and_members() {
+        let completions = Completions::new(
+            vec!["counter".to_string(), "clock".to_string()],
+            HashMap::from([(
+                "tort".to_string(),
+                vec!["aroma".to_string(), "adauga".to_string()],
+            )]),
+        );
+
+        // Keywords and globals share the same namespace
+        assert_eq!(
+            completions.complete("print cl", 8),
+            (6, vec!["class".to_string(), "clock".to_string()])
+        );
+        assert_eq!(
+            completions.complete("tort.a", 6),
+            (5, vec!["adauga".to_string(), "aroma".to_string()])
+        );
+        // Only the part of the line before the cursor matters
+        assert_eq!(
+            completions.complete("cou + 1", 3),
+            (0, vec!["counter".to_string()])
+        );
+        assert_eq!(completions.complete("nimic.", 6), (6, vec![]));
+    }
+}
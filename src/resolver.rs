@@ -5,11 +5,14 @@ use crate::{
         Logical, ReturnStmt, SetExpr, Stmt, SuperExpr, Ternary, Unary, VarStmt, WhileStmt,
     },
     error::ResolverError,
-    token::Token,
+    token::{Keyword, Token, TokenType},
     visit::{ExprVisitor, StmtVisitor},
 };
 use std::collections::{HashMap, LinkedList};
 
+// Names declared in a scope, along with what we know about them. See `Resolver::scopes`.
+type Scope = HashMap<String, (bool, bool, Option<Token>)>;
+
 // The resolver visits every node in the syntax tree and could perform the following actions:
 // - Define a new scope
 // - Append to an existing scope
@@ -26,10 +29,11 @@ pub struct Resolver<'a> {
     // which their resolution is found.
     interpreter: &'a mut Interpreter,
     // Keeps track of all scopes in the form of a stack. Top most element is the innermost scope.
-    // We use the key `String` as the name of the variable. The value is split in 2:
+    // We use the key `String` as the name of the variable. The value is split in 3:
     // 1. First one flags that the variable was declared but not defined
     // 2. Second one defines that the variable was declared and defined but it is never used
-    scopes: LinkedList<HashMap<String, (bool, bool)>>,
+    // 3. Third one is the token which declared the variable, used to locate errors about it
+    scopes: LinkedList<Scope>,
     // Keeps track if for this current point in time, the resolver is whithin a function scope or
     // not. This is used in order to prevent invalid `return` statements, as the ones which are not
     // inside a function.
@@ -37,6 +41,19 @@ pub struct Resolver<'a> {
     // Keeps track if for this current point in time, the resolver is withing a class in order to
     // be able to tell if we should resolve `self` or other types of OOP functionality
     current_class: ClassType,
+    // Where names are declared and used, only recorded for tools like the language server
+    bindings: Option<Bindings>,
+}
+
+/// Declarations and uses of names found by a `recording` resolver, which also carries on after
+/// errors and keeps all of them
+#[derive(Debug, Default)]
+pub(crate) struct Bindings {
+    /// Tokens declaring a name, along with the number of scopes enclosing them. Globals are at 0.
+    pub declarations: Vec<(Token, usize)>,
+    /// Each use of a name, along with the token declaring it if there is one
+    pub uses: Vec<(Token, Option<Token>)>,
+    pub errors: Vec<ResolverError>,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -60,9 +77,20 @@ impl<'a> Resolver<'a> {
             scopes: LinkedList::new(),
             current_function: ResolverFunctionType::None,
             current_class: ClassType::None,
+            bindings: None,
         }
     }
 
+    /// Makes the resolver record its `Bindings`, instead of stopping at the first error
+    pub(crate) fn recording(mut self) -> Self {
+        self.bindings = Some(Bindings::default());
+        self
+    }
+
+    pub(crate) fn into_bindings(self) -> Bindings {
+        self.bindings.unwrap_or_default()
+    }
+
     pub fn resolve(&mut self, stmts: &[Stmt]) -> Result<(), ResolverError> {
         // Begin a new scope, the global scope
         self.begin_scope();
@@ -70,8 +98,7 @@ impl<'a> Resolver<'a> {
             self.resolve_stmt(stmt)?;
         }
         // End the scope before exiting
-        self.end_scope();
-        Ok(())
+        self.end_scope()
     }
 
     fn resolve_stmt(&mut self, stmt: &Stmt) -> Result<(), ResolverError> {
@@ -112,14 +139,14 @@ impl<'a> Resolver<'a> {
 
         // We first declare and define each of the function's parameters
         for param in function.parameters.iter() {
-            self.declare(param.lexeme());
+            self.declare(param)?;
             self.define(param.lexeme());
         }
 
         // Afterards, we resolve the function body
         self.resolve(&function.body)?;
 
-        self.end_scope();
+        self.end_scope()?;
         // We revert the current function back to the state it was in before calling this
         // `resolve_function`
         self.current_function = func_state;
@@ -127,45 +154,126 @@ impl<'a> Resolver<'a> {
         Ok(())
     }
 
+    // Stops at `err`, unless we are recording, in which case we keep it and carry on
+    fn fail(&mut self, err: ResolverError) -> Result<(), ResolverError> {
+        match &mut self.bindings {
+            Some(bindings) => {
+                bindings.errors.push(err);
+                Ok(())
+            }
+            None => Err(err),
+        }
+    }
+
+    // Records that `name` is used here, along with the innermost declaration it could refer to.
+    // Names which are only used in a scope do not count as declarations.
+    fn record_use(&mut self, name: &Token) {
+        if let Some(bindings) = &mut self.bindings {
+            let declaration = self.scopes.iter().rev().find_map(|scope| {
+                scope
+                    .get(name.lexeme())
+                    .and_then(|(_, _, token)| token.clone())
+            });
+            bindings.uses.push((name.clone(), declaration));
+        }
+    }
+
     fn begin_scope(&mut self) {
         self.scopes.push_back(HashMap::new());
     }
 
-    fn declare(&mut self, name: &str) {
+    fn declare(&mut self, token: &Token) -> Result<(), ResolverError> {
+        let name = token.lexeme();
+        // If the variable was already declared, the user should've just assigned to it. Globals
+        // could be declared again though, replacing the previous value, like in the REPL.
+        if self.scopes.len() > 1
+            && self
+                .scopes
+                .back()
+                .is_some_and(|current_scope| current_scope.contains_key(name))
+        {
+            // At this point we have a double initialisation
+            return self.fail(located(
+                ResolverError::DoubleInitialise(format!(
+                    "Already a variable with this name in this scope {:?}",
+                    name
+                )),
+                token,
+            ));
+        }
         // We get a mutable reference to the top stack scope. This way the variable will be
         // declared in the the innermost scope and will shadow any other existing variable with the
         // same name
         if let Some(current_scope) = self.scopes.back_mut() {
-            // If the variable was already declared, the user should've just assigned to it.
-            if current_scope.contains_key(name) {
-                // At this point we have a double initialisation
-                panic!("Already a variable with this name in this scope {:?}", name);
-            }
             // And insert the new declaration in this scope. Because we did not resolve the variable
             // yet, we insert it with a `false` flag in the scopes `HashMap`.
-            current_scope.insert(name.to_string(), (false, false));
+            current_scope.insert(name.to_string(), (false, false, Some(token.clone())));
+        }
+        if let Some(bindings) = &mut self.bindings {
+            let depth = self.scopes.len().saturating_sub(1);
+            bindings.declarations.push((token.clone(), depth));
         }
+        Ok(())
     }
 
     fn define(&mut self, name: &str) {
         // At this point, initializer for the variable represented by name should have been run
         // and we mark it as such in the scope
         if let Some(current_scope) = self.scopes.back_mut() {
-            current_scope.insert(name.to_string(), (true, false));
+            current_scope
+                .entry(name.to_string())
+                .and_modify(|entry| entry.0 = true)
+                .or_insert((true, false, None));
         }
     }
 
-    fn end_scope(&mut self) {
+    // Marks the variable identified by `name` as used in the innermost scope that declares it.
+    fn mark_used(&mut self, name: &str) {
+        if let Some(entry) = self
+            .scopes
+            .iter_mut()
+            .rev()
+            .find_map(|scope| scope.get_mut(name))
+        {
+            entry.1 = true;
+        }
+    }
+
+    fn end_scope(&mut self) -> Result<(), ResolverError> {
         // Pop the inner most scope
         if let Some(scope) = self.scopes.pop_back() {
+            // The outermost scope holds the globals, which could still be used by code that is
+            // resolved later on (for example the next line in the REPL), so we do not check it.
+            if self.scopes.is_empty() {
+                // Uses we could not link to a declaration could still refer to globals declared
+                // after them, like functions calling each other
+                if let Some(bindings) = &mut self.bindings {
+                    for (name, declaration) in bindings.uses.iter_mut() {
+                        if declaration.is_none() {
+                            *declaration = scope
+                                .get(name.lexeme())
+                                .and_then(|(_, _, token)| token.clone());
+                        }
+                    }
+                }
+                return Ok(());
+            }
             // Verify all the names defined in the scope are being used. Except `self` which is
             // a keyword to access the current instance
-            for (key, (defined, accessed)) in scope.iter() {
+            for (key, (defined, accessed, token)) in scope.iter() {
                 if defined == &true && accessed == &false && key != "self" && key != "super" {
-                    panic!("Variable defined in this scope is not used {:?}", key);
+                    let err = ResolverError::UnusedName(format!(
+                        "Variable defined in this scope is not used {:?}",
+                        key
+                    ));
+                    self.fail(match token {
+                        Some(token) => located(err, token),
+                        None => err,
+                    })?;
                 }
             }
         }
+        Ok(())
     }
 }
 
@@ -196,18 +304,31 @@ impl ExprVisitor<Result<(), ResolverError>> for Resolver<'_> {
 
     fn visit_variable(&mut self, variable: &Token) -> Result<(), ResolverError> {
         // We read the scope map and check whether the variable is defined in the current scope.
-        if let Some(current_scope) = self.scopes.back_mut() {
-            // If the variable is in this scope but it's initializer flag is false, it means it
-            // was declared but not defined yet. We consider this an error and we report it.
-            if current_scope.get(variable.lexeme()) == Some(&(false, false)) {
-                return Err(ResolverError::NotInitialized(format!(
+        // If the variable is in this scope but it's initializer flag is false, it means it was
+        // declared but not defined yet. We consider this an error and we report it.
+        if matches!(
+            self.scopes
+                .back()
+                .and_then(|current_scope| current_scope.get(variable.lexeme())),
+            Some((false, false, _))
+        ) {
+            self.fail(located(
+                ResolverError::NotInitialized(format!(
                     "Can't access local variable {} in it own initializer.",
                     variable
-                )));
-            } else {
-                // We mark the variable as accessed
-                current_scope.insert(variable.lexeme().to_string(), (true, true));
-            }
+                )),
+                variable,
+            ))?;
+        }
+        self.record_use(variable);
+        // We mark the variable as accessed in the scope that declares it
+        self.mark_used(variable.lexeme());
+        // And also in the current scope, such that the access is resolved from here
+        if let Some(current_scope) = self.scopes.back_mut() {
+            current_scope
+                .entry(variable.lexeme().to_string())
+                .and_modify(|entry| (entry.0, entry.1) = (true, true))
+                .or_insert((true, true, None));
         }
         // At this point, we know we should have a value for the variable and we resolve it
         self.resolve_local(format!("{:p}", variable), variable)?;
@@ -216,7 +337,17 @@ impl ExprVisitor<Result<(), ResolverError>> for Resolver<'_> {
 
     fn visit_assign(&mut self, ident: &Token, expr: &Expr) -> Result<(), ResolverError> {
         self.resolve_expr(expr)?;
-        self.resolve_local(format!("{:p}", expr), ident)?;
+        self.record_use(ident);
+        // Like accesses, assignments look for the variable from the current environment outwards,
+        // as a block opens two scopes here but a single environment at runtime. This also keeps a
+        // variable declared later in the same block from being assigned before it exists.
+        if self
+            .scopes
+            .iter()
+            .any(|scope| scope.contains_key(ident.lexeme()))
+        {
+            self.interpreter.resolve(format!("{:p}", expr), 0)?;
+        }
         Ok(())
     }
 
@@ -249,10 +380,13 @@ impl ExprVisitor<Result<(), ResolverError>> for Resolver<'_> {
 
     fn visit_self(&mut self, class_self: &Token) -> Result<(), ResolverError> {
         if let ClassType::None = self.current_class {
-            return Err(ResolverError::InvalidSelfUse(format!(
-                "Can't use `self` keyword outside a class {}.",
-                class_self
-            )));
+            self.fail(located(
+                ResolverError::InvalidSelfUse(format!(
+                    "Can't use `self` keyword outside a class {}.",
+                    class_self
+                )),
+                class_self,
+            ))?;
         }
         self.resolve_local(format!("{:p}", class_self), class_self)
     }
@@ -261,18 +395,20 @@ impl ExprVisitor<Result<(), ResolverError>> for Resolver<'_> {
         // Check to see whether wi are outside a class or in a class thath does not inherit from
         // another class. The use of `super` in this cases is invalid.
         match self.current_class {
-            ClassType::None => {
-                return Err(ResolverError::InvalidSuperUse(format!(
+            ClassType::None => self.fail(located(
+                ResolverError::InvalidSuperUse(format!(
                     "Can't use `super` expression outside of a class -> {}",
                     super_expr.keyword()
-                )))
-            }
-            ClassType::Class => {
-                return Err(ResolverError::InvalidSuperUse(format!(
+                )),
+                super_expr.keyword(),
+            ))?,
+            ClassType::Class => self.fail(located(
+                ResolverError::InvalidSuperUse(format!(
                     "Can't use `super` expression in a class which does not inherit -> {}",
                     super_expr.keyword()
-                )))
-            }
+                )),
+                super_expr.keyword(),
+            ))?,
             _ => (),
         };
         // We save the `super` expression with an unique key based on the token of the keyword
@@ -300,7 +436,7 @@ impl StmtVisitor<Result<(), ResolverError>> for Resolver<'_> {
 
     fn visit_var_stmt(&mut self, stmt: &VarStmt) -> Result<(), ResolverError> {
         // We spilt variable initialization into 2 steps: declaring and defining.
-        self.declare(stmt.identifier().lexeme());
+        self.declare(stmt.identifier())?;
         if let Some(expr) = &stmt.expr() {
             self.resolve_expr(expr)?;
         }
@@ -314,8 +450,7 @@ impl StmtVisitor<Result<(), ResolverError>> for Resolver<'_> {
         // It resolves the statement inside it
         self.resolve(stmts)?;
         // And finished the scope afterwards
-        self.end_scope();
-        Ok(())
+        self.end_scope()
     }
 
     fn visit_if_stmt(&mut self, stmt: &IfStmt) -> Result<(), ResolverError> {
@@ -341,10 +476,13 @@ impl StmtVisitor<Result<(), ResolverError>> for Resolver<'_> {
     fn visit_return_stmt(&mut self, stmt: &ReturnStmt) -> Result<(), ResolverError> {
         // We first check if we are in a function's scope
         if self.current_function == ResolverFunctionType::None {
-            return Err(ResolverError::ReturnOutsideFunction(format!(
-                "Can't return from top-level code: {:?}",
-                stmt.keyword()
-            )));
+            self.fail(located(
+                ResolverError::ReturnOutsideFunction(format!(
+                    "Can't return from top-level code: {:?}",
+                    stmt.keyword()
+                )),
+                stmt.keyword(),
+            ))?;
         }
         // If return also comes with a value to be returned
         if let Some(value) = stmt.expr() {
@@ -359,7 +497,7 @@ impl StmtVisitor<Result<(), ResolverError>> for Resolver<'_> {
         // of the function is bound in the current scope where the function is declared. And when
         // we step into the function's body, we also bind its parameters to the new scope introduced
         // by the function's body.
-        self.declare(function.name.lexeme());
+        self.declare(&function.name)?;
         // We define the function eagerly, just after declaration. This enables a function to call
         // itself and do recursion.
         self.define(function.name.lexeme());
@@ -373,7 +511,7 @@ impl StmtVisitor<Result<(), ResolverError>> for Resolver<'_> {
         self.current_class = ClassType::Class;
         // The Malis resolver essentially sees this class as just a variable
         // Declare the class
-        self.declare(class.name.lexeme());
+        self.declare(&class.name)?;
         // Define the class
         self.define(class.name.lexeme());
         // Also resolve the superclass which we treat as a variable, because at runtime, this
@@ -385,10 +523,13 @@ impl StmtVisitor<Result<(), ResolverError>> for Resolver<'_> {
             // We need to check that the current class does not try to inherit itself, such that
             // when the interpreter gets its turn, we do not run into cycles.
             if superclass.lexeme() == class.name.lexeme() {
-                return Err(ResolverError::SelfInheritance(format!(
-                    "A class cannot inherit from itself -> {}",
-                    class.name
-                )));
+                self.fail(located(
+                    ResolverError::SelfInheritance(format!(
+                        "A class cannot inherit from itself -> {}",
+                        class.name
+                    )),
+                    superclass,
+                ))?;
             }
             self.visit_variable(superclass)?;
 
@@ -397,7 +538,7 @@ impl StmtVisitor<Result<(), ResolverError>> for Resolver<'_> {
             self.begin_scope();
             // We then declare and define super as a variable of that scope, such that the methods
             // could access a known variable.
-            self.declare("super");
+            self.declare(&Token::create(TokenType::Keyword(Keyword::Super), "super"))?;
             self.define("super");
         }
         // Create a new scope for the class declaration. This will aid `self` keyword to access
@@ -414,11 +555,11 @@ impl StmtVisitor<Result<(), ResolverError>> for Resolver<'_> {
             }
         }
         // Terminate the scope started for this class' properties and methods
-        self.end_scope();
+        self.end_scope()?;
         // Terminate the scope (if any) started in order to enclose a superclass environment for
         // the use of the `self` keyword.
         if let Some(_superclass) = &class.superclass {
-            self.end_scope();
+            self.end_scope()?;
         }
         // Revert the class scope to the previous checkpoint
         self.current_class = class_type;
@@ -426,3 +567,8 @@ impl StmtVisitor<Result<(), ResolverError>> for Resolver<'_> {
         Ok(())
     }
 }
+
+// Attaches the token at which `err` was found to it
+fn located(err: ResolverError, token: &Token) -> ResolverError {
+    ResolverError::Located(Box::new(err), token.clone())
+}
@@ -1,8 +1,29 @@
-use crate::error::{ScannerError, SourceError};
+use crate::error::ScannerError;
 use crate::token::{Comparison, Keyword, Literal, SingleChar, Token, TokenType};
 use core::iter::Peekable;
 use core::str::CharIndices;
-use std::{collections::HashMap, io::Write};
+use std::collections::HashMap;
+
+// Reserved words of the language
+const KEYWORDS: [(&str, Keyword); 17] = [
+    ("and", Keyword::And),
+    ("or", Keyword::Or),
+    ("not", Keyword::Not),
+    ("class", Keyword::Class),
+    ("fun", Keyword::Fun),
+    ("if", Keyword::If),
+    ("else", Keyword::Else),
+    ("while", Keyword::While),
+    ("for", Keyword::For),
+    ("true", Keyword::True),
+    ("false", Keyword::False),
+    ("nil", Keyword::Nil),
+    ("var", Keyword::Var),
+    ("print", Keyword::Print),
+    ("return", Keyword::Return),
+    ("self", Keyword::ClassSelf),
+    ("super", Keyword::Super),
+];
 
 #[derive(Debug)]
 pub struct Scanner<'a> {
@@ -13,6 +34,8 @@ pub struct Scanner<'a> {
     line: usize,
     // Keywords of the language
     keywords: HashMap<&'a str, Keyword>,
+    // Whether comments are issued as `Comment` tokens instead of being discarded
+    keep_comments: bool,
 }
 
 impl<'a> Scanner<'a> {
@@ -20,33 +43,29 @@ impl<'a> Scanner<'a> {
     pub fn new(data: &'a str) -> Self {
         // We instantiate a dictionary for reserved words here, such that we save processing power
         // when we parse identifiers
-        let keywords = HashMap::from([
-            ("and", Keyword::And),
-            ("or", Keyword::Or),
-            ("not", Keyword::Not),
-            ("class", Keyword::Class),
-            ("fun", Keyword::Fun),
-            ("if", Keyword::If),
-            ("else", Keyword::Else),
-            ("while", Keyword::While),
-            ("for", Keyword::For),
-            ("true", Keyword::True),
-            ("false", Keyword::False),
-            ("nil", Keyword::Nil),
-            ("var", Keyword::Var),
-            ("print", Keyword::Print),
-            ("return", Keyword::Return),
-            ("self", Keyword::ClassSelf),
-            ("super", Keyword::Super),
-        ]);
+        let keywords = HashMap::from(KEYWORDS);
         Self {
             data,
             offset: 0,
             line: 1,
             keywords,
+            keep_comments: false,
+        }
+    }
+
+    // Creates a new scanner from the given bytes which also issues tokens for comments
+    pub fn with_comments(data: &'a str) -> Self {
+        Self {
+            keep_comments: true,
+            ..Self::new(data)
         }
     }
 
+    /// Returns the reserved words of the language
+    pub fn keywords() -> impl Iterator<Item = &'static str> {
+        KEYWORDS.iter().map(|(keyword, _)| *keyword)
+    }
+
     /// Scan through the internal buffer and issue `Token`s
     pub fn scan_tokens(&mut self) -> Result<Vec<Token>, Vec<ScannerError>> {
         let mut token_list = vec![];
@@ -65,7 +84,9 @@ impl<'a> Scanner<'a> {
             match maybe_token {
                 Ok(token) => {
                     // If the current token is classified as `Ignored` we move to the next iteration
-                    if token.t_type() == &TokenType::Ignored {
+                    if token.t_type() == &TokenType::Ignored
+                        || (token.t_type() == &TokenType::Comment && !self.keep_comments)
+                    {
                         continue;
                     }
                     // At this point, the token needs to be in the token list
@@ -81,6 +102,10 @@ impl<'a> Scanner<'a> {
             Err(err) => error_list.push(err),
         };
 
+        if !error_list.is_empty() {
+            return Err(error_list);
+        }
+
         Ok(token_list)
     }
 
@@ -179,35 +204,37 @@ impl<'a> Scanner<'a> {
             }
             '/' => {
                 if self.match_next('/', chars) {
+                    self.offset = start + 2;
                     // A comment goes until the end of line. So we lookahead until we find
                     // a newline
-                    while let Some((idx, peek_ch)) = chars.peek() {
+                    while let Some(&(idx, peek_ch)) = chars.peek() {
                         // If we are not at the `newline` char, we consume the character.
-                        if peek_ch != &'\n' {
-                            self.offset = *idx;
+                        if peek_ch != '\n' {
+                            self.offset = idx + peek_ch.len_utf8();
                             chars.next();
                         } else {
                             break;
                         }
                     }
-                    self.create_token(TokenType::Ignored, start)?
+                    self.create_token(TokenType::Comment, start)?
                 } else if self.match_next('*', chars) {
+                    self.offset = start + 2;
                     // We do not allow multiline block comments to nest as it requires keeping
                     // a stack of previous open blocks characters `/*`
-                    while let Some(&(idx, peek_ch)) = chars.peek() {
-                        self.offset = idx;
-                        chars.next();
+                    while let Some((idx, peek_ch)) = chars.next() {
+                        self.offset = idx + peek_ch.len_utf8();
+                        if peek_ch == '\n' {
+                            self.line += 1;
+                        }
                         if peek_ch == '*' {
-                            if let Some(&(idx2, peek_ch2)) = chars.peek() {
-                                if peek_ch2 == '/' {
-                                    self.offset = idx2;
-                                    chars.next();
-                                    break;
-                                }
+                            if let Some(&(idx2, '/')) = chars.peek() {
+                                self.offset = idx2 + 1;
+                                chars.next();
+                                break;
                             }
                         }
                     }
-                    self.create_token(TokenType::Ignored, start)?
+                    self.create_token(TokenType::Comment, start)?
                 } else {
                     self.offset += 1;
                     self.create_token(TokenType::SingleChar(SingleChar::Slash), start)?
@@ -230,16 +257,7 @@ impl<'a> Scanner<'a> {
                 } else if ch.is_ascii_alphabetic() || ch == '_' {
                     self.parse_ident(start, chars)?
                 } else {
-                    let err = SourceError {
-                        line: self.line,
-                        location: start,
-                        err: format!("Unexpected character: {ch}"),
-                    };
-                    let mut stdout = std::io::stdout();
-                    stdout.write_fmt(format_args!("{err:?}"))?;
-                    stdout.flush()?;
-
-                    return Err(ScannerError::UnexpectedCharacter(ch));
+                    return Err(ScannerError::UnexpectedCharacter(ch, self.line));
                 }
             }
         };
@@ -269,6 +287,7 @@ impl<'a> Scanner<'a> {
         start: usize,
         chars: &mut Peekable<CharIndices>,
     ) -> Result<Token, ScannerError> {
+        let mut terminated = false;
         // While there is a next character in `chars`
         while let Some((idx, peek_ch)) = chars.peek() {
             // Update our offset to the current index position
@@ -283,15 +302,16 @@ impl<'a> Scanner<'a> {
                 self.offset += 1;
                 // Consume the final character of the literal string
                 chars.next();
+                terminated = true;
                 break;
             }
             // Consume the current peeked character to advance
             chars.next();
+        }
 
-            if self.offset == self.data.len() {
-                // If we are at the end and we did not end the string, return an error
-                return Err(ScannerError::UnterminatedString);
-            }
+        if !terminated {
+            // If we are at the end and we did not end the string, return an error
+            return Err(ScannerError::UnterminatedString(self.line));
         }
 
         // Get the string, without the surrounding quotes. This is the lexeme
@@ -368,10 +388,12 @@ impl<'a> Scanner<'a> {
                 self.offset = *idx;
                 chars.next();
             } else {
-                self.offset += 1;
                 break;
             }
         }
+        // Go past the last character of the identifier, which could also be the last one of the
+        // input
+        self.offset += 1;
         let value = self
             .data
             .get(start..self.offset)
@@ -397,6 +419,28 @@ impl<'a> Scanner<'a> {
             .get(start..self.offset)
             .ok_or(ScannerError::FailedToIndexSlice)?
             .to_string();
-        Ok(Token::new(token_type, text, self.line))
+        // The column is counted from the start of the line the token starts on
+        let line_start = self.data[..start].rfind('\n').map_or(0, |idx| idx + 1);
+        let column = self.data[line_start..start].chars().count() + 1;
+        Ok(Token::new(token_type, text, self.line, column))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Scanner;
+
+    #[test]
+    fn scans_identifiers_ending_the_input() {
+        let lexemes = |source| {
+            let tokens = Scanner::new(source).scan_tokens().expect("Failed to scan");
+            tokens
+                .iter()
+                .map(|token| token.lexeme().to_string())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(lexemes("var nume"), ["var", "nume", ""]);
+        assert_eq!(lexemes("x"), ["x", ""]);
+        assert_eq!(lexemes("print x;"), ["print", "x", ";", ""]);
     }
 }
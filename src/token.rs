@@ -9,6 +9,8 @@ pub struct Token {
     lexeme: String,
     // Line on which the token occurs
     line: usize,
+    // Column, counted in characters starting from 1, at which the token starts on its first line
+    column: usize,
 }
 
 impl fmt::Display for Token {
@@ -18,11 +20,12 @@ impl fmt::Display for Token {
 }
 
 impl Token {
-    pub fn new(t_type: TokenType, lexeme: String, line: usize) -> Self {
+    pub fn new(t_type: TokenType, lexeme: String, line: usize, column: usize) -> Self {
         Self {
             t_type,
             lexeme,
             line,
+            column,
         }
     }
 
@@ -38,12 +41,17 @@ impl Token {
         self.line
     }
 
+    pub fn column(&self) -> usize {
+        self.column
+    }
+
     pub fn create(t_type: TokenType, new_lexeme: &str) -> Self {
         let lexeme = new_lexeme.to_string();
         Self {
             t_type,
             lexeme,
             line: 0,
+            column: 0,
         }
     }
 }
@@ -55,6 +63,8 @@ pub enum TokenType {
     Literal(Literal),
     Keyword(Keyword),
     Ident,
+    // Only produced when the `Scanner` is asked to keep comments, as the formatter does
+    Comment,
     Ignored,
     Eof,
 }
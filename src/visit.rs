@@ -238,6 +238,45 @@ impl AstPrinter {
         expr.walk(self)
     }
 
+    // Prints the statement similar to `print_stmt`, but each nested node starts on a new line
+    // indented according to its depth in the tree
+    pub fn print_tree(&mut self, stmt: &Stmt) -> String {
+        let sexpr = stmt.walk(self);
+        let mut tree = String::with_capacity(sexpr.len());
+        let mut chars = sexpr.chars().peekable();
+        let mut depth = 0;
+        // Parenthesis inside string literals are not part of the tree structure
+        let mut in_string = false;
+        // Neither are the ones of literals, such as `LitString("a")`, which follow their name
+        let mut literal_depth = 0;
+        let mut previous = ' ';
+
+        while let Some(ch) = chars.next() {
+            match ch {
+                '"' => in_string = !in_string,
+                '(' if !in_string && previous.is_alphanumeric() => literal_depth += 1,
+                ')' if !in_string && literal_depth != 0 => literal_depth -= 1,
+                // The new line takes the place of the space separating the child node
+                ' ' if !in_string && chars.peek() == Some(&'(') => {
+                    previous = ch;
+                    continue;
+                }
+                '(' if !in_string => {
+                    if depth != 0 {
+                        tree.push('\n');
+                        tree.push_str(&"  ".repeat(depth));
+                    }
+                    depth += 1;
+                }
+                ')' if !in_string => depth -= 1,
+                _ => {}
+            }
+            tree.push(ch);
+            previous = ch;
+        }
+        tree
+    }
+
     pub fn print_stmt(&mut self, statements: &[Stmt]) -> String {
         let statements = statements
             .iter()
@@ -250,16 +289,16 @@ impl AstPrinter {
 #[cfg(test)]
 mod tests {
     use super::AstPrinter;
-    use crate::ast::{Binary, Expr, Group, Literal, LiteralType, Unary};
+    use crate::ast::{Binary, Expr, Group, Literal, LiteralType, Stmt, Unary};
     use crate::token::{SingleChar, Token, TokenType};
 
     #[test]
     fn unary_test() {
         let unary_expr = Unary {
             operator: Token::create(TokenType::SingleChar(SingleChar::Minus), "-"),
-            right: Box::new(Expr::Literal(Literal {
-                l_type: LiteralType::Number(1.72),
-            })),
+            right: Box::new(Expr::Literal(Literal::from(LiteralType::Number(
+                1.72f32.to_le_bytes(),
+            )))),
         };
         let mut ast_printer = AstPrinter;
         println!("Ast: {}", ast_printer.print_expr(&Expr::Unary(unary_expr)))
@@ -269,12 +308,12 @@ mod tests {
     fn binary_test() {
         let binary_expr = Binary {
             operator: Token::create(TokenType::SingleChar(SingleChar::Minus), "*"),
-            left: Box::new(Expr::Literal(Literal {
-                l_type: LiteralType::Number(425.12),
-            })),
-            right: Box::new(Expr::Literal(Literal {
-                l_type: LiteralType::Number(0.132),
-            })),
+            left: Box::new(Expr::Literal(Literal::from(LiteralType::Number(
+                425.12f32.to_le_bytes(),
+            )))),
+            right: Box::new(Expr::Literal(Literal::from(LiteralType::Number(
+                0.132f32.to_le_bytes(),
+            )))),
         };
         let mut ast_printer = AstPrinter;
         println!(
@@ -286,9 +325,9 @@ mod tests {
     #[test]
     fn grouping_test() {
         let grouping_expr = Group {
-            expr: Box::new(Expr::Literal(Literal {
-                l_type: LiteralType::Number(32.0),
-            })),
+            expr: Box::new(Expr::Literal(Literal::from(LiteralType::Number(
+                32.0f32.to_le_bytes(),
+            )))),
         };
         let mut ast_printer = AstPrinter;
         println!(
@@ -301,14 +340,14 @@ mod tests {
     fn nested_test() {
         let unary_expr = Unary {
             operator: Token::create(TokenType::SingleChar(SingleChar::Minus), "-"),
-            right: Box::new(Expr::Literal(Literal {
-                l_type: LiteralType::Number(987.65),
-            })),
+            right: Box::new(Expr::Literal(Literal::from(LiteralType::Number(
+                987.65f32.to_le_bytes(),
+            )))),
         };
         let grouping_expr = Group {
-            expr: Box::new(Expr::Literal(Literal {
-                l_type: LiteralType::Number(123.0),
-            })),
+            expr: Box::new(Expr::Literal(Literal::from(LiteralType::Number(
+                123.0f32.to_le_bytes(),
+            )))),
         };
         let binary_expr = Binary::new(
             Expr::Unary(unary_expr),
@@ -322,4 +361,18 @@ mod tests {
             ast_printer.print_expr(&Expr::Binary(binary_expr))
         )
     }
+
+    #[test]
+    fn tree_test() {
+        let stmt = Stmt::Print(Expr::Group(Group {
+            expr: Box::new(Expr::Literal(Literal::from(LiteralType::LitString(
+                "(not a node)".to_string(),
+            )))),
+        }));
+        let mut ast_printer = AstPrinter;
+        assert_eq!(
+            ast_printer.print_tree(&stmt),
+            "(print_stmt\n  (group LitString(\"(not a node)\")))"
+        );
+    }
 }
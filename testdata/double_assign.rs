@@ -1,4 +0,0 @@
-fun bad() {
-    var a = "first";
-    var a = "second";
-}
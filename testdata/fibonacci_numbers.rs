@@ -1,8 +0,0 @@
-fun fib(n) {
-    if (n <= 1) return n;
-    return fib(n-1) + fib(n-2);
-}
-
-for (var i = 0; i < 20; i = i + 1) {
-    print fib(i);
-}
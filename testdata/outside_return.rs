@@ -1 +0,0 @@
-return "Apples";
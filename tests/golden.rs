@@ -0,0 +1,131 @@
+//! Runs every script in `testdata/` and checks it against the expectations annotated in its
+//! comments, similar to the test suite of the upstream Lox implementation:
+//! - `// expect: <line>` is a line the script is expected to print, in the order of the comments
+//! - `// expect error: <message>` is part of the message of the error the script is expected to
+//!   fail with
+use malis::{Malis, SharedBuffer};
+use std::fs;
+use std::panic::{self, AssertUnwindSafe};
+use std::path::Path;
+
+const EXPECT: &str = "// expect: ";
+const EXPECT_ERROR: &str = "// expect error: ";
+
+// Expectations gathered from the comments of a script
+#[derive(Debug, Default)]
+struct Expectations {
+    output: Vec<String>,
+    errors: Vec<String>,
+}
+
+impl Expectations {
+    fn parse(source: &str) -> Self {
+        let mut expectations = Self::default();
+
+        for line in source.lines() {
+            if let Some(idx) = line.find(EXPECT) {
+                expectations
+                    .output
+                    .push(line[idx + EXPECT.len()..].to_string());
+            } else if let Some(idx) = line.find(EXPECT_ERROR) {
+                expectations
+                    .errors
+                    .push(line[idx + EXPECT_ERROR.len()..].to_string());
+            }
+        }
+        expectations
+    }
+}
+
+// Runs the script at `path` and returns a report of every expectation that was not met
+fn check_script(path: &Path) -> Result<(), String> {
+    let source = fs::read_to_string(path).map_err(|e| format!("cannot read script: {e}"))?;
+    let expectations = Expectations::parse(&source);
+
+    let output = SharedBuffer::new();
+    let mut malis = Malis::with_output(output.clone(), SharedBuffer::new())
+        .map_err(|e| format!("cannot create interpreter: {e}"))?;
+
+    // A panic in one script should not stop us from checking the others
+    let result = panic::catch_unwind(AssertUnwindSafe(|| malis.run(&source, false)))
+        .map_err(|_| "interpreter panicked".to_string())?;
+
+    let mut report = vec![];
+    let actual = output.contents();
+    let actual = actual.lines().collect::<Vec<_>>();
+
+    if actual != expectations.output {
+        report.push("output differs (- expected, + actual):".to_string());
+        for idx in 0..actual.len().max(expectations.output.len()) {
+            let expected = expectations.output.get(idx).map(String::as_str);
+            let got = actual.get(idx).copied();
+            if expected == got {
+                report.push(format!("    {}", got.unwrap_or_default()));
+                continue;
+            }
+            if let Some(expected) = expected {
+                report.push(format!("  - {expected}"));
+            }
+            if let Some(got) = got {
+                report.push(format!("  + {got}"));
+            }
+        }
+    }
+
+    match result {
+        Ok(()) if !expectations.errors.is_empty() => {
+            report.push(format!(
+                "expected error(s) {:?}, but the script succeeded",
+                expectations.errors
+            ));
+        }
+        Ok(()) => {}
+        Err(err) => {
+            let message = err.to_string();
+            if expectations.errors.is_empty() {
+                report.push(format!("unexpected error: {message}"));
+            }
+            for expected in expectations.errors.iter() {
+                if !message.contains(expected.as_str()) {
+                    report.push(format!(
+                        "expected error containing {expected:?}, got: {message}"
+                    ));
+                }
+            }
+        }
+    }
+
+    if report.is_empty() {
+        Ok(())
+    } else {
+        Err(report.join("\n"))
+    }
+}
+
+#[test]
+fn golden_scripts() {
+    let mut scripts = fs::read_dir("testdata")
+        .expect("Failed to read the test data directory")
+        .map(|entry| entry.expect("Failed to read directory entry").path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "ms"))
+        .collect::<Vec<_>>();
+    scripts.sort();
+    assert!(!scripts.is_empty(), "No scripts found in testdata");
+
+    let failures = scripts
+        .iter()
+        .filter_map(|path| {
+            check_script(path)
+                .err()
+                .map(|report| format!("{}:\n{report}", path.display()))
+        })
+        .collect::<Vec<_>>();
+
+    assert!(
+        failures.is_empty(),
+        "{} of {} scripts failed\n\n{}",
+        failures.len(),
+        scripts.len(),
+        failures.join("\n\n")
+    );
+}